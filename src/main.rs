@@ -28,20 +28,21 @@ fn main() -> Result<(), eframe::Error> {
             if args[2] == "disk_upload" {
                 app.upload_ui.upload_type = UploadType::Disk;
             }
+            // Folders are shown in the folder upload window, where they can be previewed as projects
+            let mut folders = vec![];
             for arg in args.into_iter().skip(3) {
                 let path = PathBuf::from(arg);
                 if path.is_dir() {
-                    for file in path.read_dir().unwrap().flatten() {
-                        if file.path().is_file() {
-                            app.upload_ui.disk_upload_settings.file_paths.push(file.path());
-                            app.upload_ui.disk_upload_settings.file_names.push(String::new());
-                        }
-                    }
+                    folders.push(path);
                 } else if path.is_file() {
                     app.upload_ui.disk_upload_settings.file_paths.push(path);
                     app.upload_ui.disk_upload_settings.file_names.push(String::new());
                 }
             }
+            if !folders.is_empty() {
+                app.upload_ui.upload_type = UploadType::Disk;
+                app.upload_ui.add_folders(folders);
+            }
         }
     }
 
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use crate::modules::api::LoginResponse;
+use crate::modules::settings::{settings, update_settings};
+use crate::modules::vault;
+
+/// Represents a named MultiUp account.
+///
+/// The details returned by the last login are cached so the account can be used without logging in again.
+/// The password is kept in the credential vault.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct AccountProfile {
+    pub name: String,
+    pub username: String,
+    pub user_id: Option<u64>,
+    pub account_type: Option<String>,
+    pub premium_days_left: Option<String>,
+    pub favourite_hosts: Vec<String>,
+}
+
+impl AccountProfile {
+    /// Returns the cached login of the account.
+    pub fn login_response(&self) -> LoginResponse {
+        LoginResponse {
+            error: "success".to_string(),
+            login: Some(self.username.clone()),
+            user: self.user_id,
+            account_type: self.account_type.clone(),
+            premium_days_left: self.premium_days_left.clone(),
+        }
+    }
+
+    /// Caches the details of a successful login.
+    pub fn update_from(&mut self, login_response: &LoginResponse) {
+        self.user_id = login_response.user;
+        self.account_type = login_response.account_type.clone();
+        self.premium_days_left = login_response.premium_days_left.clone();
+    }
+
+    /// Returns the password saved in the vault, if it is unlocked.
+    pub fn password(&self) -> Option<String> {
+        vault::credentials()?.account_passwords.get(&self.username).cloned()
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct AccountSettings {
+    pub profiles: Vec<AccountProfile>,
+    /// The name of the profile selected in the Upload tab.
+    pub active: Option<String>,
+}
+
+pub fn profiles() -> Vec<AccountProfile> {
+    settings().accounts.profiles
+}
+
+pub fn profile(name: &str) -> Option<AccountProfile> {
+    profiles().into_iter().find(|profile| profile.name == name)
+}
+
+pub fn active_profile() -> Option<AccountProfile> {
+    profile(&settings().accounts.active?)
+}
+
+/// Adds the profile, replacing any profile with the same name.
+pub fn save_profile(profile: AccountProfile) -> Result<(), String> {
+    update_settings(|settings| {
+        match settings.accounts.profiles.iter_mut().find(|existing| existing.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => settings.accounts.profiles.push(profile),
+        }
+    })
+}
+
+pub fn remove_profile(name: &str) -> Result<(), String> {
+    update_settings(|settings| {
+        settings.accounts.profiles.retain(|profile| profile.name != name);
+        if settings.accounts.active.as_deref() == Some(name) {
+            settings.accounts.active = None;
+        }
+    })
+}
+
+pub fn set_active_profile(name: Option<String>) -> Result<(), String> {
+    update_settings(|settings| settings.accounts.active = name)
+}
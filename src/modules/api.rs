@@ -1,9 +1,10 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap};
-use crossbeam_channel::{Receiver, TryRecvError};
 use reqwest::{Client, multipart};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 use crate::modules::debrid::{DebridResponse, DebridService};
 use crate::modules::links::{DirectLink, DownloadLink, LinkError};
+use crate::modules::network::{self, Service};
 
 /// Represents information about a MultiUp link from the MultiUp API.
 /// Contains details such as the request status, file name, size (in bytes), upload and download dates,
@@ -40,8 +41,8 @@ impl MultiUpLinkInformation {
     }
 }
 
-pub async fn recheck_validity_api(mirror_link: String, mut download_link: DownloadLink, cancel_receiver: Receiver<bool>, client: Client) -> DownloadLink {
-    if let Ok(_) | Err(TryRecvError::Disconnected) = cancel_receiver.try_recv() {
+pub async fn recheck_validity_api(mirror_link: String, mut download_link: DownloadLink, cancellation_token: CancellationToken, client: Client) -> DownloadLink {
+    if cancellation_token.is_cancelled() {
         download_link.status = Some(Err(LinkError::Cancelled));
         return download_link;
     }
@@ -49,14 +50,23 @@ pub async fn recheck_validity_api(mirror_link: String, mut download_link: Downlo
     // let client = Client::new();
     let mut params = HashMap::new();
     params.insert("link", mirror_link);
-    let information = match client.post("https://multiup.io/api/check-file")
+    let request = client.post("https://multiup.io/api/check-file")
         .form(&params)
-        .send().await {
+        .send();
+    let response = tokio::select! {
+        response = request => response,
+        _ = cancellation_token.cancelled() => {
+            download_link.status = Some(Err(LinkError::Cancelled));
+            return download_link;
+        }
+    };
+    let information = match response {
         Ok(response) => {
             match response.json::<MultiUpLinkInformation>().await {
                 Ok(information) => information,
+                // An unreadable answer is not an answer from the API, e.g. an error page from a proxy
                 Err(error) => {
-                    download_link.status = Some(Err(LinkError::APIError(error.to_string())));
+                    download_link.status = Some(Err(LinkError::Reqwest(error)));
                     return download_link;
                 }
             }
@@ -91,16 +101,18 @@ pub async fn recheck_validity_api(mirror_link: String, mut download_link: Downlo
     download_link
 }
 
-#[derive(Clone, Default)]
+/// The password is never serialized, so it is not written to disk with queued uploads.
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct Login {
     pub username: String,
+    #[serde(skip)]
     pub password: String,
     pub user_id: Option<String>
 }
 
 impl Login {
     pub async fn login(&self) -> Result<LoginResponse, LinkError> {
-        let client = Client::new();
+        let client = network::client(Service::MultiUp).map_err(LinkError::APIError)?;
         let params = multipart::Form::new()
             .text("username", self.username.clone())
             .text("password", self.password.clone());
@@ -138,7 +150,7 @@ pub struct FastestServer {
 }
 
 pub async fn get_fastest_server() -> Result<String, LinkError> {
-    let response = match reqwest::get("https://multiup.io/api/get-fastest-server").await {
+    let response = match network::client(Service::Upload).map_err(LinkError::APIError)?.get("https://multiup.io/api/get-fastest-server").send().await {
         Ok(response) => {
             match response.json::<FastestServer>().await {
                 Ok(server) => server,
@@ -205,7 +217,7 @@ impl AddProject {
     }
 
     pub async fn add_project(&self) -> Result<AddProjectResponse, LinkError> {
-        let client = Client::new();
+        let client = network::client(Service::MultiUp).map_err(LinkError::APIError)?;
         let mut params = HashMap::new();
         params.insert("name", self.name.clone());
         if let Some(password) = &self.password {
@@ -258,7 +270,7 @@ pub struct AvailableHosts {
 
 impl AvailableHosts {
     pub async fn get() -> Result<Self, LinkError> {
-        match reqwest::get("https://multiup.io/api/get-list-hosts").await {
+        match network::client(Service::MultiUp).map_err(LinkError::APIError)?.get("https://multiup.io/api/get-list-hosts").send().await {
             Ok(response) => match response.json::<AvailableHosts>().await {
                 Ok(hosts) => Ok(hosts),
                 Err(error) => Err(LinkError::APIError(error.to_string())),
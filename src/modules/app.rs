@@ -1,9 +1,15 @@
 use eframe::{App, Frame};
 use eframe::egui::{CentralPanel, Context, menu, TopBottomPanel, Ui};
+use crate::modules::browser::BrowserSettingsUI;
 use crate::modules::extract::ExtractUI;
 use crate::modules::debrid::DebridUI;
 use crate::modules::upload::UploadUI;
 use crate::modules::help::HelpUI;
+use crate::modules::jobs::{job_manager, JobRerun, JobsUI};
+use crate::modules::network::NetworkSettingsUI;
+use crate::modules::settings::{settings, SettingsUI};
+use crate::modules::vault::{self, VaultUI};
+use crate::modules::watchlist::WatchlistUI;
 
 
 pub const HOMEPAGE: &str = "https://cs.rin.ru/forum/viewtopic.php?f=14&p=2822500#p2822500";
@@ -18,29 +24,75 @@ pub enum TabBar {
     #[default]
     Extract,
     Debrid,
-    Upload
+    Upload,
+    Watchlist
 }
 
 /// A struct representing the application UI.
 /// 
 /// Stores the state of each tab.
-#[derive(Default)]
 pub struct MultiUpDirect {
     pub tab_bar: TabBar,
     extract_ui: ExtractUI,
     debrid_ui: DebridUI,
     pub upload_ui: UploadUI,
+    watchlist_ui: WatchlistUI,
+    jobs_ui: JobsUI,
+    network_settings_ui: NetworkSettingsUI,
+    browser_settings_ui: BrowserSettingsUI,
+    settings_ui: SettingsUI,
+    vault_ui: VaultUI,
+    vault_unlocked: bool,
     help_ui: HelpUI,
 }
 
+impl Default for MultiUpDirect {
+    /// Creates the tabs with the defaults from the settings file.
+    fn default() -> Self {
+        let settings = settings();
+        Self {
+            tab_bar: TabBar::default(),
+            extract_ui: ExtractUI::new(&settings),
+            debrid_ui: DebridUI::new(&settings),
+            upload_ui: UploadUI::new(&settings),
+            watchlist_ui: WatchlistUI::default(),
+            jobs_ui: JobsUI::default(),
+            network_settings_ui: NetworkSettingsUI::default(),
+            browser_settings_ui: BrowserSettingsUI::default(),
+            settings_ui: SettingsUI::default(),
+            vault_ui: VaultUI::default(),
+            vault_unlocked: false,
+            help_ui: HelpUI::default(),
+        }
+    }
+}
+
 impl App for MultiUpDirect {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        self.update_vault(ctx);
+        self.upload_ui.update(ctx);
         MultiUpDirect::display_top_bar(self, ctx);
         MultiUpDirect::display_central_panel(self, ctx);
     }
 }
 
 impl MultiUpDirect {
+    /// Locks the vault once its timeout passes and passes its credentials to the tabs when it
+    /// is unlocked. Locking clears them from the tabs again.
+    fn update_vault(&mut self, ctx: &Context) {
+        if let Some(remaining) = vault::auto_lock() {
+            ctx.request_repaint_after(remaining);
+        }
+
+        let unlocked = vault::is_unlocked();
+        if unlocked != self.vault_unlocked {
+            let credentials = vault::credentials();
+            self.debrid_ui.set_credentials(credentials.as_ref());
+            self.upload_ui.set_credentials(credentials.as_ref());
+            self.vault_unlocked = unlocked;
+        }
+    }
+
     /// Displays the top bar.
     ///
     /// This method is responsible for rendering the top bar of the application, which includes
@@ -52,6 +104,11 @@ impl MultiUpDirect {
                 ui.selectable_value(&mut self.tab_bar, TabBar::Extract, "Extract");
                 ui.selectable_value(&mut self.tab_bar, TabBar::Debrid, "Debrid");
                 ui.selectable_value(&mut self.tab_bar, TabBar::Upload, "Upload");
+                let watchlist_text = match self.watchlist_ui.unread_alerts() {
+                    0 => "Watchlist".to_string(),
+                    alerts => format!("Watchlist ({})", alerts)
+                };
+                ui.selectable_value(&mut self.tab_bar, TabBar::Watchlist, watchlist_text);
 
                 // Menu bar/toolbar elements
                 self.menu_bar(ui);
@@ -65,6 +122,37 @@ impl MultiUpDirect {
     /// This method is responsible for adding toolbar functionality for different options.
     fn menu_bar(&mut self, ui: &mut Ui) {
         menu::bar(ui, |ui| {
+            let jobs_text = match job_manager().running_jobs() {
+                0 => "Jobs".to_string(),
+                running => format!("Jobs ({} running)", running)
+            };
+            if ui.button(jobs_text).clicked() {
+                self.jobs_ui.show_window = true;
+            }
+
+            ui.menu_button("Settings", |ui| {
+                if ui.button("General").clicked() {
+                    self.settings_ui.show_window = true;
+                    ui.close_menu();
+                }
+                if ui.button("Network").clicked() {
+                    self.network_settings_ui.show_window = true;
+                    ui.close_menu();
+                }
+                if ui.button("Browser").clicked() {
+                    self.browser_settings_ui.show_window = true;
+                    ui.close_menu();
+                }
+                let vault_text = match vault::is_unlocked() {
+                    true => "Credential vault (unlocked)",
+                    false => "Credential vault"
+                };
+                if ui.button(vault_text).clicked() {
+                    self.vault_ui.show_window = true;
+                    ui.close_menu();
+                }
+            });
+
             ui.menu_button("Help", |ui| {
                 if ui.button("Show help").clicked() {
                     self.help_ui.show_help = true;
@@ -90,10 +178,31 @@ impl MultiUpDirect {
     /// The central panel must be added last.
     fn display_central_panel(&mut self, ctx: &Context) {
         CentralPanel::default().show(ctx, |ui| {
+            self.extract_ui.set_login(self.upload_ui.login().clone());
+            // Every toaster shares the same storage, so alerts are shown by the selected tab's toaster
+            let mut alerts = self.watchlist_ui.display_alerts(ctx);
+            alerts.extend(self.upload_ui.take_remote_alerts());
             match &self.tab_bar {
-                TabBar::Extract => ExtractUI::display(ctx, ui, &mut self.extract_ui),
-                TabBar::Debrid => DebridUI::display(ctx, ui, &mut self.debrid_ui),
-                TabBar::Upload => UploadUI::display(ctx, ui, &mut self.upload_ui)
+                TabBar::Extract => ExtractUI::display(ctx, ui, &mut self.extract_ui, alerts),
+                TabBar::Debrid => DebridUI::display(ctx, ui, &mut self.debrid_ui, alerts),
+                TabBar::Upload => UploadUI::display(ctx, ui, &mut self.upload_ui, alerts),
+                TabBar::Watchlist => WatchlistUI::display(ctx, ui, &mut self.watchlist_ui, alerts)
+            }
+
+            self.watchlist_ui.add_links(self.extract_ui.take_watchlist_entries());
+
+            self.jobs_ui.show_jobs_window(ctx);
+            self.network_settings_ui.show_network_window(ctx);
+            self.browser_settings_ui.show_browser_window(ctx);
+            self.settings_ui.show_settings_window(ctx);
+            self.vault_ui.show_vault_window(ctx);
+            for rerun in self.jobs_ui.take_reruns() {
+                match rerun {
+                    JobRerun::Extract(input_text) => self.extract_ui.rerun_extraction(input_text),
+                    JobRerun::Unlock(links) => self.debrid_ui.rerun_unlocking(links),
+                    JobRerun::Upload(request) => self.upload_ui.rerun_upload(*request),
+                    JobRerun::WatchlistCheck => self.watchlist_ui.check_now(),
+                }
             }
 
             ExtractUI::display_error_log(&mut self.extract_ui, ctx);
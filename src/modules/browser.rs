@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use crossbeam_channel::Receiver;
+use eframe::egui::{Button, Context, DragValue, TextEdit, Ui, Window};
+use egui_file::FileDialog;
+use serde::{Deserialize, Serialize};
+use crate::modules::general::new_browser;
+use crate::modules::jobs::{JobKind, JobStatus, spawn_job};
+use crate::modules::settings::{settings, update_settings};
+
+/// Represents how Chrome is launched to load MultiUp pages.
+///
+/// Empty paths use the defaults of `headless_chrome`: the Chrome executable found on the system
+/// and a temporary profile which is deleted when the browser closes.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BrowserSettings {
+    pub headless: bool,
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub user_data_dir: String,
+    /// Extra launch arguments, one per line.
+    #[serde(default)]
+    pub extra_args: String,
+    /// How long to wait for a page to load, in seconds.
+    #[serde(default = "default_timeout")]
+    pub page_timeout: u64,
+    /// How long the browser may stay idle before it is closed, in seconds.
+    #[serde(default = "default_timeout")]
+    pub idle_timeout: u64,
+}
+
+fn default_timeout() -> u64 {
+    60
+}
+
+impl Default for BrowserSettings {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            path: String::new(),
+            user_data_dir: String::new(),
+            extra_args: String::new(),
+            page_timeout: default_timeout(),
+            idle_timeout: default_timeout(),
+        }
+    }
+}
+
+impl BrowserSettings {
+    pub fn path(&self) -> Option<PathBuf> {
+        Some(PathBuf::from(self.path.trim())).filter(|_| !self.path.trim().is_empty())
+    }
+
+    pub fn user_data_dir(&self) -> Option<PathBuf> {
+        Some(PathBuf::from(self.user_data_dir.trim())).filter(|_| !self.user_data_dir.trim().is_empty())
+    }
+
+    pub fn extra_args(&self) -> Vec<&str> {
+        self.extra_args.lines()
+            .map(|arg| arg.trim())
+            .filter(|arg| !arg.is_empty())
+            .collect()
+    }
+
+    pub fn page_timeout(&self) -> Duration {
+        Duration::from_secs(self.page_timeout)
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout)
+    }
+}
+
+/// Returns a copy of the current browser settings.
+pub fn browser_settings() -> BrowserSettings {
+    settings().browser
+}
+
+/// Replaces the current browser settings and saves them.
+pub fn set_browser_settings(browser: BrowserSettings) -> Result<(), String> {
+    update_settings(|settings| settings.browser = browser)
+}
+
+#[derive(Default)]
+pub struct BrowserSettingsUI {
+    pub show_window: bool,
+    settings: Option<BrowserSettings>,
+    error: Option<String>,
+    path_dialog: Option<FileDialog>,
+    user_data_dir_dialog: Option<FileDialog>,
+    test_receiver: Option<Receiver<Result<String, String>>>,
+    test_result: Option<Result<String, String>>,
+}
+
+impl BrowserSettingsUI {
+    pub fn show_browser_window(&mut self, ctx: &Context) {
+        if let Some(receiver) = &self.test_receiver {
+            if let Ok(result) = receiver.try_recv() {
+                self.test_result = Some(result);
+                self.test_receiver = None;
+            }
+        }
+
+        let mut show_window = self.show_window;
+        Window::new("Browser Settings")
+            .default_width(500.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let settings = self.settings.get_or_insert_with(browser_settings);
+
+                ui.checkbox(&mut settings.headless, "Run Chrome headless (without a window)");
+
+                display_path_setting(ui, ctx, "Chrome path:", "Detect automatically", &mut settings.path, &mut self.path_dialog, || FileDialog::open_file(None));
+                display_path_setting(ui, ctx, "Profile directory:", "Temporary profile", &mut settings.user_data_dir, &mut self.user_data_dir_dialog, || FileDialog::select_folder(None));
+                ui.label("A persistent profile keeps cookies and logins between extractions.");
+
+                ui.label("Extra launch arguments (one per line):");
+                ui.add(TextEdit::multiline(&mut settings.extra_args)
+                    .hint_text("--lang=en-GB")
+                    .desired_rows(3)
+                    .desired_width(ui.available_width()));
+
+                ui.horizontal(|ui| {
+                    ui.label("Page load timeout (s):");
+                    ui.add(DragValue::new(&mut settings.page_timeout).clamp_range(5..=600));
+                    ui.label("Idle timeout (s):");
+                    ui.add(DragValue::new(&mut settings.idle_timeout).clamp_range(10..=3600));
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        self.error = set_browser_settings(settings.clone()).err();
+                    }
+                    if ui.button("Revert").clicked() {
+                        *settings = browser_settings();
+                        self.error = None;
+                    }
+                    if ui.add_enabled(self.test_receiver.is_none(), Button::new("Test launch")).clicked() {
+                        let (test_sender, test_receiver) = crossbeam_channel::unbounded();
+                        self.test_receiver = Some(test_receiver);
+                        self.test_result = None;
+                        spawn_job(JobKind::BrowserTest, "Test Chrome launch with the saved settings".to_string(), None, |_| async move {
+                            let result = match new_browser() {
+                                Ok(browser) => browser.get_version()
+                                    .map(|version| version.product)
+                                    .map_err(|error| error.to_string()),
+                                Err(error) => Err(error.message()),
+                            };
+                            let status = match &result {
+                                Ok(version) => JobStatus::Completed(version.clone()),
+                                Err(error) => JobStatus::Failed(error.clone())
+                            };
+                            let _ = test_sender.send(result);
+                            status
+                        });
+                    }
+                    if self.test_receiver.is_some() {
+                        ui.spinner();
+                    }
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                match &self.test_result {
+                    Some(Ok(version)) => {
+                        ui.label(format!("Launched {}", version));
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    None => {}
+                }
+            });
+        self.show_window = show_window;
+    }
+}
+
+fn display_path_setting(ui: &mut Ui, ctx: &Context, label: &str, hint: &str, path: &mut String, dialog: &mut Option<FileDialog>, new_dialog: impl FnOnce() -> FileDialog) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(TextEdit::singleline(path)
+            .hint_text(hint)
+            .desired_width(300.0));
+        if ui.button("Browse").clicked() {
+            let mut file_dialog = new_dialog();
+            file_dialog.open();
+            *dialog = Some(file_dialog);
+        }
+    });
+
+    if let Some(file_dialog) = dialog {
+        if file_dialog.show(ctx).selected() {
+            if let Some(selected_path) = file_dialog.path() {
+                *path = selected_path.to_string_lossy().to_string();
+            }
+            *dialog = None;
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::Local;
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+/// Represents the checksums of a file's contents.
+#[derive(Clone, Debug)]
+pub struct FileHashes {
+    pub crc32: u32,
+    pub md5: String,
+    pub sha256: String,
+}
+
+/// Computes the checksums of a file as its chunks are read.
+#[derive(Clone, Default)]
+pub struct Hasher {
+    crc32: crc32fast::Hasher,
+    md5: Md5,
+    sha256: Sha256,
+    bytes: u64,
+}
+
+impl Hasher {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.crc32.update(chunk);
+        self.md5.update(chunk);
+        self.sha256.update(chunk);
+        self.bytes += chunk.len() as u64;
+    }
+
+    /// The number of bytes hashed so far.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn hashes(&self) -> FileHashes {
+        let hasher = self.clone();
+        FileHashes {
+            crc32: hasher.crc32.finalize(),
+            md5: format!("{:x}", hasher.md5.finalize()),
+            sha256: format!("{:x}", hasher.sha256.finalize()),
+        }
+    }
+}
+
+/// Represents the result of comparing a file's checksums with the hash returned by MultiUp.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HashCheck {
+    /// The returned hash matches the named checksum.
+    Matched(&'static str),
+    Mismatched {
+        algorithm: &'static str,
+        expected: String,
+        actual: String,
+    },
+    /// MultiUp returned no hash, or a hash in an unknown format.
+    Unavailable,
+}
+
+impl HashCheck {
+    /// Compares the checksums with the returned hash, which is picked out as MD5 or SHA-256 by its length.
+    pub fn check(hashes: &FileHashes, returned: Option<&str>) -> Self {
+        let Some(returned) = returned.map(|hash| hash.trim().to_lowercase()).filter(|hash| !hash.is_empty()) else {
+            return HashCheck::Unavailable;
+        };
+        let (algorithm, actual) = match returned.len() {
+            32 => ("MD5", &hashes.md5),
+            64 => ("SHA-256", &hashes.sha256),
+            _ => return HashCheck::Unavailable
+        };
+        match &returned == actual {
+            true => HashCheck::Matched(algorithm),
+            false => HashCheck::Mismatched {
+                algorithm,
+                expected: returned,
+                actual: actual.clone(),
+            }
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            HashCheck::Matched(algorithm) => format!("{} matches", algorithm),
+            HashCheck::Mismatched { algorithm, .. } => format!("{} mismatch", algorithm),
+            HashCheck::Unavailable => "Not verified".to_string(),
+        }
+    }
+}
+
+/// Represents the formats of checksum manifests.
+#[derive(Clone, Copy)]
+pub enum ManifestKind {
+    Sfv,
+    Md5,
+    Sha256,
+}
+
+impl ManifestKind {
+    pub const ALL: [ManifestKind; 3] = [ManifestKind::Sfv, ManifestKind::Md5, ManifestKind::Sha256];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ManifestKind::Sfv => "sfv",
+            ManifestKind::Md5 => "md5",
+            ManifestKind::Sha256 => "sha256",
+        }
+    }
+
+    /// Returns the contents of the manifest, in the format read by QuickSFV, `md5sum -c` and `sha256sum -c`.
+    pub fn manifest(&self, files: &[(String, FileHashes)]) -> String {
+        let mut contents = match self {
+            ManifestKind::Sfv => format!("; Generated by MultiUp-Direct on {}\n", Local::now().format("%Y-%m-%d %H:%M:%S")),
+            _ => String::new(),
+        };
+        for (name, hashes) in files {
+            contents += &match self {
+                ManifestKind::Sfv => format!("{} {:08X}\n", name, hashes.crc32),
+                ManifestKind::Md5 => format!("{}  {}\n", hashes.md5, name),
+                ManifestKind::Sha256 => format!("{}  {}\n", hashes.sha256, name),
+            };
+        }
+        contents
+    }
+}
+
+/// Writes an SFV, MD5 and SHA-256 manifest named after the base name into the directory.
+///
+/// Returns the paths of the manifests.
+pub fn write_manifests(directory: &Path, base_name: &str, files: &[(String, FileHashes)]) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(directory)?;
+    ManifestKind::ALL.iter()
+        .map(|kind| {
+            let path = directory.join(format!("{}.{}", base_name, kind.extension()));
+            fs::write(&path, kind.manifest(files))?;
+            Ok(path)
+        })
+        .collect()
+}
@@ -0,0 +1,298 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use crossbeam_channel::{Receiver, Sender};
+use eframe::egui::{Button, Context, ScrollArea, TextEdit, Window};
+use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use crate::modules::api::{AddProject, Login};
+use crate::modules::jobs::{Job, JobKind, JobStatus, spawn_job};
+use crate::modules::links::{LinkError, ProjectLink};
+use crate::modules::replenish::{best_direct_link, HostSelection, reupload_direct_link};
+use crate::modules::settings::settings;
+
+/// Represents a file in a project which is to be cloned.
+/// Contains the original MultiUp link, the file name and the valid direct link to upload from, if any.
+#[derive(Clone, Debug)]
+pub struct CloneFile {
+    pub link: String,
+    pub file_name: String,
+    pub source_url: Option<String>,
+}
+
+/// Represents a MultiUp project which is to be cloned into the user's account.
+#[derive(Clone, Debug)]
+pub struct CloneTarget {
+    pub link: String,
+    pub name: String,
+    pub files: Vec<CloneFile>,
+}
+
+impl CloneTarget {
+    pub fn new(project_link: &ProjectLink) -> Self {
+        let mut files: Vec<CloneFile> = project_link.download_links.iter()
+            .flatten()
+            .map(|download_link| CloneFile {
+                link: download_link.original_link.clone(),
+                file_name: download_link.link_information.as_ref()
+                    .and_then(|information| information.file_name.clone())
+                    .unwrap_or_default(),
+                source_url: best_direct_link(download_link).map(|(_, url)| url),
+            })
+            .collect();
+        files.sort_by(|file_1, file_2| file_1.file_name.cmp(&file_2.file_name));
+
+        Self {
+            link: project_link.original_link.clone(),
+            name: project_link.name.clone(),
+            files,
+        }
+    }
+}
+
+/// Represents an update on the progress of a project clone.
+pub enum CloneUpdate {
+    ProjectCreated(Result<String, LinkError>),
+    FileUploaded(String, Result<String, LinkError>),
+    Finished,
+}
+
+/// Creates a new project and remote uploads every file of the target into it.
+///
+/// Updates are sent as each step completes. Files are uploaded concurrently from their best valid direct link.
+async fn clone_project(target: CloneTarget, project: AddProject, login: Login, hosts: HashSet<String>, update_sender: Sender<CloneUpdate>, job: Arc<Job>) -> JobStatus {
+    let project_hash = match project.add_project().await {
+        Ok(response) => match response.hash {
+            Some(hash) => hash,
+            None => {
+                let status = JobStatus::Failed(format!("Failed to create project: {}", response.error));
+                let _ = update_sender.send(CloneUpdate::ProjectCreated(Err(LinkError::APIError(response.error))));
+                let _ = update_sender.send(CloneUpdate::Finished);
+                return status;
+            }
+        },
+        Err(error) => {
+            let status = JobStatus::Failed(format!("Failed to create project: {:?}", error));
+            let _ = update_sender.send(CloneUpdate::ProjectCreated(Err(error)));
+            let _ = update_sender.send(CloneUpdate::Finished);
+            return status;
+        }
+    };
+    let _ = update_sender.send(CloneUpdate::ProjectCreated(Ok(project_hash.clone())));
+
+    let semaphore = Arc::new(Semaphore::new(settings().extract.clone_concurrency.max(1)));
+    let mut tasks = vec![];
+    job.set_total(target.files.len());
+    for file in target.files {
+        let semaphore = Arc::clone(&semaphore);
+        let login = login.clone();
+        let hosts = hosts.clone();
+        let project_hash = project_hash.clone();
+        let update_sender = update_sender.clone();
+        let job = Arc::clone(&job);
+        let task = tokio::spawn(async move {
+            let upload = async {
+                let _permit = semaphore.acquire().await.unwrap();
+                match &file.source_url {
+                    Some(url) => reupload_direct_link(url, &file.file_name, login, hosts, Some(project_hash)).await,
+                    None => Err(LinkError::NoLinks)
+                }
+            };
+            let result = tokio::select! {
+                result = upload => result,
+                _ = job.cancellation_token.cancelled() => Err(LinkError::Cancelled)
+            };
+            let uploaded = result.is_ok();
+            let _ = update_sender.send(CloneUpdate::FileUploaded(file.link, result));
+            job.advance();
+            uploaded
+        });
+        tasks.push(task);
+    }
+
+    let uploaded = futures::future::join_all(tasks).await.into_iter()
+        .filter(|result| matches!(result, Ok(true)))
+        .count();
+    let _ = update_sender.send(CloneUpdate::Finished);
+    JobStatus::Completed(format!("{}/{} files uploaded", uploaded, job.progress().1))
+}
+
+#[derive(Default)]
+pub struct CloneProjectUI {
+    pub login: Login,
+    show_window: bool,
+    target: Option<CloneTarget>,
+    name: String,
+    password: String,
+    description: String,
+    hosts: HostSelection,
+    cloning: bool,
+    update_receiver: Option<Receiver<CloneUpdate>>,
+    cancellation_token: Option<CancellationToken>,
+    project_hash: Option<String>,
+    results: Vec<(String, Result<String, LinkError>)>,
+}
+
+impl CloneProjectUI {
+    pub fn open(&mut self, target: CloneTarget) {
+        if self.cloning {
+            return;
+        }
+        self.name = target.name.clone();
+        self.password = String::new();
+        self.description = String::new();
+        self.project_hash = None;
+        self.results = vec![];
+        self.target = Some(target);
+        self.show_window = true;
+    }
+
+    pub fn show_clone_window(&mut self, ctx: &Context, toasts: &mut Toasts) {
+        self.receive_updates(toasts);
+
+        let mut show_window = self.show_window;
+        Window::new("Clone Project")
+            .default_width(600.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let Some(target) = self.target.clone() else {
+                    ui.label("Right-click a project in the extracted links to clone it.");
+                    return;
+                };
+
+                let uploadable_files = target.files.iter().filter(|file| file.source_url.is_some()).count();
+                ui.label(format!("Project: {} ({})", target.name, target.link));
+                ui.label(format!("{}/{} files have a valid direct link to upload from", uploadable_files, target.files.len()));
+                let user = match &self.login.user_id {
+                    Some(_) => self.login.username.clone(),
+                    None => "Anonymous".to_string()
+                };
+                ui.label(format!("Cloning into: {} (log in from the Upload tab to change)", user));
+
+                ui.add_enabled_ui(!self.cloning, |ui| {
+                    ui.add(TextEdit::singleline(&mut self.name)
+                        .hint_text("Enter project name"));
+                    ui.add(TextEdit::singleline(&mut self.password)
+                        .hint_text("Enter new project password (optional)"));
+                    ui.add(TextEdit::singleline(&mut self.description)
+                        .hint_text("Enter new project description (optional)"));
+                });
+
+                ui.separator();
+                self.hosts.show(ui, "Clone Project Hosts");
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.cloning, Button::new("Clone project")).clicked() {
+                        self.cloning = true;
+                        self.project_hash = None;
+                        self.results = vec![];
+                        let (update_sender, update_receiver) = crossbeam_channel::unbounded();
+                        self.update_receiver = Some(update_receiver);
+
+                        let project = AddProject::new(
+                            self.name.clone(),
+                            Some(self.password.clone()).filter(|password| !password.is_empty()),
+                            Some(self.description.clone()).filter(|description| !description.is_empty()),
+                            self.login.user_id.clone()
+                        );
+                        let login = self.login.clone();
+                        let hosts = self.hosts.selected();
+                        let target = target.clone();
+                        let description = format!("Clone {}", target.name);
+                        let job = spawn_job(JobKind::CloneProject, description, None, |job| {
+                            clone_project(target, project, login, hosts, update_sender, job)
+                        });
+                        self.cancellation_token = Some(job.cancellation_token.clone());
+                    }
+
+                    if self.cloning {
+                        ui.spinner();
+                        ui.label(format!("Cloning... {}/{} files done", self.results.len(), target.files.len()));
+                        if ui.button("Cancel").clicked() {
+                            if let Some(cancellation_token) = self.cancellation_token.take() {
+                                cancellation_token.cancel();
+                            }
+                        }
+                    }
+                });
+
+                if let Some(hash) = &self.project_hash {
+                    ui.label(format!("New project: https://multiup.io/en/project/{}", hash));
+                }
+
+                if !self.results.is_empty() {
+                    let failed = self.results.iter().filter(|(_, result)| result.is_err()).count();
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} uploaded, {} failed", self.results.len() - failed, failed));
+                        if ui.button("Copy link mapping").clicked() {
+                            ui.output_mut(|output| output.copied_text = self.link_mapping());
+                        }
+                    });
+
+                    ScrollArea::vertical()
+                        .id_source("Clone Project Results")
+                        .max_height(ui.available_height())
+                        .show(ui, |ui| {
+                            for (old_link, result) in &self.results {
+                                match result {
+                                    Ok(new_link) => ui.label(format!("{} -> {}", old_link, new_link)),
+                                    Err(error) => ui.label(format!("{} -> Failed: {:?}", old_link, error)),
+                                };
+                            }
+                        });
+                }
+            });
+        self.show_window = show_window;
+    }
+
+    /// Returns the mapping from old links to new links, one pair per line.
+    fn link_mapping(&self) -> String {
+        let mut mapping = String::new();
+        if let (Some(target), Some(hash)) = (&self.target, &self.project_hash) {
+            mapping += &format!("{} -> https://multiup.io/en/project/{}\n", target.link, hash);
+        }
+        for (old_link, result) in &self.results {
+            if let Ok(new_link) = result {
+                mapping += &format!("{} -> {}\n", old_link, new_link);
+            }
+        }
+        mapping
+    }
+
+    fn receive_updates(&mut self, toasts: &mut Toasts) {
+        let Some(receiver) = &self.update_receiver else {
+            return;
+        };
+
+        while let Ok(update) = receiver.try_recv() {
+            match update {
+                CloneUpdate::ProjectCreated(Ok(hash)) => self.project_hash = Some(hash),
+                CloneUpdate::ProjectCreated(Err(error)) => {
+                    toasts.add(Toast {
+                        text: format!("Failed to create project: {:?}", error).into(),
+                        kind: ToastKind::Error,
+                        options: ToastOptions::default()
+                            .duration_in_seconds(10.0)
+                            .show_progress(true)
+                            .show_icon(true)
+                    });
+                }
+                CloneUpdate::FileUploaded(link, result) => self.results.push((link, result)),
+                CloneUpdate::Finished => {
+                    self.cloning = false;
+                    if self.project_hash.is_some() {
+                        toasts.add(Toast {
+                            text: "Finished cloning project".into(),
+                            kind: ToastKind::Success,
+                            options: ToastOptions::default()
+                                .duration_in_seconds(5.0)
+                                .show_progress(true)
+                                .show_icon(true)
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
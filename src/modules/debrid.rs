@@ -1,16 +1,20 @@
-use std::{fs, thread};
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 use crossbeam_channel::Receiver;
 use eframe::egui;
-use eframe::egui::{Align2, ComboBox, Context, Id, ScrollArea, TextEdit, Ui, Window};
+use eframe::egui::{Align2, Button, ComboBox, Context, Id, ScrollArea, TextEdit, Ui};
 use eframe::egui::Direction::TopDown;
 use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
 use regex::Regex;
-use reqwest::Client;
-use serde::Deserialize;
-use tokio::runtime::Runtime;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 use crate::modules::api::{AllDebridResponse, RealDebridResponse, unlock_links};
+use crate::modules::error_log::{ErrorLog, ErrorStage};
+use crate::modules::general::add_alerts;
+use crate::modules::jobs::{JobKind, JobRerun, JobStatus, spawn_job};
 use crate::modules::links::{LinkError};
+use crate::modules::network::{self, Service};
+use crate::modules::settings::Settings;
+use crate::modules::vault::{self, Credentials};
 
 pub enum DebridResponse {
     AllDebrid(Result<AllDebridResponse, LinkError>),
@@ -19,24 +23,49 @@ pub enum DebridResponse {
 
 #[derive(Default)]
 struct Channels {
-    pub debrid: Option<Receiver<Vec<DebridResponse>>>
+    pub debrid: Option<Receiver<Vec<DebridResponse>>>,
+    pub cancel: Option<CancellationToken>
 }
 
-#[derive(Clone, Default, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub enum DebridService {
     #[default]
     AllDebrid,
     RealDebrid
 }
 
-#[derive(Clone, Default, Deserialize)]
+impl DebridService {
+    pub fn name(&self) -> &str {
+        match self {
+            DebridService::AllDebrid => "AllDebrid",
+            DebridService::RealDebrid => "RealDebrid"
+        }
+    }
+}
+
+#[derive(Clone, Default)]
 struct DebridAPIKeys {
-    #[serde(default)]
     all_debrid: String,
-    #[serde(default)]
     real_debrid: String
 }
 
+impl DebridAPIKeys {
+    /// Returns the plain-text keys saved by older versions, which are kept until a vault is created.
+    fn from_settings(settings: &Settings) -> Self {
+        Self {
+            all_debrid: settings.debrid.all_debrid_api_key.clone(),
+            real_debrid: settings.debrid.real_debrid_api_key.clone(),
+        }
+    }
+
+    fn from_credentials(credentials: &Credentials) -> Self {
+        Self {
+            all_debrid: credentials.all_debrid_api_key.clone(),
+            real_debrid: credentials.real_debrid_api_key.clone(),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct DebridUI {
     toasts: Toasts,
@@ -49,14 +78,31 @@ pub struct DebridUI {
     unlocking: bool,
     debrid_links: String,
     error_log_open: bool,
-    error_log_text: String,
+    error_log: ErrorLog,
+    retrying: bool,
 }
 
 impl DebridUI {
-    pub fn display(ctx: &Context, ui: &mut Ui, debrid_ui: &mut DebridUI) {
+    /// Creates the tab with the defaults from the settings.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            debrid_service: settings.debrid.service.clone(),
+            api_key: DebridAPIKeys::from_settings(settings),
+            use_remote_traffic: settings.debrid.use_remote_traffic,
+            ..Default::default()
+        }
+    }
+
+    /// Fills in the API keys from the unlocked vault, or clears them when it locks.
+    pub fn set_credentials(&mut self, credentials: Option<&Credentials>) {
+        self.api_key = credentials.map(DebridAPIKeys::from_credentials).unwrap_or_default();
+    }
+
+    pub fn display(ctx: &Context, ui: &mut Ui, debrid_ui: &mut DebridUI, alerts: Vec<String>) {
         debrid_ui.toasts = Toasts::new()
             .anchor(Align2::RIGHT_TOP, (10.0, 10.0))
             .direction(TopDown);
+        add_alerts(&mut debrid_ui.toasts, alerts);
 
         debrid_ui.display_input_area(ui);
         debrid_ui.display_debrid_links_area(ui);
@@ -68,10 +114,7 @@ impl DebridUI {
         ui.horizontal(|ui| {
             ui.label("Choose Debrid service:");
             ComboBox::from_id_source("Upload Type")
-                .selected_text(match self.debrid_service {
-                    DebridService::AllDebrid => "AllDebrid",
-                    DebridService::RealDebrid => "RealDebrid"
-                })
+                .selected_text(self.debrid_service.name())
                 .show_ui(ui, |ui| {
                     ui.selectable_value(&mut self.debrid_service, DebridService::AllDebrid, "AllDebrid");
                     ui.selectable_value(&mut self.debrid_service, DebridService::RealDebrid, "RealDebrid");
@@ -91,74 +134,63 @@ impl DebridUI {
                 }
             };
 
-            if ui.button("Read from file").clicked() {
-                let api_key_json = fs::read_to_string("api_key.json");
-                match api_key_json {
-                    Ok(json_string) => {
-                        let api_key_result: Result<DebridAPIKeys, _> = serde_json::from_str(&json_string);
-                        match api_key_result {
-                            Ok(debrid_api_keys) => {
-                                self.api_key = debrid_api_keys;
-                                match self.debrid_service {
-                                    DebridService::AllDebrid => {
-                                        if self.api_key.all_debrid.is_empty() {
-                                            self.toasts.add(Toast {
-                                                text: "API key not found for AllDebrid".into(),
-                                                kind: ToastKind::Warning,
-                                                options: ToastOptions::default()
-                                                    .duration_in_seconds(5.0)
-                                                    .show_progress(true)
-                                                    .show_icon(true)
-                                            });
-                                        }
-                                    },
-                                    DebridService::RealDebrid => {
-                                        if self.api_key.real_debrid.is_empty() {
-                                            self.toasts.add(Toast {
-                                                text: "API key not found for RealDebrid".into(),
-                                                kind: ToastKind::Warning,
-                                                options: ToastOptions::default()
-                                                    .duration_in_seconds(5.0)
-                                                    .show_progress(true)
-                                                    .show_icon(true)
-                                            });
-                                        }
-                                    }
-                                }
-
-                                self.toasts.add(Toast {
-                                    text: "Successfully read API key".into(),
-                                    kind: ToastKind::Success,
-                                    options: ToastOptions::default()
-                                        .duration_in_seconds(5.0)
-                                        .show_progress(true)
-                                        .show_icon(true)
-                                });
-                            }
-                            Err(_) => {
-                                self.toasts.add(Toast {
-                                    text: "Failed to parse \"api_key.json\"".into(),
-                                    kind: ToastKind::Error,
-                                    options: ToastOptions::default()
-                                        .duration_in_seconds(5.0)
-                                        .show_progress(true)
-                                        .show_icon(true)
-                                });
-                            }
-                        }
+            if ui.button("Load saved key").clicked() {
+                let api_key = vault::credentials().map(|credentials| {
+                    self.api_key = DebridAPIKeys::from_credentials(&credentials);
+                    match self.debrid_service {
+                        DebridService::AllDebrid => credentials.all_debrid_api_key,
+                        DebridService::RealDebrid => credentials.real_debrid_api_key
                     }
-                    Err(_) => {
-                        self.toasts.add(Toast {
-                            text: "Failed to read \"api_key.json\"".into(),
-                            kind: ToastKind::Error,
-                            options: ToastOptions::default()
-                                .duration_in_seconds(5.0)
-                                .show_progress(true)
-                                .show_icon(true)
-                        });
-                    },
+                });
+                if api_key.is_none() {
+                    self.toasts.add(Toast {
+                        text: "Unlock the vault in Settings > Credential vault to load saved keys".into(),
+                        kind: ToastKind::Warning,
+                        options: ToastOptions::default()
+                            .duration_in_seconds(5.0)
+                            .show_progress(true)
+                            .show_icon(true)
+                    });
+                } else if api_key.is_some_and(|api_key| api_key.is_empty()) {
+                    self.toasts.add(Toast {
+                        text: format!("API key not found for {}", self.debrid_service.name()).into(),
+                        kind: ToastKind::Warning,
+                        options: ToastOptions::default()
+                            .duration_in_seconds(5.0)
+                            .show_progress(true)
+                            .show_icon(true)
+                    });
+                } else {
+                    self.toasts.add(Toast {
+                        text: "Successfully read API key".into(),
+                        kind: ToastKind::Success,
+                        options: ToastOptions::default()
+                            .duration_in_seconds(5.0)
+                            .show_progress(true)
+                            .show_icon(true)
+                    });
                 }
             }
+
+            if ui.button("Save key").clicked() {
+                let api_key = self.api_key.clone();
+                let saved = vault::update_credentials(|credentials| {
+                    credentials.all_debrid_api_key = api_key.all_debrid;
+                    credentials.real_debrid_api_key = api_key.real_debrid;
+                });
+                let (text, kind) = match saved {
+                    Ok(()) => ("Saved API key".to_string(), ToastKind::Success),
+                    Err(error) => (format!("Failed to save API key: {}", error), ToastKind::Error)
+                };
+                self.toasts.add(Toast {
+                    text: text.into(),
+                    kind,
+                    options: ToastOptions::default()
+                        .duration_in_seconds(5.0)
+                        .show_progress(true)
+                        .show_icon(true)
+                });
+            }
         });
 
         ui.heading("Input Links");
@@ -191,46 +223,19 @@ impl DebridUI {
         }
 
         ui.horizontal(|ui| {
-            if ui.button("Unlock links").clicked() {
-                self.unlocking = true;
-                let (debrid_sender, debrid_receiver) = crossbeam_channel::unbounded();
-                self.channels.debrid = Some(debrid_receiver);
-                self.input_links_vec = process_links(&self.input_links);
-                let links = self.input_links_vec.clone();
-                let debrid_service = self.debrid_service.clone();
-                let api_key = match self.debrid_service {
-                    DebridService::AllDebrid => self.api_key.all_debrid.clone(),
-                    DebridService::RealDebrid => self.api_key.real_debrid.clone()
-                };
-                let use_remote_traffic = self.use_remote_traffic;
-                let rt = Runtime::new().unwrap();
-                thread::spawn(move || {
-                    rt.block_on(async {
-                        let client = Client::new();
-                        let mut tasks = vec![];
-                        for link in links {
-                            let link = link.clone();
-                            let debrid_service = debrid_service.clone();
-                            let api_key = api_key.clone();
-                            let client = client.clone();
-                            let task = tokio::spawn(async move {
-                                unlock_links(&link, debrid_service, &api_key, use_remote_traffic, client).await
-                            });
-                            tasks.push(task);
-                        }
-                        let mut debrid_links = vec![];
-                        let results = futures::future::join_all(tasks).await;
-                        for result in results {
-                            debrid_links.push(result.unwrap());
-                        }
-                        debrid_sender.send(debrid_links).unwrap();
-                    });
-                });
+            if ui.add_enabled(!self.unlocking, Button::new("Unlock links")).clicked() {
+                self.retrying = false;
+                self.start_unlocking(process_links(&self.input_links));
             }
 
             if self.unlocking {
                 ui.spinner();
                 ui.label("Unlocking links...");
+                if ui.button("Cancel").clicked() {
+                    if let Some(cancellation_token) = self.channels.cancel.take() {
+                        cancellation_token.cancel();
+                    }
+                }
             }
 
             if ui.button("See errors").clicked() {
@@ -238,54 +243,122 @@ impl DebridUI {
             }
         });
     }
+    /// Starts unlocking the given links with the selected Debrid service.
+    fn start_unlocking(&mut self, links: Vec<String>) {
+        self.unlocking = true;
+        let (debrid_sender, debrid_receiver) = crossbeam_channel::unbounded();
+        self.channels.debrid = Some(debrid_receiver);
+        self.input_links_vec = links.clone();
+        let debrid_service = self.debrid_service.clone();
+        let api_key = match self.debrid_service {
+            DebridService::AllDebrid => self.api_key.all_debrid.clone(),
+            DebridService::RealDebrid => self.api_key.real_debrid.clone()
+        };
+        let use_remote_traffic = self.use_remote_traffic;
+        let service = match self.debrid_service {
+            DebridService::AllDebrid => Service::AllDebrid,
+            DebridService::RealDebrid => Service::RealDebrid
+        };
+        let description = format!("Unlock {} links", links.len());
+        let rerun = JobRerun::Unlock(links.clone());
+        let job = spawn_job(JobKind::Unlock, description, Some(rerun), |job| async move {
+            let client = match network::client(service) {
+                Ok(client) => client,
+                Err(error) => {
+                    let debrid_links = links.iter()
+                        .map(|_| match debrid_service {
+                            DebridService::AllDebrid => DebridResponse::AllDebrid(Err(LinkError::APIError(error.clone()))),
+                            DebridService::RealDebrid => DebridResponse::RealDebrid(Err(LinkError::APIError(error.clone()))),
+                        })
+                        .collect();
+                    let _ = debrid_sender.send(debrid_links);
+                    return JobStatus::Failed(error);
+                }
+            };
+            let mut tasks = vec![];
+            job.set_total(links.len());
+            for link in links {
+                let link = link.clone();
+                let debrid_service = debrid_service.clone();
+                let api_key = api_key.clone();
+                let client = client.clone();
+                let job = Arc::clone(&job);
+                let cancellation_token = job.cancellation_token.child_token();
+                let task = tokio::spawn(async move {
+                    let response = tokio::select! {
+                        response = unlock_links(&link, debrid_service.clone(), &api_key, use_remote_traffic, client) => response,
+                        _ = cancellation_token.cancelled() => match debrid_service {
+                            DebridService::AllDebrid => DebridResponse::AllDebrid(Err(LinkError::Cancelled)),
+                            DebridService::RealDebrid => DebridResponse::RealDebrid(Err(LinkError::Cancelled)),
+                        }
+                    };
+                    job.advance();
+                    response
+                });
+                tasks.push(task);
+            }
+            let mut debrid_links = vec![];
+            let results = futures::future::join_all(tasks).await;
+            for result in results {
+                debrid_links.push(result.unwrap());
+            }
+            let failed = debrid_links.iter()
+                .filter(|response| matches!(response, DebridResponse::AllDebrid(Err(_)) | DebridResponse::RealDebrid(Err(_))))
+                .count();
+            let status = JobStatus::Completed(format!("{} unlocked, {} failed", debrid_links.len() - failed, failed));
+            let _ = debrid_sender.send(debrid_links);
+            status
+        });
+        self.channels.cancel = Some(job.cancellation_token.clone());
+    }
+
+    /// Unlocks the given links again, unless links are already being unlocked.
+    pub fn rerun_unlocking(&mut self, links: Vec<String>) {
+        if !self.unlocking {
+            self.retrying = false;
+            self.start_unlocking(links);
+        }
+    }
+
     pub fn display_error_log(&mut self, ctx: &Context) {
-        Window::new("Debrid Error Log")
-            .default_width(200.0)
-            .open(&mut self.error_log_open)
-            .show(ctx, |ui| {
-                ScrollArea::vertical()
-                    .id_source("Error Log")
-                    .min_scrolled_height(ui.available_height())
-                    .show(ui, |ui| {
-                        let mut error = self.error_log_text.clone();
-                        ui.add(TextEdit::multiline(&mut error).desired_width(ui.available_width()));
-                    });
-            });
+        let retry = self.error_log.show(ctx, "Debrid Error Log", &mut self.error_log_open, !self.unlocking);
+        if retry {
+            self.retrying = true;
+            self.start_unlocking(self.error_log.failed_links());
+        }
     }
+
     fn display_debrid_links_area(&mut self, ui: &mut Ui) {
         ui.heading("Debrid Links");
 
         if let Some(receiver) = &self.channels.debrid {
             if let Ok(debrid_results) = receiver.try_recv() {
                 let mut links = String::new();
-                let mut errors = String::new();
+                if self.retrying {
+                    self.error_log.remove_links(&self.input_links_vec);
+                    links = format!("{}\n", self.debrid_links.trim());
+                } else {
+                    self.error_log.clear();
+                }
+
                 for (index, response) in debrid_results.iter().enumerate() {
-                    match response {
-                        DebridResponse::AllDebrid(result) => {
-                            match result {
-                                Ok(response) => {
-                                    links = format!("{}{}\n", links, response.data.link);
-                                },
-                                Err(error) => {
-                                    errors = format!("{}\n\n{} - {:?}", errors, self.input_links_vec[index], error);
-                                }
-                            }
-                        }
-                        DebridResponse::RealDebrid(result) => {
-                            match result {
-                                Ok(response) => {
-                                    links = format!("{}{}\n", links, response.link);
-                                },
-                                Err(error) => {
-                                    errors = format!("{}\n\n{} - {:?}", errors, self.input_links_vec[index], error);
-                                }
-                            }
+                    let result = match response {
+                        DebridResponse::AllDebrid(result) => result.as_ref().map(|response| &response.data.link),
+                        DebridResponse::RealDebrid(result) => result.as_ref().map(|response| &response.link),
+                    };
+                    match result {
+                        Ok(link) => {
+                            links = format!("{}{}\n", links, link);
+                        },
+                        Err(error) => {
+                            self.error_log.record(&self.input_links_vec[index], ErrorStage::Unlock, error);
                         }
                     }
                 }
-                self.debrid_links = links;
-                self.error_log_text = errors;
+                self.debrid_links = links.trim_start().to_string();
+                self.retrying = false;
                 self.unlocking = false;
+                self.channels.cancel = None;
             }
         }
 
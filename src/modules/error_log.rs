@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use chrono::{DateTime, Local};
+use eframe::egui::{Button, ComboBox, Context, Grid, ScrollArea, Window};
+use crate::modules::links::{LinkError, LinkErrorKind, MultiUpLink};
+
+/// Represents the stage of processing at which a link failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorStage {
+    Project,
+    Mirror,
+    Validity,
+    Unlock,
+}
+
+impl ErrorStage {
+    pub fn name(&self) -> &str {
+        match self {
+            ErrorStage::Project => "Project",
+            ErrorStage::Mirror => "Mirror page",
+            ErrorStage::Validity => "Validity check",
+            ErrorStage::Unlock => "Debrid unlock",
+        }
+    }
+}
+
+/// Represents a single failed link in the error log.
+/// Contains the link, the stage and kind of the error, a message,
+/// the time of the latest failure and the number of attempts made.
+#[derive(Clone, Debug)]
+pub struct ErrorEntry {
+    pub link: String,
+    pub stage: ErrorStage,
+    pub kind: LinkErrorKind,
+    pub message: String,
+    pub timestamp: DateTime<Local>,
+    pub attempts: u32,
+}
+
+#[derive(Default)]
+pub struct ErrorLog {
+    entries: Vec<ErrorEntry>,
+    attempts: HashMap<String, u32>,
+    kind_filter: Option<LinkErrorKind>,
+}
+
+impl ErrorLog {
+    /// Removes all entries and resets the attempt counts.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.attempts.clear();
+    }
+
+    /// Removes the entries for the given links, keeping their attempt counts.
+    pub fn remove_links(&mut self, links: &[String]) {
+        self.entries.retain(|entry| !links.contains(&entry.link));
+    }
+
+    pub fn record(&mut self, link: &str, stage: ErrorStage, error: &LinkError) {
+        self.record_entry(link, stage, error.kind(), error.message());
+    }
+
+    fn record_entry(&mut self, link: &str, stage: ErrorStage, kind: LinkErrorKind, message: String) {
+        let attempts = self.attempts.entry(link.to_string()).or_insert(0);
+        *attempts += 1;
+        self.entries.push(ErrorEntry {
+            link: link.to_string(),
+            stage,
+            kind,
+            message,
+            timestamp: Local::now(),
+            attempts: *attempts,
+        });
+    }
+
+    /// Records every failed link in the given extraction results.
+    pub fn record_extraction_errors(&mut self, links: &[MultiUpLink]) {
+        for link in links {
+            match link {
+                MultiUpLink::Project(project) => {
+                    match &project.status {
+                        Some(Ok(_)) => {
+                            for download_link in project.download_links.iter().flatten() {
+                                match &download_link.status {
+                                    Some(Ok(_)) => {}
+                                    Some(Err(error)) => {
+                                        self.record(&download_link.original_link, download_stage(download_link.direct_links.is_some()), error);
+                                    }
+                                    None => {
+                                        self.record_entry(&download_link.original_link, ErrorStage::Mirror, LinkErrorKind::Other, "Unknown".to_string());
+                                    }
+                                }
+                            }
+                        }
+                        Some(Err(error)) => self.record(&project.original_link, ErrorStage::Project, error),
+                        None => {
+                            self.record_entry(&project.original_link, ErrorStage::Project, LinkErrorKind::Other, "Unknown".to_string());
+                        }
+                    }
+                }
+                MultiUpLink::Download(download) => {
+                    match &download.status {
+                        Some(Ok(_)) => {}
+                        Some(Err(error)) => self.record(&download.original_link, download_stage(download.direct_links.is_some()), error),
+                        None => {
+                            self.record_entry(&download.original_link, ErrorStage::Mirror, LinkErrorKind::Other, "Unknown".to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the failed links which match the current filter.
+    pub fn failed_links(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.filtered_entries()
+            .map(|entry| entry.link.clone())
+            .filter(|link| seen.insert(link.clone()))
+            .collect()
+    }
+
+    fn filtered_entries(&self) -> impl Iterator<Item = &ErrorEntry> {
+        self.entries.iter()
+            .filter(|entry| self.kind_filter.is_none_or(|kind| entry.kind == kind))
+    }
+
+    fn to_text(&self) -> String {
+        self.filtered_entries()
+            .map(|entry| format!("[{}] {} | {} | {} | {} | attempt {}",
+                                 entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                                 entry.link,
+                                 entry.stage.name(),
+                                 entry.kind.name(),
+                                 entry.message,
+                                 entry.attempts))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Shows the error log window.
+    ///
+    /// Returns `true` if the user asked to retry the failed links.
+    pub fn show(&mut self, ctx: &Context, title: &str, open: &mut bool, can_retry: bool) -> bool {
+        let mut retry = false;
+        Window::new(title)
+            .default_width(600.0)
+            .open(open)
+            .show(ctx, |ui| {
+                let mut kind_counts: BTreeMap<LinkErrorKind, usize> = BTreeMap::new();
+                for entry in &self.entries {
+                    *kind_counts.entry(entry.kind).or_insert(0) += 1;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Error kind:");
+                    ComboBox::from_id_source(format!("{} Kind Filter", title))
+                        .selected_text(match self.kind_filter {
+                            Some(kind) => kind.name().to_string(),
+                            None => format!("All ({})", self.entries.len()),
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.kind_filter, None, format!("All ({})", self.entries.len()));
+                            for (kind, count) in &kind_counts {
+                                ui.selectable_value(&mut self.kind_filter, Some(*kind), format!("{} ({})", kind.name(), count));
+                            }
+                        });
+
+                    if ui.button("Copy log").clicked() {
+                        ui.output_mut(|output| output.copied_text = self.to_text());
+                    }
+
+                    let failed_links = self.failed_links();
+                    if ui.add_enabled(can_retry && !failed_links.is_empty(), Button::new(format!("Retry failed links ({})", failed_links.len()))).clicked() {
+                        retry = true;
+                    }
+                });
+
+                ui.separator();
+
+                ScrollArea::both()
+                    .id_source(format!("{} Entries", title))
+                    .min_scrolled_height(ui.available_height())
+                    .show(ui, |ui| {
+                        Grid::new(format!("{} Grid", title))
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("Time");
+                                ui.strong("Link");
+                                ui.strong("Stage");
+                                ui.strong("Kind");
+                                ui.strong("Message");
+                                ui.strong("Attempts");
+                                ui.end_row();
+
+                                for entry in self.filtered_entries() {
+                                    ui.label(entry.timestamp.format("%H:%M:%S").to_string());
+                                    ui.label(&entry.link);
+                                    ui.label(entry.stage.name());
+                                    ui.label(entry.kind.name());
+                                    ui.label(&entry.message);
+                                    ui.label(entry.attempts.to_string());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        retry
+    }
+}
+
+/// Infers the stage at which a download link failed.
+///
+/// Direct links are only present once the mirror page has been processed,
+/// so a failure with direct links happened during the validity check.
+fn download_stage(has_direct_links: bool) -> ErrorStage {
+    if has_direct_links {
+        ErrorStage::Validity
+    } else {
+        ErrorStage::Mirror
+    }
+}
+
+/// Merges retried links into the completed links.
+///
+/// Retried download links replace the matching link inside its project, or the matching top-level link.
+/// Links which do not match any completed link are appended.
+pub fn merge_links(completed_links: &mut Vec<MultiUpLink>, retried_links: Vec<MultiUpLink>) {
+    for retried_link in retried_links {
+        if let Some(position) = completed_links.iter().position(|link| link == &retried_link) {
+            completed_links[position] = retried_link;
+            continue;
+        }
+
+        if let MultiUpLink::Download(download) = retried_link {
+            let project = completed_links.iter_mut().find_map(|link| match link {
+                MultiUpLink::Project(project) => project.download_links.as_mut()
+                    .filter(|download_links| download_links.contains(&download)),
+                MultiUpLink::Download(_) => None,
+            });
+            match project {
+                Some(download_links) => {
+                    download_links.replace(download);
+                }
+                None => completed_links.push(MultiUpLink::Download(download)),
+            }
+        } else {
+            completed_links.push(retried_link);
+        }
+    }
+}
@@ -1,36 +1,45 @@
 use std::collections::{BTreeSet, HashSet};
 use std::sync::{Arc, OnceLock};
-use std::thread;
 
 use async_recursion::async_recursion;
-use crossbeam_channel::{Receiver, Sender};
-use eframe::egui::{Align2, Button, CollapsingHeader, Context, Label, ScrollArea, Sense, TextEdit, TopBottomPanel, Ui, Window};
+use crossbeam_channel::Receiver;
+use eframe::egui::{Align2, Button, CollapsingHeader, Context, Label, Response, ScrollArea, Sense, TextEdit, TopBottomPanel, Ui};
 use eframe::egui::Direction::TopDown;
 use egui_extras::{Column, TableBuilder};
 use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
-use headless_chrome::Browser;
 use regex::Regex;
 use reqwest::Client;
 use scraper::{ElementRef, Selector};
-use tokio::runtime::Runtime;
-use tokio::sync::Semaphore;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio_util::sync::CancellationToken;
 
-use crate::modules::api::{MultiUpLinkInformation, recheck_validity_api};
+use crate::modules::api::{Login, MultiUpLinkInformation, recheck_validity_api};
+use crate::modules::clone_project::{CloneProjectUI, CloneTarget};
+use crate::modules::error_log::{ErrorLog, merge_links};
 use crate::modules::filter::FilterMenu;
-use crate::modules::general::{get_page_html, new_browser};
+use crate::modules::general::{add_alerts, FetchStats, PageFetcher};
+use crate::modules::health::HealthReportUI;
+use crate::modules::jobs::{Job, JobKind, JobRerun, JobStatus, spawn_job};
 use crate::modules::links::{DirectLink, DownloadLink, LinkError, MultiUpLink, ProjectLink};
+use crate::modules::network::{self, Service};
+use crate::modules::replenish::{ReplenishTarget, ReplenishUI};
+use crate::modules::settings::{Settings, settings};
+use crate::modules::watchlist::WatchedEntry;
+
+/// Represents the extracted links along with how their pages were loaded.
+type ExtractionResult = Result<(Vec<MultiUpLink>, FetchStats), LinkError>;
 
 #[derive(Default)]
 struct Channels {
-    direct_links: Option<Receiver<Vec<MultiUpLink>>>,
-    cancel: Option<Sender<bool>>,
+    direct_links: Option<Receiver<ExtractionResult>>,
+    cancel: Option<CancellationToken>,
 }
 
 impl Channels {
-    fn new(direct_links_receiver: Option<Receiver<Vec<MultiUpLink>>>, cancel_sender: Option<Sender<bool>>) -> Self {
+    fn new(direct_links_receiver: Option<Receiver<ExtractionResult>>, cancellation_token: Option<CancellationToken>) -> Self {
         Self {
             direct_links: direct_links_receiver,
-            cancel: cancel_sender
+            cancel: cancellation_token
         }
     }
 }
@@ -41,27 +50,45 @@ pub struct ExtractUI {
     recheck_validity: bool,
     currently_extracting: bool,
     completed_links: Vec<MultiUpLink>,
+    fetch_stats: Option<FetchStats>,
     toasts: Toasts,
     shown_toast: bool,
     channels: Channels,
     error_log_open: bool,
-    error_log_text: String,
+    error_log: ErrorLog,
+    retrying_links: Option<Vec<String>>,
     search_filter: String,
     filter: FilterMenu,
     selection: (Option<usize>, Option<usize>),
     selected_links: HashSet<String>,
-    direct_links: Vec<String>
+    direct_links: Vec<String>,
+    health_report_ui: HealthReportUI,
+    watchlist_entries: Vec<WatchedEntry>,
+    replenish_ui: ReplenishUI,
+    clone_project_ui: CloneProjectUI,
 }
 
 impl ExtractUI {
-    pub fn display(ctx: &Context, ui: &mut Ui, extract_ui: &mut ExtractUI) {
+    /// Creates the tab with the defaults from the settings.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            recheck_validity: settings.extract.recheck_validity,
+            ..Default::default()
+        }
+    }
+
+    pub fn display(ctx: &Context, ui: &mut Ui, extract_ui: &mut ExtractUI, alerts: Vec<String>) {
         extract_ui.toasts = Toasts::new()
             .anchor(Align2::RIGHT_TOP, (10.0, 10.0))
             .direction(TopDown);
+        add_alerts(&mut extract_ui.toasts, alerts);
 
         extract_ui.display_input_area(ui);
         extract_ui.display_footer(ctx);
         extract_ui.display_output_area(ui);
+        extract_ui.health_report_ui.show_report_window(ctx, &extract_ui.completed_links, &mut extract_ui.toasts);
+        extract_ui.replenish_ui.show_replenish_window(ctx, &mut extract_ui.toasts);
+        extract_ui.clone_project_ui.show_clone_window(ctx, &mut extract_ui.toasts);
 
         extract_ui.toasts.show(ctx);
     }
@@ -92,23 +119,8 @@ impl ExtractUI {
                 .add_enabled(!self.currently_extracting, Button::new("Extract direct links"))
                 .clicked()
             {
-                self.currently_extracting = true;
-
-
-                let (direct_links_sender, direct_links_receiver) = crossbeam_channel::unbounded();
-                let (cancel_sender, cancel_receiver) = crossbeam_channel::unbounded();
-                self.channels = Channels::new(Some(direct_links_receiver), Some(cancel_sender));
-
-                // Main extraction function
-                let rt = Runtime::new().unwrap();
-                let multiup_links = self.multiup_links.clone();
-                let recheck_validity = self.recheck_validity;
-                thread::spawn(move || {
-                    rt.block_on(async {
-                        let multiup_links = extract_direct_links(&multiup_links, recheck_validity, cancel_receiver).await;
-                        let _ = direct_links_sender.send(multiup_links);
-                    });
-                });
+                self.retrying_links = None;
+                self.start_extraction(self.multiup_links.clone());
             }
 
             // Generation text and cancel extraction button
@@ -117,65 +129,46 @@ impl ExtractUI {
                 ui.label("Extracting links...");
                 let cancel_button = ui.add_enabled(self.channels.cancel.is_some(), Button::new("Cancel now"));
                 if cancel_button.clicked() {
-                    if let Some(sender) = &self.channels.cancel {
-                        let _ = sender.send(true);
-                        self.channels.cancel.take();
+                    if let Some(cancellation_token) = self.channels.cancel.take() {
+                        cancellation_token.cancel();
                     }
                 }
             }
 
             if let Some(receiver) = &self.channels.direct_links {
-                if let Ok(multiup_links) = receiver.try_recv() {
-                    self.completed_links = multiup_links;
-                    self.currently_extracting = false;
-                    self.shown_toast = false;
-                    self.filter.update_hosts(&self.completed_links);
-
-                    // Update error log
-                    let mut errors = String::new();
-                    for link in self.completed_links.iter() {
-                        match link {
-                            MultiUpLink::Project(project) => {
-                                match &project.status {
-                                    Some(status) => {
-                                        match status {
-                                            Ok(_) => {
-                                                for link in project.download_links.as_ref().unwrap() {
-                                                    if link.status.as_ref().is_none() {
-                                                        errors = format!("{}\n\n{} - {}", errors, &link.original_link, "Unknown");
-                                                    } else if let Err(error) = link.status.as_ref().unwrap() {
-                                                        errors = format!("{}\n\n{} - {:?}", errors, &link.original_link, error);
-                                                    }
-                                                }
-                                            }
-                                            Err(error) => {
-                                                errors = format!("{}\n\n{} - {:?}", errors, &project.original_link, error);
-                                            }
-                                        }
-                                    }
-                                    None => {
-                                        errors = format!("{}\n\n{} - {}", errors, &project.original_link, "Unknown");
-                                    }
-                                }
+                match receiver.try_recv() {
+                    Ok(Ok((multiup_links, fetch_stats))) => {
+                        self.fetch_stats = Some(fetch_stats);
+                        // Update error log
+                        match self.retrying_links.take() {
+                            Some(retried_links) => {
+                                self.error_log.remove_links(&retried_links);
+                                self.error_log.record_extraction_errors(&multiup_links);
+                                merge_links(&mut self.completed_links, multiup_links);
                             }
-                            MultiUpLink::Download(download) => {
-                                match &download.status {
-                                    Some(status) => {
-                                        match status {
-                                            Ok(_) => {},
-                                            Err(error) => {
-                                                errors = format!("{}\n\n{} - {:?}", errors, &download.original_link, error);
-                                            }
-                                        }
-                                    },
-                                    None => {
-                                        errors = format!("{}\n\n{} - {}", errors, &download.original_link, "Unknown");
-                                    }
-                                }
+                            None => {
+                                self.error_log.clear();
+                                self.error_log.record_extraction_errors(&multiup_links);
+                                self.completed_links = multiup_links;
                             }
                         }
+                        self.currently_extracting = false;
+                        self.shown_toast = false;
+                        self.filter.update_hosts(&self.completed_links);
                     }
-                    self.error_log_text = errors;
+                    Ok(Err(error)) => {
+                        self.currently_extracting = false;
+                        self.retrying_links = None;
+                        self.toasts.add(Toast {
+                            text: error.message().into(),
+                            kind: ToastKind::Error,
+                            options: ToastOptions::default()
+                                .duration_in_seconds(10.0)
+                                .show_progress(true)
+                                .show_icon(true)
+                        });
+                    }
+                    Err(_) => {}
                 }
             }
 
@@ -235,22 +228,68 @@ impl ExtractUI {
             if ui.button("See errors").clicked() {
                 self.error_log_open = true;
             }
+
+            if ui.button("Health report").clicked() {
+                self.health_report_ui.show_report = true;
+            }
+
+            if ui.add_enabled(!self.completed_links.is_empty(), Button::new("Add to watchlist")).clicked() {
+                self.watchlist_entries = self.completed_links.iter()
+                    .filter(|link| match link {
+                        MultiUpLink::Project(project) => matches!(project.status, Some(Ok(()))),
+                        MultiUpLink::Download(download) => matches!(download.status, Some(Ok(()))),
+                    })
+                    .filter_map(WatchedEntry::from_multiup_link)
+                    .collect();
+                self.toasts.add(Toast {
+                    text: format!("Added {} links to the watchlist", self.watchlist_entries.len()).into(),
+                    kind: ToastKind::Info,
+                    options: ToastOptions::default()
+                        .duration_in_seconds(5.0)
+                        .show_progress(true)
+                        .show_icon(true)
+                });
+            }
         });
     }
 
+    /// Starts extracting the direct links of the MultiUp links in `input_text`.
+    fn start_extraction(&mut self, input_text: String) {
+        self.currently_extracting = true;
+
+        let (direct_links_sender, direct_links_receiver) = crossbeam_channel::unbounded();
+
+        // Main extraction function
+        let recheck_validity = self.recheck_validity;
+        let description = format!("Extract {} links", detect_links(&input_text).len());
+        let rerun = JobRerun::Extract(input_text.clone());
+        let job = spawn_job(JobKind::Extract, description, Some(rerun), |job| async move {
+            let multiup_links = extract_direct_links(&input_text, recheck_validity, Arc::clone(&job)).await;
+            let status = match &multiup_links {
+                Ok((multiup_links, fetch_stats)) => JobStatus::Completed(format!("{} links extracted, {}", multiup_links.len(), fetch_stats.summary())),
+                Err(error) => JobStatus::Failed(error.message())
+            };
+            let _ = direct_links_sender.send(multiup_links);
+            status
+        });
+        self.channels = Channels::new(Some(direct_links_receiver), Some(job.cancellation_token.clone()));
+    }
+
+    /// Extracts the given links again, unless an extraction is already running.
+    pub fn rerun_extraction(&mut self, input_text: String) {
+        if !self.currently_extracting {
+            self.retrying_links = None;
+            self.start_extraction(input_text);
+        }
+    }
+
     pub fn display_error_log(&mut self, ctx: &Context) {
-        Window::new("Extract Error Log")
-            .default_width(200.0)
-            .open(&mut self.error_log_open)
-            .show(ctx, |ui| {
-                ScrollArea::vertical()
-                    .id_source("Error Log")
-                    .min_scrolled_height(ui.available_height())
-                    .show(ui, |ui| {
-                        let mut error = self.error_log_text.clone();
-                        ui.add(TextEdit::multiline(&mut error).desired_width(ui.available_width()));
-                    });
-            });
+        let retry = self.error_log.show(ctx, "Extract Error Log", &mut self.error_log_open, !self.currently_extracting);
+        if retry {
+            let failed_links = self.error_log.failed_links();
+            self.start_extraction(failed_links.join("\n"));
+            self.retrying_links = Some(failed_links);
+        }
     }
 
     fn display_output_area(&mut self, ui: &mut Ui) {
@@ -269,6 +308,8 @@ impl ExtractUI {
         let height = ui.available_height();
         self.direct_links = Vec::new();
         let mut url_labels = vec![];
+        let mut replenish_target = None;
+        let mut clone_target = None;
         ui.horizontal(|ui| {
             ui.set_height(height);
             let output_box_width = 0.80 * ui.available_width();
@@ -281,7 +322,7 @@ impl ExtractUI {
                             match link {
                                 MultiUpLink::Project(project) => {
                                     if let Some(Ok(())) = project.status {
-                                        CollapsingHeader::new(&project.name)
+                                        let project_header = CollapsingHeader::new(&project.name)
                                             .id_source(&project.link_id)
                                             .default_open(true)
                                             .show(ui, |ui| {
@@ -311,16 +352,20 @@ impl ExtractUI {
                                                                 if let Some(date_upload) = &information.date_upload {
                                                                     display_information += format!(" | Uploaded on {}", date_upload).as_str();
                                                                 }
+                                                                if let Some(replenished_links) = self.replenish_ui.replenished_links(&link.link_id) {
+                                                                    display_information += format!(" | Replenished as {}", replenished_links.join(", ")).as_str();
+                                                                }
                                                                 let filtered_links = self.filter.filter_links(link);
                                                                 if searched {
                                                                     row.col(|ui| {
-                                                                        CollapsingHeader::new(&display_information).id_source(&link.link_id).default_open(true).show(ui, |ui| {
+                                                                        let header = CollapsingHeader::new(&display_information).id_source(&link.link_id).default_open(true).show(ui, |ui| {
                                                                             for link in &filtered_links {
                                                                                 let url_label = ui.add(Label::new(link).sense(Sense::click()));
 
                                                                                 url_labels.push((url_label, link.clone()));
                                                                             }
                                                                         });
+                                                                        display_replenish_menu(&header.header_response, link, &mut replenish_target);
                                                                     });
                                                                 }
 
@@ -331,6 +376,13 @@ impl ExtractUI {
                                                         });
                                                     });
                                             });
+
+                                        project_header.header_response.context_menu(|ui| {
+                                            if ui.button("Clone project").clicked() {
+                                                clone_target = Some(CloneTarget::new(project));
+                                                ui.close_menu();
+                                            }
+                                        });
                                     }
                                 },
                                 MultiUpLink::Download(download) => {
@@ -351,6 +403,9 @@ impl ExtractUI {
                                         if let Some(date_upload) = &information.date_upload {
                                             display_information += format!(" | Uploaded on {}", date_upload).as_str();
                                         }
+                                        if let Some(replenished_links) = self.replenish_ui.replenished_links(&download.link_id) {
+                                            display_information += format!(" | Replenished as {}", replenished_links.join(", ")).as_str();
+                                        }
 
                                         let filtered_links = self.filter.filter_links(download);
                                         let number_of_direct_links = filtered_links.len() as f32;
@@ -361,13 +416,14 @@ impl ExtractUI {
                                                     body.rows(height, 1, |mut row| {
                                                         // let row_index = row.index();
                                                         row.col(|ui| {
-                                                            CollapsingHeader::new(&display_information).id_source(&download.link_id).default_open(true).show(ui, |ui| {
+                                                            let header = CollapsingHeader::new(&display_information).id_source(&download.link_id).default_open(true).show(ui, |ui| {
                                                                 for link in &filtered_links {
                                                                     let url_label = ui.add(Label::new(link).sense(Sense::click()));
 
                                                                     url_labels.push((url_label, link.clone()))
                                                                 }
                                                             });
+                                                            display_replenish_menu(&header.header_response, download, &mut replenish_target);
                                                         });
                                                     });
                                                 });
@@ -387,6 +443,14 @@ impl ExtractUI {
             self.filter.show(ui, &self.completed_links);
         });
 
+        if let Some(target) = replenish_target {
+            self.replenish_ui.open(target);
+        }
+
+        if let Some(target) = clone_target {
+            self.clone_project_ui.open(target);
+        }
+
         if self.selection.1.is_some() && self.selection.0 > self.selection.1 {
             (self.selection.0, self.selection.1) = (self.selection.1, self.selection.0)
         }
@@ -595,29 +659,63 @@ impl ExtractUI {
         }
     }
 
+    /// Sets the MultiUp account used when replenishing mirrors and cloning projects.
+    pub fn set_login(&mut self, login: Login) {
+        self.replenish_ui.login = login.clone();
+        self.clone_project_ui.login = login;
+    }
+
+    /// Takes the links the user has chosen to add to the watchlist.
+    pub fn take_watchlist_entries(&mut self) -> Vec<WatchedEntry> {
+        std::mem::take(&mut self.watchlist_entries)
+    }
+
     fn display_footer(&mut self, ctx: &Context) {
         TopBottomPanel::bottom("Information").show(ctx, |ui| {
-            ui.label(format!("Selected {}/{} links", self.selected_links.len(), self.direct_links.len()));
+            ui.horizontal(|ui| {
+                ui.label(format!("Selected {}/{} links", self.selected_links.len(), self.direct_links.len()));
+                if let Some(fetch_stats) = &self.fetch_stats {
+                    ui.separator();
+                    ui.label(format!("Pages loaded: {} over HTTP, {} with the browser", fetch_stats.http_pages, fetch_stats.browser_pages));
+                }
+            });
         });
     }
 }
 
 // Extraction Functions
-async fn extract_direct_links(input_text: &str, recheck_validity: bool, cancel_receiver: Receiver<bool>) -> Vec<MultiUpLink> {
+async fn extract_direct_links(input_text: &str, recheck_validity: bool, job: Arc<Job>) -> ExtractionResult {
+    let cancellation_token = job.cancellation_token.clone();
     // Detect links
     let detected_links = detect_links(input_text);
     
-    // Create browser for links
-    let browser = new_browser();
+    let client = network::client(Service::MultiUp).map_err(LinkError::APIError)?;
+    // Pages are loaded over HTTP where possible, so the browser is only launched if needed
+    let fetcher = Arc::new(PageFetcher::new(client.clone()));
     // Process links
-    let processed_links = process_links(detected_links, cancel_receiver.clone(), browser.clone()).await;
+    let processed_links = process_links(detected_links, cancellation_token.clone(), Arc::clone(&fetcher)).await;
 
     // Return vec of completed links
     // let time_now = Instant::now();
-    let completed_links = get_direct_links(processed_links, recheck_validity, cancel_receiver, browser).await;
+    let completed_links = get_direct_links(processed_links, recheck_validity, cancellation_token, client, Arc::clone(&fetcher), job).await;
     // let time_taken = time_now.elapsed();
     // println!("{}", time_taken.as_secs_f32());
-    completed_links
+    Ok((completed_links, fetcher.stats()))
+}
+
+/// Extracts the direct links of the MultiUp links in the given input text and rechecks their validity with the API.
+pub async fn verify_links(input_text: &str, job: Arc<Job>) -> Result<Vec<MultiUpLink>, LinkError> {
+    extract_direct_links(input_text, true, job).await.map(|(links, _)| links)
+}
+
+/// Detects and processes MultiUp links in the given input text without extracting their direct links.
+///
+/// Project links are resolved into their download links.
+pub async fn resolve_links(input_text: &str) -> Result<Vec<MultiUpLink>, LinkError> {
+    let detected_links = detect_links(input_text);
+    let cancellation_token = CancellationToken::new();
+    let fetcher = Arc::new(PageFetcher::new(network::client(Service::MultiUp).map_err(LinkError::APIError)?));
+    Ok(process_links(detected_links, cancellation_token, fetcher).await)
 }
 
 /// Detects MultiUp links in the given input text.
@@ -637,7 +735,7 @@ fn detect_links(input_text: &str) -> Vec<String> {
     detected_links
 }
 
-async fn process_links(detected_links: Vec<String>, cancel_receiver: Receiver<bool>, browser: Browser) -> Vec<MultiUpLink> {
+async fn process_links(detected_links: Vec<String>, cancellation_token: CancellationToken, fetcher: Arc<PageFetcher>) -> Vec<MultiUpLink> {
     // Create regexes
     let (_, download_regex, mirror_regex, project_regex) = create_regexes();
 
@@ -649,12 +747,12 @@ async fn process_links(detected_links: Vec<String>, cancel_receiver: Receiver<bo
     let mut project_processing_tasks = Vec::new();
     // Processing
     for link in detected_links {
-        let cancel_receiver = cancel_receiver.clone();
-        let browser = browser.clone();
+        let cancellation_token = cancellation_token.child_token();
+        let fetcher = Arc::clone(&fetcher);
         if project_regex.is_match(&link) {
             let link = link.clone();
             let processing_task = tokio::spawn(async move {
-                process_project_link(&link, cancel_receiver, browser).await
+                process_project_link(&link, cancellation_token, fetcher).await
             });
             project_processing_tasks.push(processing_task);
         } else if mirror_regex.is_match(&link) {
@@ -725,14 +823,14 @@ pub fn create_regexes() -> (Regex, Regex, Regex, Regex) {
 ///
 /// This function takes in a project link, mirror regex, and download regex as inputs,
 /// and returns a Project MultiUpLink.
-async fn process_project_link(project_link: &str, cancel_receiver: Receiver<bool>, browser: Browser) -> MultiUpLink {
+async fn process_project_link(project_link: &str, cancellation_token: CancellationToken, fetcher: Arc<PageFetcher>) -> MultiUpLink {
     // Download links
     let download_regex = DOWNLOAD_REGEX.get().unwrap();
 
     // Mirror links
     let mirror_regex = MIRROR_REGEX.get().unwrap();
 
-    let (id, name, download_links) = get_project_information(project_link, cancel_receiver, browser).await;
+    let (id, name, download_links) = get_project_information(project_link, cancellation_token, fetcher).await;
     let download_links = match download_links {
         Ok(download_links) => download_links,
         Err(error) => {
@@ -763,18 +861,25 @@ async fn process_project_link(project_link: &str, cancel_receiver: Receiver<bool
 static PROJECT_DOWNLOAD_LINKS_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static PROJECT_TITLE_SELECTOR: OnceLock<Selector> = OnceLock::new();
 
+/// Checks whether a project page loaded over HTTP contains its download links.
+fn is_project_page_complete(html: &str) -> bool {
+    let parsed_page = scraper::Html::parse_document(html);
+    let project_download_links_selector = PROJECT_DOWNLOAD_LINKS_SELECTOR
+        .get_or_init(|| Selector::parse(r#"#textarea-links-long"#).unwrap());
+    parsed_page.select(project_download_links_selector).next().is_some()
+}
+
 /// Retrieves information about a project given a project link.
 ///
 /// Parses the project link for an ID, parses the page title for a name and extracts download links.
 /// If there is no name, it is set to the ID.
 #[async_recursion]
-async fn get_project_information(project_link: &str, cancel_receiver: Receiver<bool>, browser: Browser) -> (String, String, Result<Vec<String>, LinkError>) {
+async fn get_project_information(project_link: &str, cancellation_token: CancellationToken, fetcher: Arc<PageFetcher>) -> (String, String, Result<Vec<String>, LinkError>) {
     let link_parts: Vec<&str> = project_link.split('/').collect();
     let id = link_parts.last().unwrap().to_string();
     let name = id.clone();
 
-    let client = Client::new();
-    let html = match get_page_html(project_link, &client, Some(cancel_receiver), 0, browser).await {
+    let html = match fetcher.fetch(project_link, cancellation_token, is_project_page_complete).await {
         Ok(html) => html,
         Err(error) => {
             return (id, name, Err(error));
@@ -821,6 +926,21 @@ fn get_project_name_from_title(title_text: &str) -> Option<&str> {
     None
 }
 
+/// Returns the ID of a MultiUp download or mirror link, or `None` for other links.
+pub fn download_link_id(link: &str) -> Option<String> {
+    let (_, download_regex, mirror_regex, project_regex) = create_regexes();
+    let regex = if project_regex.is_match(link) {
+        return None;
+    } else if mirror_regex.is_match(link) {
+        mirror_regex
+    } else if download_regex.is_match(link) {
+        download_regex
+    } else {
+        return None;
+    };
+    Some(process_non_project_link(link, &regex).link_id).filter(|link_id| !link_id.is_empty())
+}
+
 /// Process a non-project link and return a `DownloadLink` object.
 fn process_non_project_link(link: &str, regex: &Regex) -> DownloadLink {
     let link_parts = regex.replace(link, "");
@@ -831,22 +951,24 @@ fn process_non_project_link(link: &str, regex: &Regex) -> DownloadLink {
 }
 
 
-async fn get_direct_links(multiup_links: Vec<MultiUpLink>, recheck_validity: bool, cancel_receiver: Receiver<bool>, browser: Browser) -> Vec<MultiUpLink> {
+async fn get_direct_links(multiup_links: Vec<MultiUpLink>, recheck_validity: bool, cancellation_token: CancellationToken, client: Client, fetcher: Arc<PageFetcher>, job: Arc<Job>) -> Vec<MultiUpLink> {
     // At the beginning of the function
-    let semaphore = Arc::new(Semaphore::new(100));
+    let semaphore = Arc::new(Semaphore::new(settings().extract.project_concurrency.max(1)));
     let mut tasks = Vec::new();
-    let client = Client::new();
+    job.set_total(multiup_links.len());
     for link in multiup_links {
-        let cancel_receiver = cancel_receiver.clone();
+        let cancellation_token = cancellation_token.child_token();
+        let job = Arc::clone(&job);
         let client = client.clone();
-        let browser = browser.clone();
+        let fetcher = Arc::clone(&fetcher);
         match link {
             MultiUpLink::Project(project_link) => {
                 // Create a task for each project link
                 let semaphore = Arc::clone(&semaphore);
                 let task = tokio::spawn(async move {
-                    let _permit = semaphore.acquire().await.unwrap();
-                    let project = get_direct_links_from_project(project_link, recheck_validity, cancel_receiver, client, browser).await;
+                    let _permit = acquire_permit(&semaphore, &cancellation_token).await;
+                    let project = get_direct_links_from_project(project_link, recheck_validity, cancellation_token, client, fetcher).await;
+                    job.advance();
                     MultiUpLink::Project(project)
                 });
                 tasks.push(task);
@@ -855,8 +977,9 @@ async fn get_direct_links(multiup_links: Vec<MultiUpLink>, recheck_validity: boo
                 // Create a task for each download link
                 let semaphore = Arc::clone(&semaphore);
                 let task = tokio::spawn(async move {
-                    let _permit = semaphore.acquire().await.unwrap();
-                    let download = get_direct_links_from_download_link(download_link, recheck_validity, cancel_receiver, client, browser).await;
+                    let _permit = acquire_permit(&semaphore, &cancellation_token).await;
+                    let download = get_direct_links_from_download_link(download_link, recheck_validity, cancellation_token, client, fetcher).await;
+                    job.advance();
                     MultiUpLink::Download(download)
                 });
                 tasks.push(task);
@@ -874,22 +997,22 @@ async fn get_direct_links(multiup_links: Vec<MultiUpLink>, recheck_validity: boo
     multiup_links
 }
 
-async fn get_direct_links_from_project(mut project_link: ProjectLink, recheck_validity: bool, cancel_receiver: Receiver<bool>, client: Client, browser: Browser) -> ProjectLink {
+async fn get_direct_links_from_project(mut project_link: ProjectLink, recheck_validity: bool, cancellation_token: CancellationToken, client: Client, fetcher: Arc<PageFetcher>) -> ProjectLink {
     if project_link.download_links.is_none() {
         return project_link;
     }
 
-    let semaphore = Arc::new(Semaphore::new(200)); // Adjust the number of permits according to your needs
+    let semaphore = Arc::new(Semaphore::new(settings().extract.link_concurrency.max(1)));
     let mut tasks = Vec::new();
     
     for link in project_link.download_links.take().unwrap() {
         let client = client.clone();
-        let browser = browser.clone();
+        let fetcher = Arc::clone(&fetcher);
         let semaphore = Arc::clone(&semaphore);
-        let cancel_receiver = cancel_receiver.clone();
+        let cancellation_token = cancellation_token.child_token();
         let task = tokio::spawn(async move {
-            let _permit = semaphore.acquire().await.unwrap();
-            get_direct_links_from_download_link(link, recheck_validity, cancel_receiver, client, browser).await
+            let _permit = acquire_permit(&semaphore, &cancellation_token).await;
+            get_direct_links_from_download_link(link, recheck_validity, cancellation_token, client, fetcher).await
         });
         tasks.push(task);
     }
@@ -904,20 +1027,30 @@ async fn get_direct_links_from_project(mut project_link: ProjectLink, recheck_va
     project_link
 }
 
-const MIRROR_PREFIX: &str = "https://multiup.io/en/mirror/";
+/// Waits for a permit from the semaphore.
+///
+/// Returns `None` as soon as the work is cancelled, so queued links are not held up waiting for a permit.
+async fn acquire_permit<'a>(semaphore: &'a Semaphore, cancellation_token: &CancellationToken) -> Option<SemaphorePermit<'a>> {
+    tokio::select! {
+        permit = semaphore.acquire() => permit.ok(),
+        _ = cancellation_token.cancelled() => None,
+    }
+}
+
+pub const MIRROR_PREFIX: &str = "https://multiup.io/en/mirror/";
 
-async fn get_direct_links_from_download_link(download_link: DownloadLink, recheck_validity: bool, cancel_receiver: Receiver<bool>, client: Client, browser: Browser) -> DownloadLink {
+async fn get_direct_links_from_download_link(download_link: DownloadLink, recheck_validity: bool, cancellation_token: CancellationToken, client: Client, fetcher: Arc<PageFetcher>) -> DownloadLink {
     let mirror_link = MIRROR_PREFIX.to_owned() + &download_link.link_id/* + "/dummy_text"*/;
-    let download_link = process_mirror_link(mirror_link.clone(), download_link, cancel_receiver.clone(), browser).await;
+    let download_link = process_mirror_link(mirror_link.clone(), download_link, cancellation_token.clone(), fetcher).await;
     if recheck_validity {
-        recheck_validity_api(mirror_link, download_link, cancel_receiver, client).await
+        recheck_validity_api(mirror_link, download_link, cancellation_token, client).await
     } else {
         download_link
     }
 }
 
-async fn process_mirror_link(mirror_link: String, mut download_link: DownloadLink, cancel_receiver: Receiver<bool>, browser: Browser) -> DownloadLink {
-    let information = get_mirror_information(&mirror_link, cancel_receiver, browser).await;
+async fn process_mirror_link(mirror_link: String, mut download_link: DownloadLink, cancellation_token: CancellationToken, fetcher: Arc<PageFetcher>) -> DownloadLink {
+    let information = get_mirror_information(&mirror_link, cancellation_token, fetcher).await;
     match information {
         Ok((direct_links, link_information)) => {
             download_link.direct_links = Some(direct_links);
@@ -936,13 +1069,26 @@ static MIRROR_HOSTS_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static MIRROR_TITLE_SELECTOR: OnceLock<Selector> = OnceLock::new();
 static QUEUE_SELECTOR: OnceLock<Selector> = OnceLock::new();
 
+/// Checks whether a mirror page loaded over HTTP can be used without the browser.
+///
+/// Bot challenges and pages whose hosts are filled in by scripts lack the title or the host buttons.
+fn is_mirror_page_complete(html: &str) -> bool {
+    let parsed_page = scraper::Html::parse_document(html);
+    let queue_selector = QUEUE_SELECTOR.get_or_init(|| Selector::parse(r#"body > section > div > section > div.row > div > section > div > div > div:nth-child(2) > div > h4"#).unwrap());
+    if parsed_page.select(queue_selector).next().is_some() {
+        return true;
+    }
+    let file_name_selector = MIRROR_TITLE_SELECTOR.get_or_init(|| Selector::parse(r#"h2.text-truncate"#).unwrap());
+    let mirror_hosts_selector = MIRROR_HOSTS_SELECTOR.get_or_init(|| Selector::parse(r#"a.host[namehost], button.host[namehost]"#).unwrap());
+    parsed_page.select(file_name_selector).next().is_some() && parsed_page.select(mirror_hosts_selector).next().is_some()
+}
+
 /// Retrieves
 #[async_recursion]
-async fn get_mirror_information(mirror_link: &str, cancel_receiver: Receiver<bool>, browser: Browser) -> Result<(BTreeSet<DirectLink>, MultiUpLinkInformation), LinkError> {
+async fn get_mirror_information(mirror_link: &str, cancellation_token: CancellationToken, fetcher: Arc<PageFetcher>) -> Result<(BTreeSet<DirectLink>, MultiUpLinkInformation), LinkError> {
     let mut direct_links: BTreeSet<DirectLink> = BTreeSet::new();
 
-    let client = Client::new();
-    let html = match get_page_html(mirror_link, &client, Some(cancel_receiver), 0, browser).await {
+    let html = match fetcher.fetch(mirror_link, cancellation_token, is_mirror_page_complete).await {
         Ok(html) => html,
         Err(error) => {
             return Err(error);
@@ -1013,6 +1159,17 @@ fn get_title_and_size_from_title_text(title: ElementRef) -> (String, String) {
     (file_name.to_string(), size_in_bytes.to_string())
 }
 
+/// Adds a context menu to a file's header which allows its mirrors to be replenished.
+fn display_replenish_menu(header: &Response, download_link: &DownloadLink, replenish_target: &mut Option<ReplenishTarget>) {
+    header.context_menu(|ui| {
+        let target = ReplenishTarget::new(download_link);
+        if ui.add_enabled(target.is_some(), Button::new("Replenish mirrors")).clicked() {
+            *replenish_target = target;
+            ui.close_menu();
+        }
+    });
+}
+
 fn calculate_row_heights(links: &HashSet<DownloadLink>, filter_menu: &FilterMenu) -> Vec<f32> {
     let mut heights = vec![];
 
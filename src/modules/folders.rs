@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+use eframe::egui::{Button, CollapsingHeader, Context, Grid, ScrollArea, TextEdit, Window};
+use egui_file::FileDialog;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use walkdir::WalkDir;
+use crate::modules::general::format_size;
+
+/// Represents a file found in a folder and the name it is uploaded with.
+#[derive(Clone)]
+pub struct FolderFile {
+    pub path: PathBuf,
+    /// The path relative to the selected folder, with the subfolders joined by " - ".
+    pub file_name: String,
+    pub size: u64,
+}
+
+/// Represents a selected folder and every file found in it and its subfolders.
+#[derive(Clone)]
+pub struct FolderProject {
+    /// The name of the folder, which is used as the project name.
+    pub name: String,
+    pub files: Vec<FolderFile>,
+}
+
+impl FolderProject {
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// Represents what to do with the scanned folders.
+pub enum FolderAction {
+    /// Queue a separate upload with its own project for each folder.
+    QueueProjects(Vec<FolderProject>),
+    /// Add the files of every folder to the disk upload list.
+    AddFiles(Vec<FolderProject>),
+}
+
+/// Builds a set from patterns separated by commas or new lines. Returns `None` if there are no patterns.
+fn build_glob_set(patterns: &str) -> Result<Option<GlobSet>, String> {
+    let patterns: Vec<&str> = patterns.split([',', '\n'])
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|error| format!("Invalid pattern {}: {}", pattern, error))?);
+    }
+    builder.build().map(Some).map_err(|error| error.to_string())
+}
+
+/// Finds the files in each folder and its subfolders.
+///
+/// Patterns are matched against the path relative to the folder, so `*.mkv` matches files in any subfolder.
+/// A file is uploaded if it matches an include pattern, or there are none, and it matches no exclude pattern.
+pub fn scan_folders(folders: &[PathBuf], include: &str, exclude: &str) -> Result<Vec<FolderProject>, String> {
+    let include = build_glob_set(include)?;
+    let exclude = build_glob_set(exclude)?;
+    let mut projects = vec![];
+    for folder in folders {
+        let mut files = vec![];
+        for entry in WalkDir::new(folder).sort_by_file_name() {
+            let entry = entry.map_err(|error| format!("Could not read {}: {}", folder.display(), error))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative_path = entry.path().strip_prefix(folder).unwrap_or(entry.path());
+            let included = include.as_ref().is_none_or(|include| include.is_match(relative_path));
+            let excluded = exclude.as_ref().is_some_and(|exclude| exclude.is_match(relative_path));
+            if !included || excluded {
+                continue;
+            }
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or_default();
+            files.push(FolderFile {
+                path: entry.path().to_path_buf(),
+                file_name: upload_file_name(relative_path),
+                size,
+            });
+        }
+        projects.push(FolderProject {
+            name: folder_name(folder),
+            files,
+        });
+    }
+    Ok(projects)
+}
+
+/// MultiUp projects cannot contain folders, so the subfolders are kept in the file name.
+fn upload_file_name(relative_path: &Path) -> String {
+    relative_path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" - ")
+}
+
+fn folder_name(folder: &Path) -> String {
+    folder.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| folder.to_string_lossy().to_string())
+}
+
+pub struct FolderUploadUI {
+    pub show_window: bool,
+    folders: Vec<PathBuf>,
+    include: String,
+    exclude: String,
+    project_per_folder: bool,
+    preview: Option<Result<Vec<FolderProject>, String>>,
+    folder_dialog: Option<FileDialog>,
+}
+
+impl Default for FolderUploadUI {
+    fn default() -> Self {
+        Self {
+            show_window: false,
+            folders: vec![],
+            include: String::new(),
+            exclude: String::new(),
+            project_per_folder: true,
+            preview: None,
+            folder_dialog: None,
+        }
+    }
+}
+
+impl FolderUploadUI {
+    /// Adds the folders and shows a preview of their files.
+    pub fn add_folders(&mut self, folders: Vec<PathBuf>) {
+        for folder in folders {
+            if !self.folders.contains(&folder) {
+                self.folders.push(folder);
+            }
+        }
+        self.preview = Some(scan_folders(&self.folders, &self.include, &self.exclude));
+        self.show_window = true;
+    }
+
+    /// Shows the folder upload window. Returns the action chosen by the user.
+    pub fn show_folder_window(&mut self, ctx: &Context) -> Option<FolderAction> {
+        let mut action = None;
+        let mut show_window = self.show_window;
+        Window::new("Folder Upload")
+            .default_width(700.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    ui.heading("Folders");
+                    if ui.button("Add folder").clicked() {
+                        let mut dialog = FileDialog::select_folder(None);
+                        dialog.open();
+                        self.folder_dialog = Some(dialog);
+                    }
+                    if ui.add_enabled(!self.folders.is_empty(), Button::new("Clear")).clicked() {
+                        self.folders.clear();
+                        changed = true;
+                    }
+                });
+                let mut removed = None;
+                for (index, folder) in self.folders.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("-").clicked() {
+                            removed = Some(index);
+                        }
+                        ui.label(folder.to_string_lossy());
+                    });
+                }
+                if let Some(index) = removed {
+                    self.folders.remove(index);
+                    changed = true;
+                }
+
+                Grid::new("Folder Patterns").num_columns(2).show(ui, |ui| {
+                    ui.label("Include:");
+                    changed |= ui.add(TextEdit::singleline(&mut self.include)
+                        .hint_text("e.g. *.mkv, *.srt (leave blank for every file)")
+                        .desired_width(400.0)).changed();
+                    ui.end_row();
+                    ui.label("Exclude:");
+                    changed |= ui.add(TextEdit::singleline(&mut self.exclude)
+                        .hint_text("e.g. *.nfo, Samples/**")
+                        .desired_width(400.0)).changed();
+                    ui.end_row();
+                });
+                ui.checkbox(&mut self.project_per_folder, "Create a project for each folder, named after the folder");
+
+                if changed {
+                    self.preview = None;
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.folders.is_empty(), Button::new("Preview")).clicked() {
+                        self.preview = Some(scan_folders(&self.folders, &self.include, &self.exclude));
+                    }
+                    let projects = match &self.preview {
+                        Some(Ok(projects)) => projects.iter().filter(|project| !project.files.is_empty()).cloned().collect(),
+                        _ => vec![]
+                    };
+                    let button_text = match self.project_per_folder {
+                        true => format!("Queue {} projects", projects.len()),
+                        false => "Add files to the upload list".to_string()
+                    };
+                    if ui.add_enabled(!projects.is_empty(), Button::new(button_text)).clicked() {
+                        action = Some(match self.project_per_folder {
+                            true => FolderAction::QueueProjects(projects),
+                            false => FolderAction::AddFiles(projects)
+                        });
+                    }
+                });
+
+                ui.separator();
+                match &self.preview {
+                    Some(Ok(projects)) => {
+                        ScrollArea::vertical().id_source("Folder Preview").show(ui, |ui| {
+                            for (index, project) in projects.iter().enumerate() {
+                                let title = match self.project_per_folder {
+                                    true => format!("Project \"{}\" - {} files, {}", project.name, project.files.len(), format_size(project.size())),
+                                    false => format!("{} - {} files, {}", project.name, project.files.len(), format_size(project.size())),
+                                };
+                                CollapsingHeader::new(title)
+                                    .id_source(("Folder Preview", index))
+                                    .show(ui, |ui| {
+                                        for file in &project.files {
+                                            ui.label(format!("{} ({})", file.file_name, format_size(file.size)));
+                                        }
+                                    });
+                            }
+                        });
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    None => {
+                        ui.label("Press Preview to see the files which will be uploaded.");
+                    }
+                }
+            });
+        self.show_window = show_window;
+
+        if let Some(dialog) = &mut self.folder_dialog {
+            if dialog.show(ctx).selected() {
+                if let Some(folder) = dialog.path() {
+                    let folder = folder.to_path_buf();
+                    self.add_folders(vec![folder]);
+                }
+            }
+        }
+        action
+    }
+}
@@ -1,25 +1,126 @@
+use std::collections::HashMap;
 use std::ffi::OsStr;
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use async_recursion::async_recursion;
-use crossbeam_channel::{Receiver, TryRecvError};
-use headless_chrome::{Browser, LaunchOptions};
-use reqwest::Client;
+use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use headless_chrome::protocol::cdp::Network::CookieParam;
+use reqwest::{Client, StatusCode};
+use tokio_util::sync::CancellationToken;
 
+use crate::modules::browser::browser_settings;
 use crate::modules::links::LinkError;
+use crate::modules::network::{network_settings, NetworkSettings, Service};
+
+/// Adds alerts raised in the background to a tab's toasts.
+///
+/// Every toaster shares the same storage, so alerts are shown by the active tab instead of a toaster of their own.
+pub fn add_alerts(toasts: &mut Toasts, alerts: Vec<String>) {
+    for alert in alerts {
+        toasts.add(Toast {
+            text: alert.into(),
+            kind: ToastKind::Warning,
+            options: ToastOptions::default()
+                .duration_in_seconds(15.0)
+                .show_progress(true)
+                .show_icon(true)
+        });
+    }
+}
+
+/// Represents how many pages were loaded over plain HTTP and how many needed the browser.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FetchStats {
+    pub http_pages: usize,
+    pub browser_pages: usize,
+}
+
+impl FetchStats {
+    pub fn summary(&self) -> String {
+        format!("{}/{} pages needed the browser", self.browser_pages, self.http_pages + self.browser_pages)
+    }
+}
+
+/// Fetches MultiUp pages over plain HTTP first and falls back to Chrome.
+///
+/// The browser is only used when the HTTP response is a bot challenge or does not contain
+/// the expected content, and is only launched the first time it is needed.
+pub struct PageFetcher {
+    client: Client,
+    browser: tokio::sync::OnceCell<Result<Browser, String>>,
+    http_pages: AtomicUsize,
+    browser_pages: AtomicUsize,
+}
+
+impl PageFetcher {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            browser: tokio::sync::OnceCell::new(),
+            http_pages: AtomicUsize::new(0),
+            browser_pages: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> FetchStats {
+        FetchStats {
+            http_pages: self.http_pages.load(Ordering::Relaxed),
+            browser_pages: self.browser_pages.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the HTML of the page.
+    ///
+    /// `is_complete` decides whether the HTML from the plain HTTP request can be used as-is.
+    pub async fn fetch(&self, page_link: &str, cancellation_token: CancellationToken, is_complete: fn(&str) -> bool) -> Result<String, LinkError> {
+        if cancellation_token.is_cancelled() {
+            return Err(LinkError::Cancelled);
+        }
+
+        let response = tokio::select! {
+            response = self.client.get(page_link).send() => response,
+            _ = cancellation_token.cancelled() => return Err(LinkError::Cancelled)
+        };
+        if let Ok(response) = response {
+            // A missing page will not load in the browser either
+            if response.status() == StatusCode::NOT_FOUND {
+                return Err(LinkError::Invalid);
+            }
+            if response.status().is_success() {
+                if let Ok(html) = response.text().await {
+                    if is_complete(&html) {
+                        self.http_pages.fetch_add(1, Ordering::Relaxed);
+                        return Ok(html);
+                    }
+                }
+            }
+        }
+
+        let browser = self.browser.get_or_init(|| async {
+            tokio::task::spawn_blocking(|| new_browser().map_err(|error| error.message()))
+                .await
+                .unwrap_or_else(|error| Err(error.to_string()))
+        }).await;
+        let browser = match browser {
+            Ok(browser) => browser.clone(),
+            Err(error) => return Err(LinkError::Browser(error.clone()))
+        };
+        self.browser_pages.fetch_add(1, Ordering::Relaxed);
+        get_page_html(page_link, &self.client, Some(cancellation_token), 0, browser).await
+    }
+}
 
 #[async_recursion]
 pub async fn get_page_html(
     page_link: &str,
     client: &Client,
-    cancel_receiver: Option<Receiver<bool>>,
+    cancellation_token: Option<CancellationToken>,
     try_count: u8,
     browser: Browser
 ) -> Result<String, LinkError> {
-    if let Some(receiver) = cancel_receiver.clone() {
-        if let Ok(_) | Err(TryRecvError::Disconnected) = receiver.try_recv() {
-            return Err(LinkError::Cancelled);
-        }
+    if cancellation_token.as_ref().is_some_and(|token| token.is_cancelled()) {
+        return Err(LinkError::Cancelled);
     }
 
     if try_count >= 10 {
@@ -27,40 +128,66 @@ pub async fn get_page_html(
     }
 
 
-    let mut tab = browser.new_tab().unwrap();
+    let tab = match browser.new_tab() {
+        Ok(tab) => tab,
+        Err(_) => return Err(LinkError::Other)
+    };
+    // Chrome does not accept proxy credentials on the command line, so answer the proxy's auth challenge instead
+    let proxy = network_settings().proxy_for(Service::MultiUp).clone();
+    if proxy.enabled && proxy.has_credentials() {
+        let authenticated = tab.enable_fetch(None, Some(true))
+            .and_then(|tab| tab.authenticate(Some(proxy.username.clone()), Some(proxy.password())));
+        if authenticated.is_err() {
+            let _ = tab.close(true);
+            return Err(LinkError::Other);
+        }
+    }
     // let tab = browser.new_context().unwrap().new_tab().unwrap();
     // tab.set_user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36 Edg/122.0.0.",
     //                    None, Some("Windows")).unwrap();
     // println!("Navigating to {}", page_link);
     // tab.enable_stealth_mode().unwrap();
-    tab.navigate_to(page_link).unwrap();
-
-    // let cookies = tab.get_cookies().unwrap().iter().map(|cookie| DeleteCookies {
-    //     name: cookie.name.to_string(),
-    //     url: Some(page_link.to_string()),
-    //     domain: Some(cookie.domain.to_string()),
-    //     path: Some(cookie.path.to_string()),
-    // }).collect();
-    // tab.delete_cookies(cookies).unwrap();
-    match tab.wait_for_element_with_custom_timeout("body > section > div > section > header > h2", Duration::from_secs(60)) {
-        Ok(_) => {}
-        Err(_) => {
-            // println!("ERROR HTML: {}", tab.get_content().unwrap());
-        }
-    }
 
-    return match tab.get_content() {
-        Ok(html) => {
-            // println!("HTML: {}", html);
-            tab.close(true).unwrap();
-            Ok(html)
+    // The tab is driven on a blocking thread so that cancellation can interrupt the wait for the page
+    let page_tab = tab.clone();
+    let page_link_owned = page_link.to_string();
+    let page_task = tokio::task::spawn_blocking(move || {
+        apply_session_settings(&page_tab, &network_settings())?;
+        page_tab.navigate_to(&page_link_owned).map_err(|_| LinkError::Other)?;
+
+        // let cookies = tab.get_cookies().unwrap().iter().map(|cookie| DeleteCookies {
+        //     name: cookie.name.to_string(),
+        //     url: Some(page_link.to_string()),
+        //     domain: Some(cookie.domain.to_string()),
+        //     path: Some(cookie.path.to_string()),
+        // }).collect();
+        // tab.delete_cookies(cookies).unwrap();
+        match page_tab.wait_for_element_with_custom_timeout("body > section > div > section > header > h2", browser_settings().page_timeout()) {
+            Ok(_) => {}
+            Err(_) => {
+                // println!("ERROR HTML: {}", tab.get_content().unwrap());
+            }
         }
-        Err(error) => {
-            // eprintln!("{}", error);
-            Err(LinkError::Other)
+
+        page_tab.get_content().map_err(|_| LinkError::Other)
+    });
+
+    let cancelled = async {
+        match &cancellation_token {
+            Some(token) => token.cancelled().await,
+            None => std::future::pending().await
         }
     };
 
+    let result = tokio::select! {
+        result = page_task => result.unwrap_or(Err(LinkError::Other)),
+        _ = cancelled => Err(LinkError::Cancelled)
+    };
+
+    // Close the tab whether the page loaded, failed or was cancelled, so tabs do not pile up
+    let _ = tab.close(true);
+    return result;
+
     // let server_response = match client.get(page_link).send().await {
     //     Ok(response) => response,
     //     Err(error) => return Err(LinkError::Reqwest(error))
@@ -73,24 +200,75 @@ pub async fn get_page_html(
     //         if error.status().unwrap() != StatusCode::NOT_FOUND {
     //             println!("{}", error);
     //             let _ = tokio::time::sleep(Duration::from_millis(100)).await;
-    //             return get_page_html(page_link, client, cancel_receiver, try_count + 1).await;
+    //             return get_page_html(page_link, client, cancellation_token, try_count + 1).await;
     //         }
     //         Err(LinkError::Invalid)
     //     }
     // };
 }
 
-pub fn new_browser() -> Browser {
-    let browser = Browser::new(
+/// Applies the user agent, extra headers and imported cookies to a tab before it navigates.
+fn apply_session_settings(tab: &Tab, settings: &NetworkSettings) -> Result<(), LinkError> {
+    if !settings.user_agent.trim().is_empty() {
+        tab.set_user_agent(settings.user_agent.trim(), None, None).map_err(|_| LinkError::Other)?;
+    }
+
+    let headers: HashMap<&str, &str> = settings.extra_headers.iter()
+        .filter(|(name, _)| !name.trim().is_empty())
+        .map(|(name, value)| (name.trim(), value.as_str()))
+        .collect();
+    if !headers.is_empty() {
+        tab.set_extra_http_headers(headers).map_err(|_| LinkError::Other)?;
+    }
+
+    if !settings.cookies.is_empty() {
+        let cookies = settings.cookies.iter()
+            .map(|cookie| CookieParam {
+                name: cookie.name.clone(),
+                value: cookie.value.clone(),
+                url: None,
+                domain: Some(cookie.domain.clone()),
+                path: Some(cookie.path.clone()),
+                secure: Some(cookie.secure),
+                http_only: Some(cookie.http_only),
+                same_site: None,
+                // An expiry of 0 marks a session cookie
+                expires: Some(cookie.expires as f64).filter(|expires| *expires > 0.0),
+                priority: None,
+                same_party: None,
+                source_scheme: None,
+                source_port: None,
+                partition_key: None,
+            })
+            .collect();
+        tab.set_cookies(cookies).map_err(|_| LinkError::Other)?;
+    }
+    Ok(())
+}
+
+/// Launches Chrome with the saved browser and network settings.
+///
+/// Returns an error instead of panicking if Chrome cannot be found or started.
+pub fn new_browser() -> Result<Browser, LinkError> {
+    let settings = browser_settings();
+    if let Some(path) = settings.path() {
+        if !path.is_file() {
+            return Err(LinkError::Browser(format!("Chrome was not found at {}. Check the path in Settings > Browser.", path.display())));
+        }
+    }
+
+    let proxy = network_settings().proxy_for(Service::MultiUp).clone();
+    let proxy_server = Some(proxy.url()).filter(|_| proxy.enabled);
+    Browser::new(
         LaunchOptions {
-            headless: false,
+            headless: settings.headless,
             sandbox: false,
             enable_gpu: false,
             enable_logging: false,
-            idle_browser_timeout: Duration::from_secs(60),
+            idle_browser_timeout: settings.idle_timeout(),
             window_size: None,
-            path: None,
-            user_data_dir: None,
+            path: settings.path(),
+            user_data_dir: settings.user_data_dir(),
             port: None,
             ignore_certificate_errors: true,
             extensions: Vec::new(),
@@ -124,11 +302,31 @@ pub fn new_browser() -> Browser {
                     "--password-store=basic",
                     "--use-mock-keychain",
                 ];
-                args.clone().iter().map(OsStr::new).collect()
+                args.iter()
+                    .copied()
+                    .chain(settings.extra_args())
+                    .map(OsStr::new)
+                    .collect()
             },
             disable_default_args: true,
-            proxy_server: None,
+            proxy_server: proxy_server.as_deref(),
+            devtools: false,
+            ignore_default_args: Vec::new(),
         }
-    ).unwrap();
-    return browser;
+    ).map_err(|error| LinkError::Browser(format!("Chrome could not be started: {}. Install Chrome or set its path in Settings > Browser.", error)))
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `1.50 GB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.2} {}", size, UNITS[unit]),
+    }
 }
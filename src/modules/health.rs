@@ -0,0 +1,381 @@
+use std::fs;
+use std::path::PathBuf;
+use eframe::egui::{CollapsingHeader, ComboBox, Context, DragValue, ScrollArea, Window};
+use egui_file::FileDialog;
+use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
+use crate::modules::links::{DownloadLink, MultiUpLink, ProjectLink};
+
+/// Represents the health of a single file within a project.
+/// Contains the file name, the MultiUp link and the hosts grouped by their validity.
+#[derive(Debug)]
+pub struct FileHealth {
+    pub file_name: String,
+    pub link: String,
+    pub valid_hosts: Vec<String>,
+    pub dead_hosts: Vec<String>,
+    pub unknown_hosts: Vec<String>,
+}
+
+impl FileHealth {
+    pub fn new(download_link: &DownloadLink) -> Self {
+        let file_name = download_link.link_information.as_ref()
+            .and_then(|information| information.file_name.clone())
+            .unwrap_or(download_link.link_id.clone());
+
+        let mut valid_hosts = vec![];
+        let mut dead_hosts = vec![];
+        let mut unknown_hosts = vec![];
+        if let Some(direct_links) = &download_link.direct_links {
+            for direct_link in direct_links {
+                match direct_link.validity.as_str() {
+                    "valid" => valid_hosts.push(direct_link.host.clone()),
+                    "invalid" => dead_hosts.push(direct_link.host.clone()),
+                    _ => unknown_hosts.push(direct_link.host.clone()),
+                }
+            }
+        }
+
+        Self {
+            file_name,
+            link: download_link.original_link.clone(),
+            valid_hosts,
+            dead_hosts,
+            unknown_hosts,
+        }
+    }
+
+    pub fn total_hosts(&self) -> usize {
+        self.valid_hosts.len() + self.dead_hosts.len() + self.unknown_hosts.len()
+    }
+}
+
+/// Represents a mirror health report for a MultiUp project.
+///
+/// The report is built from the validity values stored in `DownloadLink::direct_links`, so it is
+/// most accurate when "Recheck link validity" was enabled during extraction.
+#[derive(Debug)]
+pub struct ProjectHealthReport {
+    pub project_name: String,
+    pub project_link: String,
+    pub files: Vec<FileHealth>,
+    pub failed_files: Vec<String>,
+    pub threshold: usize,
+}
+
+impl ProjectHealthReport {
+    pub fn new(project_link: &ProjectLink, threshold: usize) -> Self {
+        let mut files = vec![];
+        let mut failed_files = vec![];
+        if let Some(download_links) = &project_link.download_links {
+            for download_link in download_links {
+                match &download_link.status {
+                    Some(Ok(())) => files.push(FileHealth::new(download_link)),
+                    _ => failed_files.push(download_link.original_link.clone()),
+                }
+            }
+        }
+        files.sort_by(|file_1, file_2| file_1.file_name.cmp(&file_2.file_name));
+
+        Self {
+            project_name: project_link.name.clone(),
+            project_link: project_link.original_link.clone(),
+            files,
+            failed_files,
+            threshold,
+        }
+    }
+
+    /// Returns the files which do not have any valid hosts.
+    pub fn files_without_valid_hosts(&self) -> Vec<&FileHealth> {
+        self.files.iter().filter(|file| file.valid_hosts.is_empty()).collect()
+    }
+
+    /// Returns the files which have at least one, but fewer than `threshold`, valid hosts.
+    pub fn files_below_threshold(&self) -> Vec<&FileHealth> {
+        self.files.iter()
+            .filter(|file| !file.valid_hosts.is_empty() && file.valid_hosts.len() < self.threshold)
+            .collect()
+    }
+
+    /// Calculates the overall health score of the project, from 0 to 100.
+    ///
+    /// Each file scores the proportion of the threshold that is covered by valid hosts (capped at 1),
+    /// and the project score is the average across all files. Files which failed to extract score 0.
+    pub fn health_score(&self) -> f32 {
+        let number_of_files = self.files.len() + self.failed_files.len();
+        if number_of_files == 0 {
+            return 0.0;
+        }
+
+        let threshold = self.threshold.max(1) as f32;
+        let total: f32 = self.files.iter()
+            .map(|file| (file.valid_hosts.len() as f32 / threshold).min(1.0))
+            .sum();
+        100.0 * total / number_of_files as f32
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("## {}\n\n", self.project_name);
+        markdown += &format!("Link: {}\n\n", self.project_link);
+        markdown += &format!("Health score: {:.1}%\n\n", self.health_score());
+        markdown += &format!("- Files: {}\n", self.files.len() + self.failed_files.len());
+        markdown += &format!("- Files with no valid hosts: {}\n", self.files_without_valid_hosts().len());
+        markdown += &format!("- Files with fewer than {} valid hosts: {}\n", self.threshold, self.files_below_threshold().len());
+        markdown += &format!("- Files that failed to extract: {}\n\n", self.failed_files.len());
+
+        markdown += "| File | Link | Valid | Dead | Unknown | Dead hosts |\n";
+        markdown += "| --- | --- | --- | --- | --- | --- |\n";
+        for file in &self.files {
+            markdown += &format!("| {} | {} | {} | {} | {} | {} |\n",
+                                 file.file_name.replace('|', "\\|"),
+                                 file.link,
+                                 file.valid_hosts.len(),
+                                 file.dead_hosts.len(),
+                                 file.unknown_hosts.len(),
+                                 file.dead_hosts.join(", "));
+        }
+
+        for link in &self.failed_files {
+            markdown += &format!("| (failed to extract) | {} | 0 | 0 | 0 | |\n", link);
+        }
+
+        markdown
+    }
+
+    /// Returns the report as CSV rows, without a header.
+    pub fn to_csv_rows(&self) -> String {
+        let mut csv = String::new();
+        for file in &self.files {
+            let status = if file.valid_hosts.is_empty() {
+                "no valid hosts"
+            } else if file.valid_hosts.len() < self.threshold {
+                "below threshold"
+            } else {
+                "healthy"
+            };
+            csv += &format!("{},{},{},{},{},{},{},{}\n",
+                            escape_csv(&self.project_name),
+                            escape_csv(&file.file_name),
+                            escape_csv(&file.link),
+                            file.valid_hosts.len(),
+                            file.dead_hosts.len(),
+                            file.unknown_hosts.len(),
+                            escape_csv(&file.dead_hosts.join(" ")),
+                            status);
+        }
+
+        for link in &self.failed_files {
+            csv += &format!("{},,{},0,0,0,,failed to extract\n", escape_csv(&self.project_name), escape_csv(link));
+        }
+
+        csv
+    }
+}
+
+const CSV_HEADER: &str = "project,file,link,valid_hosts,dead_hosts,unknown_hosts,dead_host_names,status\n";
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Csv,
+}
+
+impl ReportFormat {
+    fn extension(&self) -> &str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Creates health reports for every successfully extracted project in `links`.
+pub fn create_reports(links: &[MultiUpLink], threshold: usize) -> Vec<ProjectHealthReport> {
+    links.iter()
+        .filter_map(|link| match link {
+            MultiUpLink::Project(project) if matches!(project.status, Some(Ok(()))) => {
+                Some(ProjectHealthReport::new(project, threshold))
+            }
+            _ => None
+        })
+        .collect()
+}
+
+/// Joins the reports into a single document of the given format.
+pub fn export_reports(reports: &[ProjectHealthReport], format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Markdown => {
+            let mut markdown = String::from("# Mirror Health Report\n\n");
+            for report in reports {
+                markdown += &report.to_markdown();
+                markdown += "\n";
+            }
+            markdown
+        }
+        ReportFormat::Csv => {
+            let mut csv = CSV_HEADER.to_string();
+            for report in reports {
+                csv += &report.to_csv_rows();
+            }
+            csv
+        }
+    }
+}
+
+pub struct HealthReportUI {
+    pub show_report: bool,
+    threshold: usize,
+    format: ReportFormat,
+    save_file_dialog: Option<FileDialog>,
+}
+
+impl Default for HealthReportUI {
+    fn default() -> Self {
+        Self {
+            show_report: false,
+            threshold: 3,
+            format: ReportFormat::default(),
+            save_file_dialog: None,
+        }
+    }
+}
+
+impl HealthReportUI {
+    pub fn show_report_window(&mut self, ctx: &Context, links: &[MultiUpLink], toasts: &mut Toasts) {
+        let mut show_report = self.show_report;
+        Window::new("Mirror Health Report")
+            .default_width(600.0)
+            .open(&mut show_report)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Minimum valid hosts per file:");
+                    ui.add(DragValue::new(&mut self.threshold).clamp_range(1..=100));
+                });
+
+                let reports = create_reports(links, self.threshold);
+
+                ui.horizontal(|ui| {
+                    ui.label("Export as:");
+                    ComboBox::from_id_source("Health Report Format")
+                        .selected_text(match self.format {
+                            ReportFormat::Markdown => "Markdown",
+                            ReportFormat::Csv => "CSV",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.format, ReportFormat::Markdown, "Markdown");
+                            ui.selectable_value(&mut self.format, ReportFormat::Csv, "CSV");
+                        });
+
+                    if ui.button("Copy").clicked() {
+                        ui.output_mut(|output| output.copied_text = export_reports(&reports, self.format));
+                        toasts.add(Toast {
+                            text: "Copied health report".into(),
+                            kind: ToastKind::Info,
+                            options: ToastOptions::default()
+                                .duration_in_seconds(5.0)
+                                .show_progress(true)
+                                .show_icon(true)
+                        });
+                    }
+
+                    if ui.button("Save to file").clicked() {
+                        let mut dialog = FileDialog::save_file(None)
+                            .default_filename(format!("health_report.{}", self.format.extension()));
+                        dialog.open();
+                        self.save_file_dialog = Some(dialog);
+                    }
+                });
+
+                if let Some(dialog) = &mut self.save_file_dialog {
+                    if dialog.show(ctx).selected() {
+                        if let Some(path) = dialog.path() {
+                            save_report(path.to_path_buf(), &export_reports(&reports, self.format), toasts);
+                        }
+                        self.save_file_dialog = None;
+                    }
+                }
+
+                ui.separator();
+
+                if reports.is_empty() {
+                    ui.label("No extracted projects. Health reports are only available for project links.");
+                    return;
+                }
+
+                ScrollArea::vertical()
+                    .id_source("Health Report")
+                    .min_scrolled_height(ui.available_height())
+                    .show(ui, |ui| {
+                        for report in &reports {
+                            let header = format!("{} - {:.1}% healthy", report.project_name, report.health_score());
+                            CollapsingHeader::new(header)
+                                .id_source(&report.project_link)
+                                .default_open(true)
+                                .show(ui, |ui| {
+                                    let no_valid_hosts = report.files_without_valid_hosts();
+                                    ui.label(format!("Files with no valid hosts ({}):", no_valid_hosts.len()));
+                                    for file in no_valid_hosts {
+                                        ui.label(format!("    {} ({})", file.file_name, file.link));
+                                    }
+
+                                    let below_threshold = report.files_below_threshold();
+                                    ui.label(format!("Files with fewer than {} valid hosts ({}):", report.threshold, below_threshold.len()));
+                                    for file in below_threshold {
+                                        ui.label(format!("    {} - {}/{} valid", file.file_name, file.valid_hosts.len(), file.total_hosts()));
+                                    }
+
+                                    if !report.failed_files.is_empty() {
+                                        ui.label(format!("Files that failed to extract ({}):", report.failed_files.len()));
+                                        for link in &report.failed_files {
+                                            ui.label(format!("    {}", link));
+                                        }
+                                    }
+
+                                    CollapsingHeader::new("Dead hosts per file")
+                                        .id_source(format!("{} Dead Hosts", report.project_link))
+                                        .show(ui, |ui| {
+                                            for file in report.files.iter().filter(|file| !file.dead_hosts.is_empty()) {
+                                                ui.label(format!("{}: {}", file.file_name, file.dead_hosts.join(", ")));
+                                            }
+                                        });
+                                });
+                        }
+                    });
+            });
+        self.show_report = show_report;
+    }
+}
+
+fn save_report(path: PathBuf, report: &str, toasts: &mut Toasts) {
+    match fs::write(&path, report) {
+        Ok(_) => {
+            toasts.add(Toast {
+                text: format!("Saved health report to {}", path.display()).into(),
+                kind: ToastKind::Success,
+                options: ToastOptions::default()
+                    .duration_in_seconds(5.0)
+                    .show_progress(true)
+                    .show_icon(true)
+            });
+        }
+        Err(error) => {
+            toasts.add(Toast {
+                text: format!("Failed to save health report: {}", error).into(),
+                kind: ToastKind::Error,
+                options: ToastOptions::default()
+                    .duration_in_seconds(5.0)
+                    .show_progress(true)
+                    .show_icon(true)
+            });
+        }
+    }
+}
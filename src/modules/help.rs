@@ -83,15 +83,10 @@ impl HelpUI {
             ui.heading("Debrid");
             ui.label("Unlocks links using a Debrid service.\n\n\
             Currently supports AllDebrid and RealDebrid.\n\
-            To read the keys from a file, create \"api_key.json\" in the same directory as this app with the following structure:");
-            let mut json_example = "\
-            {\n\
-                \t\"all_debrid\": \"YOUR_ALLDEBRID_API_KEY\",\n\
-                \t\"real_debrid\": \"YOUR_REALDEBRID_API_KEY\"\n\
-            }";
-            ui.code_editor(&mut json_example);
-            ui.label("You can choose to omit any field here (i.e. only have all_debrid or real_debrid) \
-            if you do not have an API key for the service.");
+            Enter your API key and click \"Save key\" to keep it in the credential vault (Settings > Credential vault), \
+            which is encrypted with a master passphrase. Saved keys and your MultiUp login are filled in when the vault is unlocked \
+            and cleared again when it locks.\n\n\
+            Keys from an \"api_key.json\" file next to this app are moved into the vault when it is created.");
 
             ui.separator();
 
@@ -100,7 +95,26 @@ impl HelpUI {
             Remote uploaded with data streaming enabled allows for better support of different sites, including Debrid services. \
             Since this is an experimental feature, be careful when uploading large files.\n\
             Data streaming essentially downloads and uploads chunks of data, as if the file was downloaded \
-            to disk and then uploaded to MultiUp. However, in this case, the data is not written to disk.");
+            to disk and then uploaded to MultiUp. However, in this case, the data is not written to disk.\n\n\
+            Disk uploads and data streamed uploads show the progress, speed and estimated time left of each file. \
+            Cancelling a file cancels every file sent in the same request.\n\
+            Disk files can be uploaded separately, so each file is sent in its own request and retried if it fails.\n\n\
+            Uploads can be added to the upload queue, which runs them one after another and is kept when the app is closed. \
+            The queue can be paused, and the settings can limit it to certain hours and cap the upload rate of every upload.\n\n\
+            Upload folders includes the files in every subfolder, which are named after their path in the folder. \
+            Each folder can be queued as its own project, and include and exclude patterns such as *.mkv choose which files are uploaded.\n\n\
+            Before uploading, the selected hosts are checked against MultiUp's maximum number of hosts and each host's file size limit. \
+            Check file sizes shows which hosts will receive each file.\n\n\
+            Split into volumes packs files and folders into an archive split into volumes no larger than the smallest host limit, \
+            which are uploaded as one project and then deleted. Open the first volume with 7-Zip to extract the archive.\n\n\
+            The MD5 and SHA-256 checksums of each file are computed as it is sent and compared with the hash returned by MultiUp. \
+            SFV, MD5 and SHA-256 manifests of the uploaded files can be saved, or uploaded into the same project.\n\n\
+            After a remote upload, MultiUp still has to fetch the file and send it to each host. \
+            The hosts of each remote upload are checked until they are filled in, with an alert if no host changes for a while or none of them are valid.\n\n\
+            Verify links extracts the uploaded links and rechecks their validity, showing how many hosts came up for each link. \
+            The settings can run it automatically a while after each upload.\n\n\
+            Project descriptions can be saved as named templates. Placeholders such as {file_list}, {total_size}, {file_count}, {hashes}, \
+            {upload_date} and {host_list} are filled in from the files being uploaded when the project is created.");
         }));
     }
 
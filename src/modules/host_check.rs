@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashSet};
+use eframe::egui::{Context, Grid, ScrollArea, Window};
+use crate::modules::general::format_size;
+
+/// Returns `true` if a host with the limit in MB accepts a file of the given size in bytes.
+///
+/// A limit of 0 or an unknown size is accepted.
+pub fn host_accepts(limit: u64, size: Option<u64>) -> bool {
+    limit == 0 || size.is_none_or(|size| size <= limit * 1024 * 1024)
+}
+
+/// Returns the hosts which accept a file of the given size.
+pub fn hosts_for_file(hosts: &HashSet<String>, limits: &BTreeMap<String, u64>, size: u64) -> HashSet<String> {
+    hosts.iter()
+        .filter(|host| host_accepts(limits.get(*host).copied().unwrap_or_default(), Some(size)))
+        .cloned()
+        .collect()
+}
+
+/// Represents which of the selected hosts can receive each file.
+#[derive(Clone, Default)]
+pub struct HostMatrix {
+    /// The name and size in bytes of each file. The size of remote files is unknown.
+    pub files: Vec<(String, Option<u64>)>,
+    /// The selected hosts and their file size limits in MB.
+    pub hosts: Vec<(String, u64)>,
+    /// Each file is sent only to the hosts which accept it, as it is uploaded in its own request.
+    pub per_file_hosts: bool,
+}
+
+impl HostMatrix {
+    /// Returns the hosts which cannot accept at least one of the files.
+    pub fn rejecting_hosts(&self) -> Vec<String> {
+        self.hosts.iter()
+            .filter(|(_, limit)| self.files.iter().any(|(_, size)| !host_accepts(*limit, *size)))
+            .map(|(host, _)| host.clone())
+            .collect()
+    }
+
+    /// Returns the files which none of the selected hosts accept.
+    pub fn rejected_files(&self) -> Vec<String> {
+        self.files.iter()
+            .filter(|(_, size)| !self.hosts.iter().any(|(_, limit)| host_accepts(*limit, *size)))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Returns `true` if every file would be uploaded to every selected host.
+    pub fn is_complete(&self) -> bool {
+        self.rejecting_hosts().is_empty()
+    }
+}
+
+/// Represents what the user chose to do with an upload which some hosts cannot accept.
+pub enum HostCheckChoice {
+    DeselectAndUpload,
+    UploadAnyway,
+}
+
+#[derive(Default)]
+pub struct HostCheckUI {
+    pub show_window: bool,
+    matrix: HostMatrix,
+    /// Whether an upload is waiting for the user to choose what to do.
+    pending: bool,
+}
+
+impl HostCheckUI {
+    /// Shows the matrix, with a choice of how to continue if an upload is waiting.
+    pub fn open(&mut self, matrix: HostMatrix, pending: bool) {
+        self.matrix = matrix;
+        self.pending = pending;
+        self.show_window = true;
+    }
+
+    pub fn show_host_check_window(&mut self, ctx: &Context) -> Option<HostCheckChoice> {
+        let mut choice = None;
+        let mut show_window = self.show_window;
+        Window::new("Host Check")
+            .default_width(700.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let rejecting_hosts = self.matrix.rejecting_hosts();
+                let rejected_files = self.matrix.rejected_files();
+                if self.matrix.is_complete() {
+                    ui.label("Every selected host accepts every file.");
+                } else if self.matrix.per_file_hosts {
+                    ui.label("Files are uploaded separately, so each file is only sent to the hosts which accept it.");
+                } else {
+                    ui.label(format!("{} cannot accept some files because of their size limits. \
+                    Files uploaded together are sent to the same hosts.", rejecting_hosts.join(", ")));
+                }
+                if !rejected_files.is_empty() {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("No selected host accepts {}.", rejected_files.join(", ")));
+                }
+
+                if self.pending {
+                    ui.horizontal(|ui| {
+                        if !self.matrix.per_file_hosts && ui.button("Deselect these hosts and upload").clicked() {
+                            choice = Some(HostCheckChoice::DeselectAndUpload);
+                        }
+                        let upload_text = match self.matrix.per_file_hosts {
+                            true => "Upload the other files",
+                            false => "Upload anyway"
+                        };
+                        if ui.button(upload_text).clicked() {
+                            choice = Some(HostCheckChoice::UploadAnyway);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.pending = false;
+                            self.show_window = false;
+                        }
+                    });
+                }
+
+                ui.separator();
+                ScrollArea::both().id_source("Host Matrix").show(ui, |ui| {
+                    Grid::new("Host Matrix Grid")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("File");
+                            ui.strong("Size");
+                            for (host, limit) in &self.matrix.hosts {
+                                let limit_text = match limit {
+                                    0 => "No size limit".to_string(),
+                                    limit => format!("Up to {}", format_size(limit * 1024 * 1024))
+                                };
+                                ui.strong(host).on_hover_text(limit_text);
+                            }
+                            ui.end_row();
+
+                            for (name, size) in &self.matrix.files {
+                                ui.label(name);
+                                ui.label(size.map(format_size).unwrap_or("Unknown".to_string()));
+                                for (_, limit) in &self.matrix.hosts {
+                                    match host_accepts(*limit, *size) {
+                                        true => ui.label("✔"),
+                                        false => ui.colored_label(ui.visuals().error_fg_color, "✘")
+                                    };
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        if choice.is_some() {
+            self.pending = false;
+            show_window = false;
+        }
+        self.show_window = show_window && self.show_window;
+        choice
+    }
+}
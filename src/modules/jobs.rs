@@ -0,0 +1,313 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::{DateTime, Local};
+use eframe::egui::{Button, Context, Grid, ProgressBar, ScrollArea, Window};
+use tokio::runtime::Runtime;
+use tokio_util::sync::CancellationToken;
+use crate::modules::upload::UploadRequest;
+
+const MAX_FINISHED_JOBS: usize = 100;
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+static JOB_MANAGER: OnceLock<JobManager> = OnceLock::new();
+
+/// Returns the runtime shared by all background work.
+pub fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| Runtime::new().unwrap())
+}
+
+/// Returns the job manager which tracks all background jobs.
+pub fn job_manager() -> &'static JobManager {
+    JOB_MANAGER.get_or_init(JobManager::default)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JobKind {
+    Extract,
+    Unlock,
+    Upload,
+    Login,
+    Hosts,
+    ResolveLinks,
+    WatchlistCheck,
+    Replenish,
+    CloneProject,
+    ConnectivityTest,
+    BrowserTest,
+    DeleteUpload,
+    RemoteStatus,
+    VerifyUpload,
+}
+
+impl JobKind {
+    pub fn name(&self) -> &str {
+        match self {
+            JobKind::Extract => "Extract",
+            JobKind::Unlock => "Debrid unlock",
+            JobKind::Upload => "Upload",
+            JobKind::Login => "Login",
+            JobKind::Hosts => "Get hosts",
+            JobKind::ResolveLinks => "Resolve links",
+            JobKind::WatchlistCheck => "Watchlist check",
+            JobKind::Replenish => "Replenish",
+            JobKind::CloneProject => "Clone project",
+            JobKind::ConnectivityTest => "Connectivity test",
+            JobKind::BrowserTest => "Browser test",
+            JobKind::DeleteUpload => "Delete upload",
+            JobKind::RemoteStatus => "Remote upload status",
+            JobKind::VerifyUpload => "Verify upload",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobStatus {
+    Running,
+    Completed(String),
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn description(&self) -> String {
+        match self {
+            JobStatus::Running => "Running".to_string(),
+            JobStatus::Completed(summary) => format!("Completed: {}", summary),
+            JobStatus::Failed(error) => format!("Failed: {}", error),
+            JobStatus::Cancelled => "Cancelled".to_string(),
+        }
+    }
+}
+
+/// Represents the information needed to start a job again.
+#[derive(Clone)]
+pub enum JobRerun {
+    Extract(String),
+    Unlock(Vec<String>),
+    Upload(Box<UploadRequest>),
+    WatchlistCheck,
+}
+
+struct JobState {
+    status: JobStatus,
+    progress: (usize, usize),
+    finished: Option<DateTime<Local>>,
+}
+
+/// Represents a background job.
+/// Contains the type of job, when it started, its progress, a handle to cancel it and its result.
+pub struct Job {
+    pub id: u64,
+    pub kind: JobKind,
+    pub description: String,
+    pub started: DateTime<Local>,
+    pub cancellation_token: CancellationToken,
+    pub rerun: Option<JobRerun>,
+    state: Mutex<JobState>,
+}
+
+impl Job {
+    /// Sets the number of steps in the job. Jobs without steps show an indeterminate progress.
+    pub fn set_total(&self, total: usize) {
+        self.state.lock().unwrap().progress.1 = total;
+    }
+
+    /// Marks a step of the job as done.
+    pub fn advance(&self) {
+        self.state.lock().unwrap().progress.0 += 1;
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        self.state.lock().unwrap().progress
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.state.lock().unwrap().status.clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status() == JobStatus::Running
+    }
+
+    /// Records the result of the job. A job which was cancelled is always recorded as cancelled.
+    pub fn finish(&self, status: JobStatus) {
+        let mut state = self.state.lock().unwrap();
+        state.status = if self.cancellation_token.is_cancelled() {
+            JobStatus::Cancelled
+        } else {
+            status
+        };
+        state.finished = Some(Local::now());
+    }
+
+    /// Returns how long the job ran for, or has been running for so far.
+    pub fn duration(&self) -> chrono::Duration {
+        self.state.lock().unwrap().finished.unwrap_or_else(Local::now) - self.started
+    }
+
+    pub fn cancel(&self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+#[derive(Default)]
+pub struct JobManager {
+    jobs: Mutex<Vec<Arc<Job>>>,
+    next_id: AtomicU64,
+}
+
+impl JobManager {
+    /// Registers a new running job without spawning it.
+    ///
+    /// The caller is responsible for calling [`Job::finish`] once the work is done.
+    pub fn start_job(&self, kind: JobKind, description: String, rerun: Option<JobRerun>) -> Arc<Job> {
+        let job = Arc::new(Job {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            kind,
+            description,
+            started: Local::now(),
+            cancellation_token: CancellationToken::new(),
+            rerun,
+            state: Mutex::new(JobState {
+                status: JobStatus::Running,
+                progress: (0, 0),
+                finished: None,
+            }),
+        });
+
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.push(Arc::clone(&job));
+        // Drop the oldest finished jobs so the list does not grow forever
+        let finished = jobs.iter().filter(|job| !job.is_running()).count();
+        if finished > MAX_FINISHED_JOBS {
+            let mut to_remove = finished - MAX_FINISHED_JOBS;
+            jobs.retain(|job| {
+                if to_remove > 0 && !job.is_running() {
+                    to_remove -= 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        job
+    }
+
+    pub fn jobs(&self) -> Vec<Arc<Job>> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    pub fn running_jobs(&self) -> usize {
+        self.jobs.lock().unwrap().iter().filter(|job| job.is_running()).count()
+    }
+
+    pub fn clear_finished(&self) {
+        self.jobs.lock().unwrap().retain(|job| job.is_running());
+    }
+}
+
+/// Registers a job and runs it on the shared runtime.
+///
+/// The task is given the job so it can report progress and watch for cancellation.
+/// The status returned by the task is recorded as the result of the job.
+pub fn spawn_job<F, Fut>(kind: JobKind, description: String, rerun: Option<JobRerun>, task: F) -> Arc<Job>
+where
+    F: FnOnce(Arc<Job>) -> Fut,
+    Fut: Future<Output = JobStatus> + Send + 'static,
+{
+    let job = job_manager().start_job(kind, description, rerun);
+    let future = task(Arc::clone(&job));
+    let spawned_job = Arc::clone(&job);
+    runtime().spawn(async move {
+        let status = future.await;
+        spawned_job.finish(status);
+    });
+    job
+}
+
+#[derive(Default)]
+pub struct JobsUI {
+    pub show_window: bool,
+    reruns: Vec<JobRerun>,
+}
+
+impl JobsUI {
+    /// Returns the jobs which the user asked to run again.
+    pub fn take_reruns(&mut self) -> Vec<JobRerun> {
+        std::mem::take(&mut self.reruns)
+    }
+
+    pub fn show_jobs_window(&mut self, ctx: &Context) {
+        let jobs = job_manager().jobs();
+        if jobs.iter().any(|job| job.is_running()) {
+            ctx.request_repaint_after(std::time::Duration::from_millis(500));
+        }
+
+        let mut show_window = self.show_window;
+        Window::new("Jobs")
+            .default_width(800.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} running, {} total", jobs.iter().filter(|job| job.is_running()).count(), jobs.len()));
+                    if ui.button("Clear finished").clicked() {
+                        job_manager().clear_finished();
+                    }
+                });
+
+                ui.separator();
+
+                ScrollArea::both()
+                    .id_source("Jobs List")
+                    .min_scrolled_height(ui.available_height())
+                    .show(ui, |ui| {
+                        Grid::new("Jobs Grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("ID");
+                                ui.strong("Type");
+                                ui.strong("Description");
+                                ui.strong("Started");
+                                ui.strong("Progress");
+                                ui.strong("Status");
+                                ui.strong("");
+                                ui.end_row();
+
+                                for job in jobs.iter().rev() {
+                                    let status = job.status();
+                                    let (done, total) = job.progress();
+                                    ui.label(job.id.to_string());
+                                    ui.label(job.kind.name());
+                                    ui.label(&job.description);
+                                    ui.label(format!("{} ({}s)", job.started.format("%H:%M:%S"), job.duration().num_seconds()));
+                                    if total > 0 {
+                                        ui.add(ProgressBar::new(done as f32 / total as f32)
+                                            .desired_width(120.0)
+                                            .text(format!("{}/{}", done, total)));
+                                    } else if status == JobStatus::Running {
+                                        ui.spinner();
+                                    } else {
+                                        ui.label("-");
+                                    }
+                                    ui.label(status.description());
+
+                                    ui.horizontal(|ui| {
+                                        let running = status == JobStatus::Running;
+                                        if ui.add_enabled(running && !job.cancellation_token.is_cancelled(), Button::new("Cancel")).clicked() {
+                                            job.cancel();
+                                        }
+                                        if let Some(rerun) = &job.rerun {
+                                            if ui.add_enabled(!running, Button::new("Re-run")).clicked() {
+                                                self.reruns.push(rerun.clone());
+                                            }
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        self.show_window = show_window;
+    }
+}
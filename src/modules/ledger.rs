@@ -0,0 +1,394 @@
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use chrono::{Local, TimeZone};
+use crossbeam_channel::{Receiver, Sender};
+use eframe::egui::{Button, Context, Grid, ScrollArea, TextEdit, Window};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::modules::api::{MultiUpUploadResponses, UploadedFileDetails};
+use crate::modules::general::format_size;
+use crate::modules::jobs::{JobKind, JobStatus, spawn_job};
+use crate::modules::links::LinkError;
+use crate::modules::network::{self, Service};
+use crate::modules::settings::{config_path, load_config_file};
+
+const LEDGER_FILE: &str = "uploads.json";
+
+/// Represents the result of deleting the file of a history entry.
+type DeleteResult = (u64, Result<(), LinkError>);
+
+static LEDGER: OnceLock<Mutex<Ledger>> = OnceLock::new();
+
+/// Represents a single uploaded file, or a failed upload, in the upload history.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LedgerEntry {
+    pub id: u64,
+    /// When the upload finished (UNIX timestamp).
+    pub time: i64,
+    pub profile: Option<String>,
+    pub username: Option<String>,
+    pub remote: bool,
+    /// The path or URL which was uploaded.
+    pub source: Option<String>,
+    pub file_name: Option<String>,
+    pub size: Option<u64>,
+    pub hash: Option<String>,
+    pub project_hash: Option<String>,
+    pub hosts: Vec<String>,
+    pub url: Option<String>,
+    pub delete_url: Option<String>,
+    pub delete_type: Option<String>,
+    pub error: Option<String>,
+    pub deleted: bool,
+}
+
+impl LedgerEntry {
+    fn matches(&self, search: &str) -> bool {
+        [&self.file_name, &self.url, &self.source, &self.profile, &self.username, &self.project_hash, &self.hash]
+            .iter()
+            .filter_map(|field| field.as_deref())
+            .any(|field| field.to_lowercase().contains(search))
+    }
+
+    pub fn project_link(&self) -> Option<String> {
+        self.project_hash.as_ref().map(|hash| format!("https://multiup.io/en/project/{}", hash))
+    }
+}
+
+/// Represents who uploaded a set of files and where, which is shared by every entry of an upload.
+#[derive(Clone, Default)]
+pub struct UploadContext {
+    pub profile: Option<String>,
+    pub username: Option<String>,
+    pub remote: bool,
+    pub hosts: Vec<String>,
+    /// The paths or URLs in the order they were uploaded.
+    pub sources: Vec<String>,
+}
+
+impl UploadContext {
+    /// Creates history entries for every file in the upload responses.
+    pub fn entries(&self, responses: &[MultiUpUploadResponses]) -> Vec<LedgerEntry> {
+        let time = Local::now().timestamp();
+        let mut entries = vec![];
+        // Each response belongs to one source, unless all files were uploaded in a single request
+        let sources_match = responses.len() == self.sources.len();
+        for (index, response) in responses.iter().enumerate() {
+            let base = LedgerEntry {
+                time,
+                profile: self.profile.clone(),
+                username: self.username.clone(),
+                remote: self.remote,
+                source: self.sources.get(index).cloned().filter(|_| sources_match),
+                hosts: self.hosts.clone(),
+                ..Default::default()
+            };
+            match response {
+                MultiUpUploadResponses::MultiUpFileUpload(Ok(file_upload)) => {
+                    let files_match = !sources_match && file_upload.files.len() == self.sources.len();
+                    for (file_index, file) in file_upload.files.iter().enumerate() {
+                        entries.push(LedgerEntry {
+                            project_hash: file_upload.project_hash.clone(),
+                            source: match files_match {
+                                true => self.sources.get(file_index).cloned(),
+                                false => base.source.clone()
+                            },
+                            ..file_entry(&base, file)
+                        });
+                    }
+                }
+                MultiUpUploadResponses::MultiUpRemoteUpload(Ok(remote_upload)) => {
+                    entries.push(LedgerEntry {
+                        file_name: remote_upload.file_name.clone(),
+                        size: remote_upload.size.as_ref().and_then(|size| size.parse().ok()),
+                        project_hash: remote_upload.project_hash.clone(),
+                        url: remote_upload.link.clone(),
+                        error: Some(remote_upload.error.clone()).filter(|error| error != "success"),
+                        ..base
+                    });
+                }
+                MultiUpUploadResponses::MultiUpFileUpload(Err(error)) | MultiUpUploadResponses::MultiUpRemoteUpload(Err(error)) => {
+                    // Cancelled uploads were never sent, so there is nothing to record
+                    if !matches!(error, LinkError::Cancelled) {
+                        entries.push(LedgerEntry {
+                            error: Some(error.message()),
+                            ..base
+                        });
+                    }
+                }
+            }
+        }
+        entries
+    }
+}
+
+fn file_entry(base: &LedgerEntry, file: &UploadedFileDetails) -> LedgerEntry {
+    LedgerEntry {
+        file_name: file.name.clone(),
+        size: file.size,
+        hash: file.hash.clone(),
+        url: file.url.clone(),
+        delete_url: file.delete_url.clone(),
+        delete_type: file.delete_type.clone(),
+        ..base.clone()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Ledger {
+    entries: Vec<LedgerEntry>,
+    next_id: u64,
+    /// Why the history file could not be read, in which case it was kept as a backup.
+    #[serde(skip)]
+    load_error: Option<String>,
+}
+
+impl Ledger {
+    fn load() -> Self {
+        let (mut ledger, load_error): (Self, _) = load_config_file(LEDGER_FILE);
+        ledger.load_error = load_error;
+        ledger
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = config_path(LEDGER_FILE);
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+        }
+        let json_string = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        fs::write(path, json_string).map_err(|error| error.to_string())
+    }
+}
+
+fn ledger() -> &'static Mutex<Ledger> {
+    LEDGER.get_or_init(|| Mutex::new(Ledger::load()))
+}
+
+/// Adds the entries to the upload history and saves it.
+pub fn record_uploads(entries: Vec<LedgerEntry>) -> Result<(), String> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut ledger = ledger().lock().unwrap();
+    for mut entry in entries {
+        ledger.next_id += 1;
+        entry.id = ledger.next_id;
+        ledger.entries.push(entry);
+    }
+    ledger.save()
+}
+
+pub fn ledger_entries() -> Vec<LedgerEntry> {
+    ledger().lock().unwrap().entries.clone()
+}
+
+fn update_entry(id: u64, update: impl FnOnce(&mut LedgerEntry)) -> Result<(), String> {
+    let mut ledger = ledger().lock().unwrap();
+    if let Some(entry) = ledger.entries.iter_mut().find(|entry| entry.id == id) {
+        update(entry);
+    }
+    ledger.save()
+}
+
+fn remove_entry(id: u64) -> Result<(), String> {
+    let mut ledger = ledger().lock().unwrap();
+    ledger.entries.retain(|entry| entry.id != id);
+    ledger.save()
+}
+
+/// Deletes the uploaded file from MultiUp through its delete URL.
+///
+/// The request method is given by the delete type of the upload response, and defaults to GET.
+/// MultiUp answers failed deletions with an HTML page and a success status, so only a JSON answer
+/// which reports no failure counts as deleted.
+async fn delete_upload(delete_url: &str, delete_type: Option<&str>) -> Result<(), LinkError> {
+    let client = network::client(Service::Upload).map_err(LinkError::APIError)?;
+    let request = match delete_type.map(|delete_type| delete_type.to_uppercase()).as_deref() {
+        Some("DELETE") => client.delete(delete_url),
+        Some("POST") => client.post(delete_url),
+        _ => client.get(delete_url),
+    };
+    let response = request.send().await.map_err(LinkError::Reqwest)?;
+    if !response.status().is_success() {
+        return Err(LinkError::APIError(format!("MultiUp returned {}", response.status())));
+    }
+    let body = response.text().await.map_err(LinkError::Reqwest)?;
+    let value: Value = serde_json::from_str(&body)
+        .map_err(|_| LinkError::APIError("MultiUp did not confirm the deletion".to_string()))?;
+    match value.get("error").and_then(Value::as_str) {
+        Some(error) if error != "success" => Err(LinkError::APIError(error.to_string())),
+        _ if reports_failure(&value) => Err(LinkError::APIError("MultiUp did not delete the file".to_string())),
+        _ => Ok(())
+    }
+}
+
+/// Returns whether any value in the answer is `false`, which is how each file's deletion is reported.
+fn reports_failure(value: &Value) -> bool {
+    match value {
+        Value::Bool(deleted) => !deleted,
+        Value::Array(values) => values.iter().any(reports_failure),
+        Value::Object(values) => values.values().any(reports_failure),
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+pub struct LedgerUI {
+    pub show_window: bool,
+    search: String,
+    delete_channel: Option<(Sender<DeleteResult>, Receiver<DeleteResult>)>,
+    deleting: Vec<u64>,
+    /// The entry whose Delete button was clicked, which is deleted once it is confirmed.
+    confirm_delete: Option<u64>,
+    message: Option<Result<String, String>>,
+}
+
+impl LedgerUI {
+    pub fn show_ledger_window(&mut self, ctx: &Context) {
+        if let Some((_, receiver)) = &self.delete_channel {
+            while let Ok((id, result)) = receiver.try_recv() {
+                self.deleting.retain(|deleting| *deleting != id);
+                self.message = Some(match result {
+                    Ok(()) => update_entry(id, |entry| entry.deleted = true).map(|_| "Deleted the file from MultiUp".to_string()),
+                    Err(error) => Err(format!("Failed to delete the file: {}", error.message())),
+                });
+            }
+        }
+
+        let mut show_window = self.show_window;
+        Window::new("Upload History")
+            .default_width(900.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let search = self.search.to_lowercase();
+                let entries: Vec<LedgerEntry> = ledger_entries().into_iter()
+                    .rev()
+                    .filter(|entry| search.is_empty() || entry.matches(&search))
+                    .collect();
+
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    ui.add(TextEdit::singleline(&mut self.search)
+                        .hint_text("File name, link, account or project")
+                        .desired_width(300.0));
+                    ui.label(format!("{} uploads", entries.len()));
+                    if ui.add_enabled(!entries.is_empty(), Button::new("Copy links")).clicked() {
+                        let links: Vec<&str> = entries.iter().filter_map(|entry| entry.url.as_deref()).collect();
+                        ui.output_mut(|output| output.copied_text = links.join("\n"));
+                    }
+                });
+
+                if let Some(error) = &ledger().lock().unwrap().load_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                match &self.message {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    None => {}
+                }
+
+                ui.separator();
+
+                ScrollArea::both()
+                    .id_source("Upload History")
+                    .min_scrolled_height(ui.available_height())
+                    .show(ui, |ui| {
+                        Grid::new("Upload History Grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("Time");
+                                ui.strong("Account");
+                                ui.strong("File");
+                                ui.strong("Size");
+                                ui.strong("Hosts");
+                                ui.strong("Link");
+                                ui.strong("");
+                                ui.end_row();
+
+                                for entry in &entries {
+                                    let time = Local.timestamp_opt(entry.time, 0).single()
+                                        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                                        .unwrap_or_default();
+                                    ui.label(time);
+                                    ui.label(entry.profile.as_deref().or(entry.username.as_deref()).unwrap_or("Anonymous"));
+                                    ui.label(entry.file_name.as_deref().or(entry.source.as_deref()).unwrap_or("-"))
+                                        .on_hover_text(entry.source.as_deref().unwrap_or_default());
+                                    ui.label(entry.size.map(format_size).unwrap_or_default());
+                                    ui.label(entry.hosts.len().to_string())
+                                        .on_hover_text(entry.hosts.join(", "));
+                                    match (&entry.url, &entry.error) {
+                                        (_, Some(error)) => {
+                                            ui.colored_label(ui.visuals().error_fg_color, error);
+                                        }
+                                        (Some(url), None) if entry.deleted => {
+                                            ui.label(format!("{} (deleted)", url));
+                                        }
+                                        (Some(url), None) => {
+                                            ui.hyperlink(url);
+                                        }
+                                        (None, None) => {
+                                            ui.label("-");
+                                        }
+                                    }
+
+                                    ui.horizontal(|ui| {
+                                        if let Some(url) = &entry.url {
+                                            if ui.button("Copy").clicked() {
+                                                ui.output_mut(|output| output.copied_text = url.clone());
+                                            }
+                                        }
+                                        if let Some(project_link) = entry.project_link() {
+                                            if ui.button("Copy project").clicked() {
+                                                ui.output_mut(|output| output.copied_text = project_link);
+                                            }
+                                        }
+                                        if let Some(delete_url) = &entry.delete_url {
+                                            let can_delete = !entry.deleted && !self.deleting.contains(&entry.id);
+                                            if self.confirm_delete == Some(entry.id) && can_delete {
+                                                if ui.button("Confirm delete").on_hover_text("Deletes the file from MultiUp, which cannot be undone").clicked() {
+                                                    let (delete_sender, _) = self.delete_channel.get_or_insert_with(crossbeam_channel::unbounded);
+                                                    spawn_delete_job(entry, delete_url.clone(), delete_sender.clone());
+                                                    self.deleting.push(entry.id);
+                                                    self.confirm_delete = None;
+                                                }
+                                                if ui.button("Cancel").clicked() {
+                                                    self.confirm_delete = None;
+                                                }
+                                            } else if ui.add_enabled(can_delete, Button::new("Delete")).clicked() {
+                                                self.confirm_delete = Some(entry.id);
+                                            }
+                                        }
+                                        if ui.button("Forget").on_hover_text("Removes the entry from the history without deleting the file").clicked() {
+                                            self.message = remove_entry(entry.id).err().map(Err);
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        self.show_window = show_window;
+    }
+}
+
+/// Deletes the file of the entry as a background job and sends the result back to the history window.
+fn spawn_delete_job(entry: &LedgerEntry, delete_url: String, delete_sender: Sender<DeleteResult>) {
+    let id = entry.id;
+    let delete_type = entry.delete_type.clone();
+    let description = format!("Delete {}", entry.file_name.as_deref().unwrap_or(&delete_url));
+    spawn_job(JobKind::DeleteUpload, description, None, move |_| async move {
+        let result = delete_upload(&delete_url, delete_type.as_deref()).await;
+        let status = match &result {
+            Ok(()) => JobStatus::Completed("Deleted".to_string()),
+            Err(error) => JobStatus::Failed(error.message())
+        };
+        let _ = delete_sender.send((id, result));
+        status
+    });
+}
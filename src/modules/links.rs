@@ -138,7 +138,10 @@ impl DirectLink {
 #[derive(Debug)]
 pub enum LinkError {
     APIError(String),
+    Browser(String),
     Cancelled,
+    /// A local file could not be read. Contains the path and the error.
+    File(String),
     Invalid,
     InQueue,
     NoLinks,
@@ -146,3 +149,68 @@ pub enum LinkError {
     Reqwest(reqwest::Error),
     TimedOut
 }
+
+impl LinkError {
+    pub fn kind(&self) -> LinkErrorKind {
+        match self {
+            LinkError::APIError(_) => LinkErrorKind::APIError,
+            LinkError::Browser(_) => LinkErrorKind::Browser,
+            LinkError::Cancelled => LinkErrorKind::Cancelled,
+            LinkError::File(_) => LinkErrorKind::File,
+            LinkError::Invalid => LinkErrorKind::Invalid,
+            LinkError::InQueue => LinkErrorKind::InQueue,
+            LinkError::NoLinks => LinkErrorKind::NoLinks,
+            LinkError::Other => LinkErrorKind::Other,
+            LinkError::Reqwest(_) => LinkErrorKind::Reqwest,
+            LinkError::TimedOut => LinkErrorKind::TimedOut,
+        }
+    }
+
+    /// Returns a human-readable description of the error.
+    pub fn message(&self) -> String {
+        match self {
+            LinkError::APIError(error) => error.to_string(),
+            LinkError::Browser(error) => error.to_string(),
+            LinkError::Cancelled => "Cancelled by the user".to_string(),
+            LinkError::File(error) => error.to_string(),
+            LinkError::Invalid => "Invalid link".to_string(),
+            LinkError::InQueue => "File is still in the MultiUp queue".to_string(),
+            LinkError::NoLinks => "No links found".to_string(),
+            LinkError::Other => "Unknown error".to_string(),
+            LinkError::Reqwest(error) => error.to_string(),
+            LinkError::TimedOut => "Timed out".to_string(),
+        }
+    }
+}
+
+/// Represents the kind of a `LinkError`, without any of its data.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LinkErrorKind {
+    APIError,
+    Browser,
+    Cancelled,
+    File,
+    Invalid,
+    InQueue,
+    NoLinks,
+    Other,
+    Reqwest,
+    TimedOut
+}
+
+impl LinkErrorKind {
+    pub fn name(&self) -> &str {
+        match self {
+            LinkErrorKind::APIError => "API error",
+            LinkErrorKind::Browser => "Browser error",
+            LinkErrorKind::Cancelled => "Cancelled",
+            LinkErrorKind::File => "File error",
+            LinkErrorKind::Invalid => "Invalid",
+            LinkErrorKind::InQueue => "In queue",
+            LinkErrorKind::NoLinks => "No links",
+            LinkErrorKind::Other => "Other",
+            LinkErrorKind::Reqwest => "Network error",
+            LinkErrorKind::TimedOut => "Timed out",
+        }
+    }
+}
@@ -1,9 +1,30 @@
+mod accounts;
 mod api;
 pub mod app;
+mod browser;
+mod checksums;
+mod clone_project;
 mod debrid;
+mod error_log;
 mod extract;
 mod filter;
+mod folders;
 mod general;
+mod health;
 mod help;
+mod host_check;
+mod jobs;
+mod ledger;
 mod links;
+mod network;
+mod progress;
+mod queue;
+mod remote_status;
+mod replenish;
+mod settings;
+mod split;
+mod templates;
 pub mod upload;
+mod vault;
+mod verification;
+mod watchlist;
@@ -0,0 +1,522 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::Instant;
+use crossbeam_channel::Receiver;
+use eframe::egui::{Button, ComboBox, Context, DragValue, Grid, TextEdit, Ui, Window};
+use egui_file::FileDialog;
+use reqwest::{Client, Proxy, Url};
+use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use crate::modules::jobs::{JobKind, JobStatus, spawn_job};
+use crate::modules::settings::{settings, update_settings};
+use crate::modules::vault;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ProxyKind {
+    #[default]
+    Http,
+    Https,
+    Socks5,
+}
+
+impl ProxyKind {
+    pub fn name(&self) -> &str {
+        match self {
+            ProxyKind::Http => "HTTP",
+            ProxyKind::Https => "HTTPS",
+            ProxyKind::Socks5 => "SOCKS5",
+        }
+    }
+
+    fn scheme(&self) -> &str {
+        match self {
+            ProxyKind::Http => "http",
+            ProxyKind::Https => "https",
+            ProxyKind::Socks5 => "socks5",
+        }
+    }
+}
+
+/// Represents a proxy server, along with optional credentials.
+///
+/// The password is kept in the credential vault. It is read from settings saved by older versions,
+/// but never written to the settings file or to exported settings.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ProxySettings {
+    pub enabled: bool,
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default, skip_serializing)]
+    pub password: String,
+}
+
+impl ProxySettings {
+    /// Returns the proxy URL without credentials, e.g. `socks5://127.0.0.1:1080`.
+    pub fn url(&self) -> String {
+        format!("{}://{}:{}", self.kind.scheme(), self.host.trim(), self.port)
+    }
+
+    pub fn has_credentials(&self) -> bool {
+        !self.username.is_empty()
+    }
+
+    /// Returns the key of the password in the vault, e.g. `user@socks5://127.0.0.1:1080`.
+    pub fn vault_key(&self) -> String {
+        format!("{}@{}", self.username, self.url())
+    }
+
+    /// Returns the password entered since the app started, or the one saved in the vault if it is unlocked.
+    pub fn password(&self) -> String {
+        if !self.password.is_empty() {
+            return self.password.clone();
+        }
+        vault::credentials()
+            .and_then(|credentials| credentials.proxy_passwords.get(&self.vault_key()).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Creates a reqwest proxy for all traffic, or `None` if the proxy is disabled.
+    pub fn reqwest_proxy(&self) -> Result<Option<Proxy>, String> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        if self.host.trim().is_empty() {
+            return Err("No proxy host".to_string());
+        }
+
+        let mut proxy = Proxy::all(self.url()).map_err(|error| error.to_string())?;
+        if self.has_credentials() {
+            proxy = proxy.basic_auth(&self.username, &self.password());
+        }
+        Ok(Some(proxy))
+    }
+}
+
+/// Represents a service which can be given its own proxy.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Service {
+    MultiUp,
+    AllDebrid,
+    RealDebrid,
+    Upload,
+}
+
+impl Service {
+    pub const ALL: [Service; 4] = [Service::MultiUp, Service::AllDebrid, Service::RealDebrid, Service::Upload];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Service::MultiUp => "MultiUp",
+            Service::AllDebrid => "AllDebrid",
+            Service::RealDebrid => "RealDebrid",
+            Service::Upload => "Upload",
+        }
+    }
+
+    /// Returns a URL which is requested to test connectivity to the service.
+    fn test_url(&self) -> &str {
+        match self {
+            Service::MultiUp => "https://multiup.io/api/get-list-hosts",
+            Service::AllDebrid => "https://api.alldebrid.com/v4/ping",
+            Service::RealDebrid => "https://api.real-debrid.com/rest/1.0/time",
+            Service::Upload => "https://multiup.io/api/get-fastest-server",
+        }
+    }
+}
+
+/// Represents a cookie imported from a Netscape `cookies.txt` file.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ImportedCookie {
+    pub domain: String,
+    pub include_subdomains: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    pub expires: i64,
+    pub name: String,
+    pub value: String,
+}
+
+impl ImportedCookie {
+    /// Returns the domain without the leading dot used for subdomain cookies.
+    pub fn host(&self) -> &str {
+        self.domain.trim_start_matches('.')
+    }
+
+    /// Returns a URL the cookie applies to, which is needed to add it to a cookie jar.
+    pub fn url(&self) -> String {
+        let scheme = if self.secure { "https" } else { "http" };
+        format!("{}://{}{}", scheme, self.host(), self.path)
+    }
+
+    fn set_cookie_string(&self) -> String {
+        let mut cookie = format!("{}={}; Path={}", self.name, self.value, self.path);
+        if self.include_subdomains {
+            cookie += &format!("; Domain={}", self.host());
+        }
+        if self.secure {
+            cookie += "; Secure";
+        }
+        if self.http_only {
+            cookie += "; HttpOnly";
+        }
+        cookie
+    }
+}
+
+/// Parses the contents of a Netscape `cookies.txt` file, as exported by most browser extensions.
+///
+/// Each line has seven tab-separated fields: domain, include subdomains, path, secure, expiry, name and value.
+/// Lines starting with `#` are comments, except for `#HttpOnly_` which marks HTTP-only cookies.
+pub fn parse_cookies_txt(contents: &str) -> Result<Vec<ImportedCookie>, String> {
+    let mut cookies = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(format!("Line {} does not have 7 tab-separated fields", index + 1));
+        }
+        cookies.push(ImportedCookie {
+            domain: fields[0].to_string(),
+            include_subdomains: fields[1].eq_ignore_ascii_case("TRUE"),
+            path: fields[2].to_string(),
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            http_only,
+            expires: fields[4].parse().map_err(|_| format!("Line {} has an invalid expiry", index + 1))?,
+            name: fields[5].to_string(),
+            value: fields[6].to_string(),
+        });
+    }
+    Ok(cookies)
+}
+
+/// Represents the network settings used by Chrome and every HTTP client.
+///
+/// The global proxy is used unless the service has an enabled override.
+/// The user agent, extra headers and imported cookies apply to every service.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct NetworkSettings {
+    pub proxy: ProxySettings,
+    #[serde(default)]
+    pub overrides: BTreeMap<Service, ProxySettings>,
+    #[serde(default)]
+    pub user_agent: String,
+    #[serde(default)]
+    pub extra_headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub cookies: Vec<ImportedCookie>,
+}
+
+impl NetworkSettings {
+    /// Returns the proxy settings which apply to the service.
+    pub fn proxy_for(&self, service: Service) -> &ProxySettings {
+        match self.overrides.get(&service) {
+            Some(proxy) if proxy.enabled => proxy,
+            _ => &self.proxy
+        }
+    }
+
+    /// Checks that every enabled proxy can be used.
+    pub fn validate(&self) -> Result<(), String> {
+        self.proxy.reqwest_proxy().map_err(|error| format!("Proxy: {}", error))?;
+        for (service, proxy) in &self.overrides {
+            proxy.reqwest_proxy().map_err(|error| format!("{} proxy: {}", service.name(), error))?;
+        }
+        self.headers()?;
+        Ok(())
+    }
+
+    /// Returns the user agent and extra headers which are sent with every request.
+    pub fn headers(&self) -> Result<HeaderMap, String> {
+        let mut headers = HeaderMap::new();
+        if !self.user_agent.trim().is_empty() {
+            let user_agent = HeaderValue::from_str(self.user_agent.trim()).map_err(|_| "Invalid user agent".to_string())?;
+            headers.insert(USER_AGENT, user_agent);
+        }
+        for (name, value) in &self.extra_headers {
+            if name.trim().is_empty() {
+                continue;
+            }
+            let header_name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| format!("Invalid header name: {}", name))?;
+            let header_value = HeaderValue::from_str(value).map_err(|_| format!("Invalid value for header {}", name))?;
+            headers.insert(header_name, header_value);
+        }
+        Ok(headers)
+    }
+
+    /// Returns the proxies with a password entered since the app started.
+    pub fn proxies_with_passwords(&self) -> impl Iterator<Item = &ProxySettings> {
+        std::iter::once(&self.proxy)
+            .chain(self.overrides.values())
+            .filter(|proxy| proxy.has_credentials() && !proxy.password.is_empty())
+    }
+
+    /// Copies the passwords of the other settings' proxies into the matching proxies which have no password yet.
+    pub fn fill_passwords(&mut self, other: &NetworkSettings) {
+        let passwords: BTreeMap<String, String> = other.proxies_with_passwords()
+            .map(|proxy| (proxy.vault_key(), proxy.password.clone()))
+            .collect();
+        for proxy in std::iter::once(&mut self.proxy).chain(self.overrides.values_mut()) {
+            if proxy.password.is_empty() {
+                if let Some(password) = passwords.get(&proxy.vault_key()) {
+                    proxy.password = password.clone();
+                }
+            }
+        }
+    }
+
+    /// Creates a cookie jar containing the imported cookies.
+    fn cookie_jar(&self) -> Jar {
+        let jar = Jar::default();
+        for cookie in &self.cookies {
+            if let Ok(url) = Url::parse(&cookie.url()) {
+                jar.add_cookie_str(&cookie.set_cookie_string(), &url);
+            }
+        }
+        jar
+    }
+
+    /// Creates an HTTP client for the service.
+    pub fn client(&self, service: Service) -> Result<Client, String> {
+        let mut builder = Client::builder()
+            .default_headers(self.headers()?)
+            .cookie_provider(Arc::new(self.cookie_jar()));
+        if let Some(proxy) = self.proxy_for(service).reqwest_proxy()? {
+            builder = builder.proxy(proxy);
+        }
+        builder.build().map_err(|error| error.to_string())
+    }
+}
+
+/// Returns a copy of the current network settings.
+pub fn network_settings() -> NetworkSettings {
+    settings().network
+}
+
+/// Replaces the current network settings and saves them, with the proxy passwords saved in the vault.
+///
+/// If the vault cannot be used, the passwords are only kept until the app closes.
+pub fn set_network_settings(network: NetworkSettings) -> Result<(), String> {
+    let passwords: Vec<(String, String)> = network.proxies_with_passwords()
+        .map(|proxy| (proxy.vault_key(), proxy.password.clone()))
+        .collect();
+    update_settings(|settings| settings.network = network)?;
+    if passwords.is_empty() {
+        return Ok(());
+    }
+    vault::update_credentials(|credentials| credentials.proxy_passwords.extend(passwords))
+        .map_err(|error| format!("The proxy passwords are only kept until the app closes: {}", error))
+}
+
+/// Creates an HTTP client for the service using the current network settings.
+///
+/// Settings are validated before they are saved, so building the client only fails if
+/// the settings file was edited by hand. The request then fails, rather than bypassing the proxy.
+pub fn client(service: Service) -> Result<Client, String> {
+    network_settings().client(service).map_err(|error| format!("Invalid network settings: {}", error))
+}
+
+/// Requests the test URL of the service and returns the status and latency.
+async fn test_connectivity(settings: NetworkSettings, service: Service) -> Result<String, String> {
+    let client = settings.client(service)?;
+    let start = Instant::now();
+    let response = client.get(service.test_url()).send().await.map_err(|error| error.to_string())?;
+    Ok(format!("{} in {} ms", response.status(), start.elapsed().as_millis()))
+}
+
+#[derive(Default)]
+pub struct NetworkSettingsUI {
+    pub show_window: bool,
+    settings: Option<NetworkSettings>,
+    error: Option<String>,
+    test_results: BTreeMap<Service, Result<String, String>>,
+    test_receiver: Option<Receiver<(Service, Result<String, String>)>>,
+    cookies_file_dialog: Option<FileDialog>,
+}
+
+impl NetworkSettingsUI {
+    pub fn show_network_window(&mut self, ctx: &Context) {
+        if let Some(receiver) = &self.test_receiver {
+            while let Ok((service, result)) = receiver.try_recv() {
+                self.test_results.insert(service, result);
+            }
+        }
+
+        let mut show_window = self.show_window;
+        Window::new("Network Settings")
+            .default_width(500.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let settings = self.settings.get_or_insert_with(network_settings);
+
+                ui.heading("Proxy");
+                display_proxy_settings(ui, &mut settings.proxy, "Global Proxy");
+
+                ui.separator();
+                ui.heading("Per-service overrides");
+                ui.label("An enabled override replaces the global proxy for that service.");
+                for service in Service::ALL {
+                    ui.collapsing(service.name(), |ui| {
+                        let proxy = settings.overrides.entry(service).or_default();
+                        display_proxy_settings(ui, proxy, service.name());
+                    });
+                }
+                ui.label("Chrome, which is used to load MultiUp pages, uses the MultiUp proxy. \
+                Chrome does not support SOCKS5 proxies with credentials.");
+
+                ui.separator();
+                ui.heading("Session");
+                ui.horizontal(|ui| {
+                    ui.label("User agent:");
+                    ui.add(TextEdit::singleline(&mut settings.user_agent)
+                        .hint_text("Default")
+                        .desired_width(ui.available_width()));
+                });
+
+                ui.label("Extra headers:");
+                let mut removed_header = None;
+                Grid::new("Extra Headers").show(ui, |ui| {
+                    for (index, (name, value)) in settings.extra_headers.iter_mut().enumerate() {
+                        ui.add(TextEdit::singleline(name).hint_text("Name").desired_width(150.0));
+                        ui.add(TextEdit::singleline(value).hint_text("Value").desired_width(250.0));
+                        if ui.button("Remove").clicked() {
+                            removed_header = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(index) = removed_header {
+                    settings.extra_headers.remove(index);
+                }
+                if ui.button("Add header").clicked() {
+                    settings.extra_headers.push((String::new(), String::new()));
+                }
+
+                ui.horizontal(|ui| {
+                    let mut domains: Vec<&str> = settings.cookies.iter().map(|cookie| cookie.host()).collect();
+                    domains.sort();
+                    domains.dedup();
+                    ui.label(format!("{} cookies imported for {} domains", settings.cookies.len(), domains.len()));
+                    if ui.button("Import cookies.txt").clicked() {
+                        let mut dialog = FileDialog::open_file(None);
+                        dialog.open();
+                        self.cookies_file_dialog = Some(dialog);
+                    }
+                    if ui.add_enabled(!settings.cookies.is_empty(), Button::new("Clear cookies")).clicked() {
+                        settings.cookies.clear();
+                    }
+                });
+
+                if let Some(dialog) = &mut self.cookies_file_dialog {
+                    if dialog.show(ctx).selected() {
+                        if let Some(path) = dialog.path() {
+                            let cookies = fs::read_to_string(path)
+                                .map_err(|error| error.to_string())
+                                .and_then(|contents| parse_cookies_txt(&contents));
+                            match cookies {
+                                Ok(cookies) => {
+                                    settings.cookies = cookies;
+                                    self.error = None;
+                                }
+                                Err(error) => self.error = Some(format!("Failed to import cookies: {}", error)),
+                            }
+                        }
+                        self.cookies_file_dialog = None;
+                    }
+                }
+                ui.label("Cookies are sent to the domains they were exported from, so a logged-in MultiUp session can be used for extraction.");
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        self.error = set_network_settings(settings.clone()).err();
+                    }
+                    if ui.button("Revert").clicked() {
+                        *settings = network_settings();
+                        self.error = None;
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.separator();
+                ui.heading("Connectivity");
+                ui.horizontal(|ui| {
+                    if ui.add(Button::new("Test connectivity")).clicked() {
+                        let (test_sender, test_receiver) = crossbeam_channel::unbounded();
+                        self.test_receiver = Some(test_receiver);
+                        self.test_results.clear();
+                        for service in Service::ALL {
+                            let settings = settings.clone();
+                            let test_sender = test_sender.clone();
+                            spawn_job(JobKind::ConnectivityTest, format!("Test connection to {}", service.name()), None, move |_| async move {
+                                let result = test_connectivity(settings, service).await;
+                                let status = match &result {
+                                    Ok(summary) => JobStatus::Completed(summary.clone()),
+                                    Err(error) => JobStatus::Failed(error.clone())
+                                };
+                                let _ = test_sender.send((service, result));
+                                status
+                            });
+                        }
+                    }
+                    ui.label("Uses the settings above, even if they are not saved.");
+                });
+
+                Grid::new("Connectivity Results").striped(true).show(ui, |ui| {
+                    for (service, result) in &self.test_results {
+                        ui.label(service.name());
+                        match result {
+                            Ok(summary) => ui.label(summary),
+                            Err(error) => ui.colored_label(ui.visuals().error_fg_color, error),
+                        };
+                        ui.end_row();
+                    }
+                });
+            });
+        self.show_window = show_window;
+    }
+}
+
+fn display_proxy_settings(ui: &mut Ui, proxy: &mut ProxySettings, id_source: &str) {
+    ui.checkbox(&mut proxy.enabled, "Use proxy");
+    ui.add_enabled_ui(proxy.enabled, |ui| {
+        ui.horizontal(|ui| {
+            ComboBox::from_id_source(format!("{} Kind", id_source))
+                .selected_text(proxy.kind.name())
+                .show_ui(ui, |ui| {
+                    for kind in [ProxyKind::Http, ProxyKind::Https, ProxyKind::Socks5] {
+                        ui.selectable_value(&mut proxy.kind, kind, kind.name());
+                    }
+                });
+            ui.add(TextEdit::singleline(&mut proxy.host)
+                .hint_text("Host")
+                .desired_width(200.0));
+            ui.label("Port:");
+            ui.add(DragValue::new(&mut proxy.port));
+        });
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut proxy.username)
+                .hint_text("Username (optional)")
+                .desired_width(150.0));
+            ui.add(TextEdit::singleline(&mut proxy.password)
+                .hint_text("Password (kept in the vault)")
+                .password(true)
+                .desired_width(150.0));
+        });
+    });
+}
@@ -0,0 +1,317 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use eframe::egui::{Button, Grid, ProgressBar, Ui};
+use futures::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use crate::modules::api::UploadedFileDetails;
+use crate::modules::checksums::{FileHashes, HashCheck, Hasher};
+use crate::modules::general::format_size;
+use crate::modules::settings::settings;
+
+static RATE_LIMITER: Mutex<RateLimiter> = Mutex::new(RateLimiter {
+    limit: 0,
+    checked: None,
+    next_send: None,
+});
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileStatus {
+    Waiting,
+    Uploading,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+impl FileStatus {
+    pub fn description(&self) -> String {
+        match self {
+            FileStatus::Waiting => "Waiting".to_string(),
+            FileStatus::Uploading => "Uploading".to_string(),
+            FileStatus::Done => "Done".to_string(),
+            FileStatus::Failed(error) => format!("Failed: {}", error),
+            FileStatus::Cancelled => "Cancelled".to_string(),
+        }
+    }
+}
+
+struct FileState {
+    status: FileStatus,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+}
+
+/// Represents the progress of a single file in an upload.
+///
+/// The number of bytes sent is counted as the upload stream is read by the HTTP client.
+pub struct FileProgress {
+    pub name: String,
+    /// The size of the file, if known.
+    pub total: Option<u64>,
+    pub cancellation_token: CancellationToken,
+    sent: AtomicU64,
+    state: Mutex<FileState>,
+    /// The checksums of the bytes sent, if the file was sent through a tracked stream.
+    hasher: Mutex<Option<Hasher>>,
+    hash_check: Mutex<Option<HashCheck>>,
+}
+
+impl FileProgress {
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.state.lock().unwrap().status.clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self.status(), FileStatus::Waiting | FileStatus::Uploading)
+    }
+
+    /// Counts bytes which have been sent or processed.
+    pub fn add_sent(&self, bytes: usize) {
+        self.sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+        if state.status == FileStatus::Waiting {
+            state.status = FileStatus::Uploading;
+            state.started = Some(Instant::now());
+        }
+    }
+
+    /// Counts a chunk which has been sent and adds it to the checksums.
+    pub fn add_chunk(&self, chunk: &[u8]) {
+        self.hasher.lock().unwrap().get_or_insert_with(Hasher::default).update(chunk);
+        self.add_sent(chunk.len());
+    }
+
+    /// Returns the checksums of the file once every byte of it has been sent.
+    pub fn hashes(&self) -> Option<FileHashes> {
+        let hasher = self.hasher.lock().unwrap();
+        let hasher = hasher.as_ref().filter(|hasher| hasher.bytes() == self.sent())?;
+        let complete = self.status() == FileStatus::Done && self.total.is_none_or(|total| total == hasher.bytes());
+        complete.then(|| hasher.hashes())
+    }
+
+    pub fn hash_check(&self) -> Option<HashCheck> {
+        self.hash_check.lock().unwrap().clone()
+    }
+
+    /// Compares the checksums with the hash MultiUp returned for the file.
+    pub fn verify(&self, uploaded: Option<&UploadedFileDetails>) {
+        let returned = uploaded.and_then(|uploaded| uploaded.hash.as_deref());
+        *self.hash_check.lock().unwrap() = self.hashes().map(|hashes| HashCheck::check(&hashes, returned));
+    }
+
+    /// Resets the progress before the file is sent again.
+    pub fn restart(&self) {
+        self.sent.store(0, Ordering::Relaxed);
+        *self.hasher.lock().unwrap() = None;
+        *self.hash_check.lock().unwrap() = None;
+        *self.state.lock().unwrap() = FileState {
+            status: FileStatus::Waiting,
+            started: None,
+            finished: None,
+        };
+    }
+
+    /// Records the result of the file. A file which was cancelled is always recorded as cancelled.
+    pub fn finish(&self, status: FileStatus) {
+        let mut state = self.state.lock().unwrap();
+        state.status = if self.cancellation_token.is_cancelled() {
+            FileStatus::Cancelled
+        } else {
+            status
+        };
+        state.finished = Some(Instant::now());
+    }
+
+    /// Returns the average upload speed in bytes per second.
+    pub fn speed(&self) -> Option<f64> {
+        let state = self.state.lock().unwrap();
+        let elapsed = state.finished.unwrap_or_else(Instant::now) - state.started?;
+        Some(self.sent() as f64 / elapsed.as_secs_f64().max(0.001))
+    }
+
+    /// Returns the estimated time left, based on the average speed so far.
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.total?.saturating_sub(self.sent());
+        let speed = self.speed().filter(|speed| *speed > 0.0)?;
+        Some(Duration::from_secs_f64(remaining as f64 / speed))
+    }
+
+    pub fn cancel(&self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+/// Represents the progress of every file in an upload.
+#[derive(Default)]
+pub struct UploadProgress {
+    cancellation_token: CancellationToken,
+    files: Mutex<Vec<Arc<FileProgress>>>,
+}
+
+impl UploadProgress {
+    /// Adds a file to the upload, which can be cancelled on its own.
+    pub fn add_file(&self, name: String, total: Option<u64>) -> Arc<FileProgress> {
+        let file = Arc::new(FileProgress {
+            name,
+            total,
+            cancellation_token: self.cancellation_token.child_token(),
+            sent: AtomicU64::new(0),
+            state: Mutex::new(FileState {
+                status: FileStatus::Waiting,
+                started: None,
+                finished: None,
+            }),
+            hasher: Mutex::new(None),
+            hash_check: Mutex::new(None),
+        });
+        self.files.lock().unwrap().push(Arc::clone(&file));
+        file
+    }
+
+    pub fn files(&self) -> Vec<Arc<FileProgress>> {
+        self.files.lock().unwrap().clone()
+    }
+
+    /// Returns the name and checksums of every file which was sent completely.
+    pub fn hashed_files(&self) -> Vec<(String, FileHashes)> {
+        self.files().iter()
+            .filter_map(|file| Some((file.name.clone(), file.hashes()?)))
+            .collect()
+    }
+
+    /// Returns the number of files whose checksums do not match the hash returned by MultiUp.
+    pub fn hash_mismatches(&self) -> usize {
+        self.files().iter()
+            .filter(|file| matches!(file.hash_check(), Some(HashCheck::Mismatched { .. })))
+            .count()
+    }
+
+    /// Cancels every file and marks the unfinished ones as cancelled.
+    pub fn cancel(&self) {
+        self.cancellation_token.cancel();
+        for file in self.files().iter().filter(|file| !file.is_finished()) {
+            file.finish(FileStatus::Cancelled);
+        }
+    }
+
+    /// Displays a row for each file with its progress, speed, ETA, hash check and a button to cancel it.
+    pub fn display(&self, ui: &mut Ui) {
+        Grid::new("Upload Progress")
+            .striped(true)
+            .show(ui, |ui| {
+                for file in self.files() {
+                    let status = file.status();
+                    ui.label(&file.name);
+                    let progress_text = match file.total {
+                        Some(total) => format!("{} / {}", format_size(file.sent()), format_size(total)),
+                        None => format_size(file.sent()),
+                    };
+                    let fraction = match (file.total, &status) {
+                        (_, FileStatus::Done) => 1.0,
+                        (Some(total), _) if total > 0 => file.sent() as f32 / total as f32,
+                        _ => 0.0,
+                    };
+                    ui.add(ProgressBar::new(fraction)
+                        .desired_width(200.0)
+                        .text(progress_text));
+                    ui.label(file.speed().map(|speed| format!("{}/s", format_size(speed as u64))).unwrap_or_default());
+                    let eta = match status {
+                        FileStatus::Uploading => file.eta().map(format_duration).unwrap_or_default(),
+                        _ => String::new(),
+                    };
+                    ui.label(eta);
+                    ui.label(status.description());
+                    match file.hash_check() {
+                        Some(HashCheck::Mismatched { algorithm, expected, actual }) => {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("✘ {} mismatch", algorithm))
+                                .on_hover_text(format!("MultiUp returned {}, but the file sent was {}", expected, actual));
+                        }
+                        Some(hash_check) => {
+                            ui.label(hash_check.description());
+                        }
+                        None => {
+                            ui.label("");
+                        }
+                    }
+                    if ui.add_enabled(!file.is_finished() && !file.cancellation_token.is_cancelled(), Button::new("Cancel")).clicked() {
+                        file.cancel();
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s left", seconds),
+        60..=3599 => format!("{}m {}s left", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m left", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// Spaces out the chunks of every upload, so their combined rate stays under the limit in the settings.
+struct RateLimiter {
+    /// The limit in KiB/s, or 0 for no limit.
+    limit: u64,
+    checked: Option<Instant>,
+    /// When the next chunk may be sent.
+    next_send: Option<Instant>,
+}
+
+/// Reserves time for a chunk of the given size and returns when it may be sent.
+///
+/// Returns `None` if there is no rate limit.
+fn reserve_send(bytes: usize) -> Option<Instant> {
+    let mut limiter = RATE_LIMITER.lock().unwrap();
+    let now = Instant::now();
+    // Reading the settings for every chunk would be slow, so the limit is refreshed every second
+    if limiter.checked.is_none_or(|checked| now - checked >= Duration::from_secs(1)) {
+        limiter.limit = settings().upload.rate_limit;
+        limiter.checked = Some(now);
+    }
+    if limiter.limit == 0 {
+        limiter.next_send = None;
+        return None;
+    }
+    let send_at = limiter.next_send.filter(|next_send| *next_send > now).unwrap_or(now);
+    limiter.next_send = Some(send_at + Duration::from_secs_f64(bytes as f64 / (limiter.limit * 1024) as f64));
+    Some(send_at)
+}
+
+/// Counts and hashes the bytes of an upload stream as they are read and stops it once the file is cancelled.
+///
+/// Chunks are held back when needed to keep to the upload rate limit.
+/// Stopping the stream fails the request which is sending the file.
+pub fn track_stream<S, B, E>(stream: S, file: Arc<FileProgress>) -> impl Stream<Item = Result<B, io::Error>>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    stream.then(move |chunk| {
+        let file = Arc::clone(&file);
+        async move {
+            let cancelled = || io::Error::new(io::ErrorKind::Interrupted, "Upload cancelled");
+            if file.cancellation_token.is_cancelled() {
+                return Err(cancelled());
+            }
+            let chunk = chunk.map_err(io::Error::other)?;
+            if let Some(send_at) = reserve_send(chunk.as_ref().len()) {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(send_at.into()) => {},
+                    _ = file.cancellation_token.cancelled() => return Err(cancelled())
+                }
+            }
+            file.add_chunk(chunk.as_ref());
+            Ok(chunk)
+        }
+    })
+}
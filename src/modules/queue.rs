@@ -0,0 +1,280 @@
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use chrono::{Local, Timelike};
+use eframe::egui::{Button, Context, Grid, ScrollArea, Window};
+use serde::{Deserialize, Serialize};
+use crate::modules::settings::{config_path, load_config_file, settings};
+use crate::modules::upload::UploadRequest;
+
+const QUEUE_FILE: &str = "upload_queue.json";
+
+static QUEUE: OnceLock<Mutex<UploadQueue>> = OnceLock::new();
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum QueueStatus {
+    #[default]
+    Waiting,
+    Running,
+    Completed(String),
+    Failed(String),
+}
+
+impl QueueStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, QueueStatus::Completed(_) | QueueStatus::Failed(_))
+    }
+}
+
+/// Represents an upload waiting in the queue.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct QueueItem {
+    pub id: u64,
+    pub request: UploadRequest,
+    pub status: QueueStatus,
+}
+
+/// Represents the time of day during which queued uploads are started.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct QueueSettings {
+    /// Only start queued uploads between the start and end hours.
+    pub scheduled: bool,
+    pub start_hour: u32,
+    /// The end hour may be before the start hour, in which case the window spans midnight.
+    pub end_hour: u32,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        Self {
+            scheduled: false,
+            start_hour: 19,
+            end_hour: 7,
+        }
+    }
+}
+
+impl QueueSettings {
+    /// Returns `true` if queued uploads can be started at the given hour.
+    pub fn allows(&self, hour: u32) -> bool {
+        if !self.scheduled || self.start_hour == self.end_hour {
+            return true;
+        }
+        match self.start_hour < self.end_hour {
+            true => (self.start_hour..self.end_hour).contains(&hour),
+            false => hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self.scheduled && self.start_hour != self.end_hour {
+            true => format!("Uploads start between {:02}:00 and {:02}:00", self.start_hour, self.end_hour),
+            false => "Uploads start at any time".to_string()
+        }
+    }
+}
+
+/// Represents the queued uploads, which are saved so that they survive a restart.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct UploadQueue {
+    items: Vec<QueueItem>,
+    next_id: u64,
+    paused: bool,
+    /// Why the queue file could not be read, in which case it was kept as a backup.
+    #[serde(skip)]
+    load_error: Option<String>,
+}
+
+impl UploadQueue {
+    /// Loads the queue. Uploads which were running when the app closed are run again.
+    fn load() -> Self {
+        let (mut queue, load_error): (Self, _) = load_config_file(QUEUE_FILE);
+        queue.load_error = load_error;
+        for item in queue.items.iter_mut().filter(|item| item.status == QueueStatus::Running) {
+            item.status = QueueStatus::Waiting;
+        }
+        queue
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = config_path(QUEUE_FILE);
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+        }
+        let json_string = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        fs::write(path, json_string).map_err(|error| error.to_string())
+    }
+}
+
+fn queue() -> &'static Mutex<UploadQueue> {
+    QUEUE.get_or_init(|| Mutex::new(UploadQueue::load()))
+}
+
+fn update_queue(update: impl FnOnce(&mut UploadQueue)) -> Result<(), String> {
+    let mut queue = queue().lock().unwrap();
+    update(&mut queue);
+    queue.save()
+}
+
+/// Adds the upload to the end of the queue. The MultiUp password is not saved.
+pub fn enqueue(mut request: UploadRequest) -> Result<(), String> {
+    request.clear_password();
+    update_queue(|queue| {
+        queue.next_id += 1;
+        let id = queue.next_id;
+        queue.items.push(QueueItem {
+            id,
+            request,
+            status: QueueStatus::Waiting,
+        });
+    })
+}
+
+pub fn queue_items() -> Vec<QueueItem> {
+    queue().lock().unwrap().items.clone()
+}
+
+pub fn is_paused() -> bool {
+    queue().lock().unwrap().paused
+}
+
+pub fn set_paused(paused: bool) -> Result<(), String> {
+    update_queue(|queue| queue.paused = paused)
+}
+
+/// Returns the next waiting upload, if the queue is running and the schedule allows it.
+pub fn next_item() -> Option<QueueItem> {
+    let queue = queue().lock().unwrap();
+    if queue.paused || !settings().queue.allows(Local::now().hour()) {
+        return None;
+    }
+    queue.items.iter()
+        .find(|item| item.status == QueueStatus::Waiting)
+        .cloned()
+}
+
+/// Returns `true` if there are uploads which have not been started.
+pub fn has_waiting_items() -> bool {
+    queue().lock().unwrap().items.iter().any(|item| item.status == QueueStatus::Waiting)
+}
+
+pub fn set_status(id: u64, status: QueueStatus) -> Result<(), String> {
+    update_queue(|queue| {
+        if let Some(item) = queue.items.iter_mut().find(|item| item.id == id) {
+            item.status = status;
+        }
+    })
+}
+
+/// Moves the item one place towards the front or back of the queue.
+fn move_item(id: u64, forwards: bool) -> Result<(), String> {
+    update_queue(|queue| {
+        let Some(index) = queue.items.iter().position(|item| item.id == id) else {
+            return;
+        };
+        match forwards {
+            true if index > 0 => queue.items.swap(index, index - 1),
+            false if index + 1 < queue.items.len() => queue.items.swap(index, index + 1),
+            _ => {}
+        }
+    })
+}
+
+fn remove_item(id: u64) -> Result<(), String> {
+    update_queue(|queue| queue.items.retain(|item| item.id != id))
+}
+
+fn clear_finished() -> Result<(), String> {
+    update_queue(|queue| queue.items.retain(|item| !item.status.is_finished()))
+}
+
+#[derive(Default)]
+pub struct QueueUI {
+    pub show_window: bool,
+    /// Shown when the next upload cannot be started.
+    pub notice: Option<String>,
+    message: Option<Result<String, String>>,
+}
+
+impl QueueUI {
+    pub fn show_queue_window(&mut self, ctx: &Context) {
+        let mut show_window = self.show_window;
+        Window::new("Upload Queue")
+            .default_width(700.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let items = queue_items();
+                let paused = is_paused();
+                ui.horizontal(|ui| {
+                    let button_text = match paused {
+                        true => "Resume queue",
+                        false => "Pause queue"
+                    };
+                    if ui.button(button_text).clicked() {
+                        self.message = Some(set_paused(!paused).map(|_| match paused {
+                            true => "Queue resumed".to_string(),
+                            false => "Queue paused. The running upload will finish.".to_string()
+                        }));
+                    }
+                    if ui.add_enabled(items.iter().any(|item| item.status.is_finished()), Button::new("Clear finished")).clicked() {
+                        self.message = Some(clear_finished().map(|_| "Cleared finished uploads".to_string()));
+                    }
+                });
+                let queue_settings = settings().queue;
+                ui.label(format!("{}. The schedule and upload rate limit can be changed in the settings.", queue_settings.description()));
+                if let Some(notice) = &self.notice {
+                    ui.label(notice);
+                }
+                if let Some(error) = &queue().lock().unwrap().load_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                match &self.message {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    None => {}
+                }
+
+                ui.separator();
+
+                ScrollArea::vertical()
+                    .id_source("Upload Queue")
+                    .show(ui, |ui| {
+                        Grid::new("Upload Queue Grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (index, item) in items.iter().enumerate() {
+                                    ui.label(format!("{}.", index + 1));
+                                    ui.label(item.request.description());
+                                    match &item.status {
+                                        QueueStatus::Waiting => ui.label("Waiting"),
+                                        QueueStatus::Running => ui.label("Uploading"),
+                                        QueueStatus::Completed(status) => ui.label(status),
+                                        QueueStatus::Failed(error) => ui.colored_label(ui.visuals().error_fg_color, error),
+                                    };
+                                    let waiting = item.status == QueueStatus::Waiting;
+                                    if ui.add_enabled(waiting && index > 0, Button::new("⏶")).on_hover_text("Move up").clicked() {
+                                        self.message = move_item(item.id, true).err().map(Err);
+                                    }
+                                    if ui.add_enabled(waiting && index + 1 < items.len(), Button::new("⏷")).on_hover_text("Move down").clicked() {
+                                        self.message = move_item(item.id, false).err().map(Err);
+                                    }
+                                    if ui.add_enabled(item.status != QueueStatus::Running, Button::new("Remove")).clicked() {
+                                        self.message = remove_item(item.id).err().map(Err);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        if items.is_empty() {
+                            ui.label("The queue is empty. Use \"Add to queue\" in the Upload tab to queue an upload.");
+                        }
+                    });
+            });
+        self.show_window = show_window;
+    }
+}
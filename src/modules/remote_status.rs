@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use eframe::egui::{CollapsingHeader, Grid, Ui};
+use reqwest::Client;
+use tokio_util::sync::CancellationToken;
+use crate::modules::api::recheck_validity_api;
+use crate::modules::extract::{download_link_id, MIRROR_PREFIX};
+use crate::modules::jobs::{JobKind, JobStatus, spawn_job};
+use crate::modules::links::{DownloadLink, LinkError};
+use crate::modules::network::{self, Service};
+use crate::modules::settings::settings;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoteState {
+    /// MultiUp has not listed any hosts for the file yet.
+    Fetching,
+    /// Some hosts have not received the file yet.
+    Transferring,
+    Complete,
+    /// No host changed for longer than the stall time in the settings.
+    Stalled,
+    NoValidHosts,
+    Failed(String),
+}
+
+impl RemoteState {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, RemoteState::Fetching | RemoteState::Transferring)
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            RemoteState::Fetching => "Waiting for MultiUp to fetch the file".to_string(),
+            RemoteState::Transferring => "Sending to hosts".to_string(),
+            RemoteState::Complete => "Complete".to_string(),
+            RemoteState::Stalled => "Stalled".to_string(),
+            RemoteState::NoValidHosts => "No valid hosts".to_string(),
+            RemoteState::Failed(error) => format!("Failed: {}", error),
+        }
+    }
+}
+
+/// Represents a remote upload whose hosts are checked until MultiUp has sent the file to each of them.
+pub struct RemoteUpload {
+    pub link: String,
+    pub file_name: String,
+    /// The validity of each host from the last check.
+    pub hosts: BTreeMap<String, String>,
+    pub state: RemoteState,
+    /// The error of the last check, which is expected until MultiUp has fetched the file.
+    pub error: Option<String>,
+    last_change: Instant,
+}
+
+impl RemoteUpload {
+    pub fn valid_hosts(&self) -> usize {
+        self.hosts.values().filter(|validity| validity.as_str() == "valid").count()
+    }
+
+    /// Returns the number of hosts which have not been marked as valid or invalid yet.
+    fn pending_hosts(&self) -> usize {
+        self.hosts.values().filter(|validity| !matches!(validity.as_str(), "valid" | "invalid")).count()
+    }
+
+    /// Records the result of a check and updates the state.
+    ///
+    /// Returns an alert if the upload stalled or ended without a valid host.
+    fn record(&mut self, check: Result<BTreeMap<String, String>, String>, stall_time: Duration) -> Option<String> {
+        let now = Instant::now();
+        match check {
+            Ok(hosts) => {
+                if hosts != self.hosts {
+                    self.hosts = hosts;
+                    self.last_change = now;
+                }
+                self.error = None;
+            }
+            Err(error) => self.error = Some(error),
+        }
+
+        self.state = if !self.hosts.is_empty() && self.pending_hosts() == 0 {
+            match self.valid_hosts() {
+                0 => RemoteState::NoValidHosts,
+                _ => RemoteState::Complete,
+            }
+        } else if now - self.last_change >= stall_time {
+            RemoteState::Stalled
+        } else if self.hosts.is_empty() {
+            RemoteState::Fetching
+        } else {
+            RemoteState::Transferring
+        };
+
+        match self.state {
+            RemoteState::Stalled => Some(format!("The remote upload of {} stalled, as no host changed for {} minutes", self.file_name, stall_time.as_secs() / 60)),
+            RemoteState::NoValidHosts => Some(format!("The remote upload of {} ended without a valid host", self.file_name)),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps track of remote uploads and the alerts raised while checking them.
+#[derive(Default)]
+pub struct RemoteTracker {
+    uploads: Mutex<Vec<RemoteUpload>>,
+    alerts: Mutex<Vec<String>>,
+}
+
+impl RemoteTracker {
+    /// Checks the hosts of the links in the background until every upload is complete, stalls or fails.
+    ///
+    /// Each link is paired with the name of its file.
+    pub fn track(self: &Arc<Self>, links: Vec<(String, String)>) {
+        if links.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        self.uploads.lock().unwrap().extend(links.iter().map(|(link, file_name)| RemoteUpload {
+            link: link.clone(),
+            file_name: file_name.clone(),
+            hosts: BTreeMap::new(),
+            state: match download_link_id(link) {
+                Some(_) => RemoteState::Fetching,
+                None => RemoteState::Failed("Not a MultiUp download link".to_string()),
+            },
+            error: None,
+            last_change: now,
+        }));
+
+        let tracker = Arc::clone(self);
+        let description = format!("Check the hosts of {} remote uploads", links.len());
+        spawn_job(JobKind::RemoteStatus, description, None, |job| async move {
+            let links: Vec<String> = links.into_iter().map(|(link, _)| link).collect();
+            let client = match network::client(Service::MultiUp) {
+                Ok(client) => client,
+                Err(error) => {
+                    tracker.fail(&links, &error);
+                    return JobStatus::Failed(error);
+                }
+            };
+            job.set_total(tracker.pending_links(&links).len());
+            loop {
+                let upload_settings = settings().upload;
+                let stall_time = Duration::from_secs(upload_settings.remote_stall_minutes.max(1) * 60);
+                let pending = tracker.pending_links(&links);
+                if pending.is_empty() {
+                    break;
+                }
+                let checks = futures::future::join_all(pending.iter()
+                    .map(|link| check_hosts(link, client.clone(), job.cancellation_token.child_token()))).await;
+                if job.cancellation_token.is_cancelled() {
+                    tracker.cancel(&links);
+                    return JobStatus::Cancelled;
+                }
+                tracker.record(pending.into_iter().zip(checks), stall_time, || job.advance());
+
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(upload_settings.remote_poll_interval.max(5))) => {},
+                    _ = job.cancellation_token.cancelled() => {
+                        tracker.cancel(&links);
+                        return JobStatus::Cancelled;
+                    }
+                }
+            }
+            match tracker.incomplete_uploads(&links) {
+                0 => JobStatus::Completed(format!("{} uploads reached their hosts", links.len())),
+                incomplete => JobStatus::Failed(format!("{} uploads did not reach their hosts", incomplete)),
+            }
+        });
+    }
+
+    /// Returns the links which are still being sent to their hosts.
+    fn pending_links(&self, links: &[String]) -> Vec<String> {
+        self.uploads.lock().unwrap().iter()
+            .filter(|upload| links.contains(&upload.link) && !upload.state.is_finished())
+            .map(|upload| upload.link.clone())
+            .collect()
+    }
+
+    fn record(&self, checks: impl Iterator<Item = (String, Result<BTreeMap<String, String>, String>)>, stall_time: Duration, mut on_finished: impl FnMut()) {
+        let mut uploads = self.uploads.lock().unwrap();
+        for (link, check) in checks {
+            let Some(upload) = uploads.iter_mut().find(|upload| upload.link == link && !upload.state.is_finished()) else {
+                continue;
+            };
+            if let Some(alert) = upload.record(check, stall_time) {
+                self.alerts.lock().unwrap().push(alert);
+            }
+            if upload.state.is_finished() {
+                on_finished();
+            }
+        }
+    }
+
+    fn cancel(&self, links: &[String]) {
+        self.fail(links, &LinkError::Cancelled.message());
+    }
+
+    fn fail(&self, links: &[String], error: &str) {
+        for upload in self.uploads.lock().unwrap().iter_mut().filter(|upload| links.contains(&upload.link) && !upload.state.is_finished()) {
+            upload.state = RemoteState::Failed(error.to_string());
+        }
+    }
+
+    fn incomplete_uploads(&self, links: &[String]) -> usize {
+        self.uploads.lock().unwrap().iter()
+            .filter(|upload| links.contains(&upload.link) && upload.state != RemoteState::Complete)
+            .count()
+    }
+
+    /// Returns the alerts raised since the last call.
+    pub fn take_alerts(&self) -> Vec<String> {
+        std::mem::take(&mut *self.alerts.lock().unwrap())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.uploads.lock().unwrap().is_empty()
+    }
+
+    pub fn is_tracking(&self) -> bool {
+        self.uploads.lock().unwrap().iter().any(|upload| !upload.state.is_finished())
+    }
+
+    pub fn clear_finished(&self) {
+        self.uploads.lock().unwrap().retain(|upload| !upload.state.is_finished());
+    }
+
+    /// Displays each remote upload with the number of valid hosts, which expands to the validity of each host.
+    pub fn display(&self, ui: &mut Ui) {
+        for (index, upload) in self.uploads.lock().unwrap().iter().enumerate() {
+            let title = format!("{} - {} ({} of {} hosts valid)", upload.file_name, upload.state.description(), upload.valid_hosts(), upload.hosts.len());
+            CollapsingHeader::new(title)
+                .id_source(("Remote Upload Status", index))
+                .show(ui, |ui| {
+                    ui.label(&upload.link);
+                    if let Some(error) = &upload.error {
+                        ui.label(format!("Last check: {}", error));
+                    }
+                    Grid::new(("Remote Upload Hosts", index))
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (host, validity) in &upload.hosts {
+                                ui.label(host);
+                                match validity.as_str() {
+                                    "invalid" => ui.colored_label(ui.visuals().error_fg_color, validity),
+                                    _ => ui.label(validity),
+                                };
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+    }
+}
+
+/// Returns the validity of each host of a MultiUp link from the check-file API.
+async fn check_hosts(link: &str, client: Client, cancellation_token: CancellationToken) -> Result<BTreeMap<String, String>, String> {
+    let link_id = download_link_id(link).ok_or("Not a MultiUp download link".to_string())?;
+    let mut download_link = DownloadLink::new(link.to_string(), link_id.clone());
+    download_link.direct_links = Some(BTreeSet::new());
+    let download_link = recheck_validity_api(MIRROR_PREFIX.to_owned() + &link_id, download_link, cancellation_token, client).await;
+    match download_link.status {
+        Some(Ok(())) => Ok(download_link.direct_links.unwrap_or_default().into_iter()
+            .map(|direct_link| (direct_link.host, direct_link.validity))
+            .collect()),
+        Some(Err(error)) => Err(error.message()),
+        None => Err(LinkError::Other.message()),
+    }
+}
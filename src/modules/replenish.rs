@@ -0,0 +1,327 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crossbeam_channel::Receiver;
+use eframe::egui::{Button, Context, ScrollArea, Ui, Window};
+use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
+use crate::modules::api::{AvailableHosts, Login, MultiUpUploadResponses};
+use crate::modules::jobs::{JobKind, JobStatus, spawn_job};
+use crate::modules::links::{DownloadLink, LinkError};
+use crate::modules::progress::UploadProgress;
+use crate::modules::upload::{remote_upload_files, stream_file};
+
+/// Represents a file whose mirrors are to be replenished.
+/// Contains the MultiUp link, the file name, the direct link that will be re-uploaded
+/// and the hosts which are still valid.
+#[derive(Clone, Debug)]
+pub struct ReplenishTarget {
+    pub link_id: String,
+    pub link: String,
+    pub file_name: String,
+    pub source_host: String,
+    pub source_url: String,
+    pub valid_hosts: HashSet<String>,
+}
+
+impl ReplenishTarget {
+    /// Creates a target from the best valid direct link of a download link.
+    ///
+    /// Returns `None` if there are no valid direct links to re-upload from.
+    pub fn new(download_link: &DownloadLink) -> Option<Self> {
+        let direct_links = download_link.direct_links.as_ref()?;
+        let best_link = best_direct_link(download_link)?;
+        let file_name = download_link.link_information.as_ref()
+            .and_then(|information| information.file_name.clone())
+            .unwrap_or_default();
+
+        Some(Self {
+            link_id: download_link.link_id.clone(),
+            link: download_link.original_link.clone(),
+            file_name,
+            source_host: best_link.0,
+            source_url: best_link.1,
+            valid_hosts: direct_links.iter()
+                .filter(|direct_link| direct_link.validity == "valid")
+                .map(|direct_link| direct_link.host.clone())
+                .collect(),
+        })
+    }
+}
+
+/// Returns the host and URL of the best direct link to re-upload from.
+///
+/// Only valid links with a known URL are considered. The first valid host in alphabetical order is used.
+pub fn best_direct_link(download_link: &DownloadLink) -> Option<(String, String)> {
+    download_link.direct_links.as_ref()?
+        .iter()
+        .find(|direct_link| direct_link.validity == "valid" && !direct_link.url.is_empty())
+        .map(|direct_link| (direct_link.host.clone(), direct_link.url.clone()))
+}
+
+/// Uploads a file from its direct link to MultiUp and returns the new MultiUp link.
+///
+/// Remote uploads always go to the account's default hosts, so the file is streamed through the app
+/// if any hosts are chosen. Otherwise remote upload is used, which falls back to data streaming
+/// if MultiUp rejects the link or the request fails entirely.
+pub async fn reupload_direct_link(url: &str, file_name: &str, login: Login, hosts: HashSet<String>, project_hash: Option<String>) -> Result<String, LinkError> {
+    let urls = [url.to_string()];
+    let file_names = [file_name.to_string()];
+    let mut responses = vec![];
+    if hosts.is_empty() {
+        responses = remote_upload_files(&urls, &file_names, login.clone(), hosts.clone(), project_hash.clone(), &UploadProgress::default()).await;
+    }
+    if matches!(responses.first(), None | Some(MultiUpUploadResponses::MultiUpRemoteUpload(Err(_)))) {
+        responses = vec![MultiUpUploadResponses::MultiUpFileUpload(stream_file(&urls, &file_names, login.user_id, hosts, project_hash, &UploadProgress::default()).await)];
+    }
+
+    match responses.pop() {
+        Some(MultiUpUploadResponses::MultiUpRemoteUpload(result)) => {
+            let response = result?;
+            match (response.error.as_str(), response.link) {
+                ("success", Some(link)) => Ok(link),
+                (error, _) => Err(LinkError::APIError(error.to_string()))
+            }
+        }
+        Some(MultiUpUploadResponses::MultiUpFileUpload(result)) => {
+            result?.files.into_iter()
+                .find_map(|file| file.url)
+                .ok_or(LinkError::NoLinks)
+        }
+        None => Err(LinkError::Other)
+    }
+}
+
+/// Represents a selection of MultiUp hosts, which are fetched from the MultiUp API when first shown.
+///
+/// If fetching the hosts fails, they are not fetched again until the user retries.
+#[derive(Default)]
+pub struct HostSelection {
+    hosts: BTreeMap<String, bool>,
+    receiver: Option<Receiver<Result<AvailableHosts, LinkError>>>,
+    error: Option<LinkError>,
+    excluded: HashSet<String>,
+}
+
+impl HostSelection {
+    /// Selects every host except the excluded ones.
+    pub fn select_all_except(&mut self, excluded: HashSet<String>) {
+        for (host, selected) in self.hosts.iter_mut() {
+            *selected = !excluded.contains(host);
+        }
+        self.excluded = excluded;
+    }
+
+    pub fn selected(&self) -> HashSet<String> {
+        self.hosts.iter()
+            .filter(|(_, selected)| **selected)
+            .map(|(host, _)| host.clone())
+            .collect()
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, id_source: &str) {
+        if self.hosts.is_empty() && self.receiver.is_none() && self.error.is_none() {
+            let (hosts_sender, hosts_receiver) = crossbeam_channel::unbounded();
+            self.receiver = Some(hosts_receiver);
+            spawn_job(JobKind::Hosts, "Get MultiUp hosts".to_string(), None, |_| async move {
+                let hosts = AvailableHosts::get().await;
+                let status = match &hosts {
+                    Ok(hosts) => JobStatus::Completed(format!("{} hosts", hosts.hosts.len())),
+                    Err(error) => JobStatus::Failed(format!("{:?}", error))
+                };
+                let _ = hosts_sender.send(hosts);
+                status
+            });
+        }
+
+        if let Some(receiver) = &self.receiver {
+            if let Ok(result) = receiver.try_recv() {
+                match result {
+                    Ok(hosts) => {
+                        self.hosts = hosts.hosts.into_keys()
+                            .map(|host| {
+                                let selected = !self.excluded.contains(&host);
+                                (host, selected)
+                            })
+                            .collect();
+                    }
+                    Err(error) => self.error = Some(error),
+                }
+                self.receiver = None;
+            }
+        }
+
+        if let Some(error) = &self.error {
+            let retry = ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Failed to get hosts: {}", error.message()));
+                ui.button("Retry").clicked()
+            }).inner;
+            if retry {
+                self.error = None;
+            }
+            return;
+        }
+
+        if self.hosts.is_empty() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Getting hosts...");
+            });
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Select all").clicked() {
+                self.hosts.values_mut().for_each(|selected| *selected = true);
+            }
+            if ui.button("Deselect all").clicked() {
+                self.hosts.values_mut().for_each(|selected| *selected = false);
+            }
+        });
+
+        ScrollArea::vertical()
+            .id_source(id_source)
+            .max_height(200.0)
+            .show(ui, |ui| {
+                ui.columns(4, |columns| {
+                    for (host, selected) in self.hosts.iter_mut() {
+                        columns[0].checkbox(selected, host);
+                        columns.rotate_left(1);
+                    }
+                });
+            });
+    }
+}
+
+#[derive(Default)]
+struct Channels {
+    upload: Option<Receiver<(String, Result<String, LinkError>)>>,
+}
+
+#[derive(Default)]
+pub struct ReplenishUI {
+    pub login: Login,
+    channels: Channels,
+    show_window: bool,
+    target: Option<ReplenishTarget>,
+    hosts: HostSelection,
+    uploading: bool,
+    replenished_links: HashMap<String, Vec<String>>,
+}
+
+impl ReplenishUI {
+    /// Opens the replenish window for the given target.
+    pub fn open(&mut self, target: ReplenishTarget) {
+        // Preselect the hosts which no longer have a valid copy of the file
+        self.hosts.select_all_except(target.valid_hosts.clone());
+        self.target = Some(target);
+        self.show_window = true;
+    }
+
+    /// Returns the new MultiUp links created for a download link.
+    pub fn replenished_links(&self, link_id: &str) -> Option<&Vec<String>> {
+        self.replenished_links.get(link_id)
+    }
+
+    pub fn show_replenish_window(&mut self, ctx: &Context, toasts: &mut Toasts) {
+        self.receive_results(toasts);
+
+        let mut show_window = self.show_window;
+        Window::new("Replenish Mirrors")
+            .default_width(500.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let Some(target) = self.target.clone() else {
+                    ui.label("Right-click a file in the extracted links to replenish it.");
+                    return;
+                };
+
+                ui.label(format!("File: {}", target.file_name));
+                ui.label(format!("MultiUp link: {}", target.link));
+                ui.label(format!("Re-upload from: {} ({} valid hosts left)", target.source_host, target.valid_hosts.len()));
+
+                let user = match &self.login.user_id {
+                    Some(_) => self.login.username.clone(),
+                    None => "Anonymous".to_string()
+                };
+                ui.label(format!("Uploading as: {} (log in from the Upload tab to change)", user));
+
+                ui.separator();
+                ui.label("Upload to hosts (hosts with a valid copy are deselected). \
+                The file is streamed through this app to the selected hosts, or remote uploaded to the account's default hosts if none are selected:");
+                self.hosts.show(ui, "Replenish Hosts");
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let selected_hosts = self.hosts.selected();
+
+                    if ui.add_enabled(!self.uploading, Button::new("Replenish")).clicked() {
+                        self.uploading = true;
+                        let (upload_sender, upload_receiver) = crossbeam_channel::unbounded();
+                        self.channels.upload = Some(upload_receiver);
+                        let login = self.login.clone();
+                        let target = target.clone();
+                        let description = format!("Replenish {}", target.file_name);
+                        spawn_job(JobKind::Replenish, description, None, |job| async move {
+                            let result = tokio::select! {
+                                result = reupload_direct_link(&target.source_url, &target.file_name, login, selected_hosts, None) => result,
+                                _ = job.cancellation_token.cancelled() => Err(LinkError::Cancelled)
+                            };
+                            let status = match &result {
+                                Ok(link) => JobStatus::Completed(link.clone()),
+                                Err(error) => JobStatus::Failed(format!("{:?}", error))
+                            };
+                            let _ = upload_sender.send((target.link_id, result));
+                            status
+                        });
+                    }
+
+                    if self.uploading {
+                        ui.spinner();
+                        ui.label("Uploading...");
+                    }
+                });
+
+                if let Some(links) = self.replenished_links.get(&target.link_id) {
+                    ui.label("New MultiUp links:");
+                    for link in links {
+                        ui.horizontal(|ui| {
+                            ui.label(link);
+                            if ui.button("Copy").clicked() {
+                                ui.output_mut(|output| output.copied_text = link.clone());
+                            }
+                        });
+                    }
+                }
+            });
+        self.show_window = show_window;
+    }
+
+    fn receive_results(&mut self, toasts: &mut Toasts) {
+        if let Some(receiver) = &self.channels.upload {
+            if let Ok((link_id, result)) = receiver.try_recv() {
+                self.uploading = false;
+                match result {
+                    Ok(link) => {
+                        toasts.add(Toast {
+                            text: format!("Replenished as {}", link).into(),
+                            kind: ToastKind::Success,
+                            options: ToastOptions::default()
+                                .duration_in_seconds(5.0)
+                                .show_progress(true)
+                                .show_icon(true)
+                        });
+                        self.replenished_links.entry(link_id).or_default().push(link);
+                    }
+                    Err(error) => {
+                        toasts.add(Toast {
+                            text: format!("Failed to replenish: {:?}", error).into(),
+                            kind: ToastKind::Error,
+                            options: ToastOptions::default()
+                                .duration_in_seconds(10.0)
+                                .show_progress(true)
+                                .show_icon(true)
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,542 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use eframe::egui::{ComboBox, Context, DragValue, Grid, Window};
+use egui_file::FileDialog;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::modules::accounts::AccountSettings;
+use crate::modules::browser::BrowserSettings;
+use crate::modules::debrid::DebridService;
+use crate::modules::network::NetworkSettings;
+use crate::modules::queue::QueueSettings;
+use crate::modules::templates::DescriptionTemplate;
+use crate::modules::upload::UploadType;
+use crate::modules::vault::vault_exists;
+
+/// The version written to new settings files.
+pub const SETTINGS_VERSION: u32 = 1;
+
+const SETTINGS_FILE: &str = "settings.json";
+const APP_DIRECTORY: &str = "MultiUp-Direct";
+
+// Files used before the settings were combined, read from the working directory
+const LEGACY_NETWORK_FILE: &str = "network.json";
+const LEGACY_BROWSER_FILE: &str = "browser.json";
+const LEGACY_API_KEY_FILE: &str = "api_key.json";
+
+static SETTINGS: OnceLock<RwLock<Settings>> = OnceLock::new();
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ExtractSettings {
+    pub recheck_validity: bool,
+    /// The number of project pages loaded at once.
+    pub project_concurrency: usize,
+    /// The number of mirror pages loaded at once.
+    pub link_concurrency: usize,
+    /// The number of files cloned at once.
+    pub clone_concurrency: usize,
+}
+
+impl Default for ExtractSettings {
+    fn default() -> Self {
+        Self {
+            recheck_validity: false,
+            project_concurrency: 100,
+            link_concurrency: 200,
+            clone_concurrency: 5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct DebridSettings {
+    pub service: DebridService,
+    pub use_remote_traffic: bool,
+    /// Plain-text keys from `api_key.json`, which are only kept in memory until they are moved into the vault.
+    #[serde(alias = "all_debrid", skip_serializing)]
+    pub all_debrid_api_key: String,
+    #[serde(alias = "real_debrid", skip_serializing)]
+    pub real_debrid_api_key: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct UploadSettings {
+    pub upload_type: UploadType,
+    /// Hosts selected when the host list is loaded. MultiUp's selection is used if this is empty.
+    pub hosts: Vec<String>,
+    pub data_streaming: bool,
+    /// Upload each disk file in its own request instead of one request for every file.
+    pub separate_requests: bool,
+    /// The number of disk files uploaded at once when they are uploaded separately.
+    pub file_concurrency: usize,
+    /// The number of times a separately uploaded file is retried after failing.
+    pub retries: usize,
+    /// The combined rate limit of every upload in KiB/s, or 0 for no limit.
+    pub rate_limit: u64,
+    /// Deselect hosts whose size limit is too small for a file instead of asking what to do.
+    pub deselect_oversized_hosts: bool,
+    /// Upload SFV, MD5 and SHA-256 manifests of disk files after they are uploaded.
+    pub upload_manifests: bool,
+    /// Check the hosts of remote uploads until MultiUp has sent the file to each of them.
+    pub track_remote_uploads: bool,
+    /// The time between checks of a remote upload's hosts in seconds.
+    pub remote_poll_interval: u64,
+    /// The time in minutes without any host changing after which a remote upload is considered stalled.
+    pub remote_stall_minutes: u64,
+    /// Extract the uploaded links and recheck their validity after an upload.
+    pub verify_after_upload: bool,
+    /// The time in minutes to wait after an upload before verifying its links.
+    pub verify_delay_minutes: u64,
+    /// Named project descriptions with placeholders, which are filled in when the project is created.
+    pub description_templates: Vec<DescriptionTemplate>,
+}
+
+impl Default for UploadSettings {
+    fn default() -> Self {
+        Self {
+            upload_type: UploadType::default(),
+            hosts: Vec::new(),
+            data_streaming: true,
+            separate_requests: false,
+            file_concurrency: 3,
+            retries: 2,
+            rate_limit: 0,
+            deselect_oversized_hosts: false,
+            upload_manifests: false,
+            track_remote_uploads: true,
+            remote_poll_interval: 30,
+            remote_stall_minutes: 15,
+            verify_after_upload: false,
+            verify_delay_minutes: 10,
+            description_templates: vec![DescriptionTemplate::release()],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct WatchlistSettings {
+    /// The number of links checked at once.
+    pub check_concurrency: usize,
+}
+
+impl Default for WatchlistSettings {
+    fn default() -> Self {
+        Self {
+            check_concurrency: 20,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct VaultSettings {
+    /// Minutes without use before the credential vault locks itself. 0 never locks.
+    pub auto_lock_minutes: u64,
+}
+
+impl Default for VaultSettings {
+    fn default() -> Self {
+        Self {
+            auto_lock_minutes: 15,
+        }
+    }
+}
+
+/// Represents every persistent setting of the app.
+///
+/// Stored as `settings.json` in the user's config directory. The version is used to migrate
+/// files written by older versions of the app.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub extract: ExtractSettings,
+    pub debrid: DebridSettings,
+    pub upload: UploadSettings,
+    pub queue: QueueSettings,
+    pub watchlist: WatchlistSettings,
+    pub vault: VaultSettings,
+    pub accounts: AccountSettings,
+    pub network: NetworkSettings,
+    pub browser: BrowserSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            extract: ExtractSettings::default(),
+            debrid: DebridSettings::default(),
+            upload: UploadSettings::default(),
+            queue: QueueSettings::default(),
+            watchlist: WatchlistSettings::default(),
+            vault: VaultSettings::default(),
+            accounts: AccountSettings::default(),
+            network: NetworkSettings::default(),
+            browser: BrowserSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings file, creating it from the legacy files if it does not exist yet.
+    ///
+    /// A file which cannot be read is kept as `settings.json.bak` and replaced by defaults.
+    /// The API keys in `api_key.json` and the proxy passwords in `network.json` are read each time until a vault exists,
+    /// as they are never saved with the settings.
+    pub fn load() -> Self {
+        let path = settings_path();
+        let mut settings = match fs::read_to_string(&path) {
+            Ok(json_string) => match Self::from_json(&json_string) {
+                Ok(settings) => settings,
+                Err(_) => {
+                    let _ = fs::rename(&path, path.with_extension("json.bak"));
+                    Self::default()
+                }
+            },
+            Err(_) => {
+                let settings = Self::from_legacy_files();
+                let _ = settings.save();
+                settings
+            }
+        };
+        settings.read_legacy_secrets();
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        self.save_to(&settings_path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+        }
+        let json_string = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        fs::write(path, json_string).map_err(|error| error.to_string())
+    }
+
+    /// Reads settings from an exported file.
+    pub fn import(path: &Path) -> Result<Self, String> {
+        let json_string = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        Self::from_json(&json_string)
+    }
+
+    /// Parses settings, migrating them from older versions.
+    pub fn from_json(json_string: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(json_string).map_err(|error| error.to_string())?;
+        let settings: Self = serde_json::from_value(migrate(value)?).map_err(|error| error.to_string())?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.network.validate()
+    }
+
+    /// Creates settings from `network.json`, `browser.json` and `api_key.json` in the working directory.
+    ///
+    /// Missing or invalid files leave the defaults in place. The legacy files are not removed.
+    fn from_legacy_files() -> Self {
+        let mut settings = Self::default();
+        if let Some(network) = read_legacy_file(LEGACY_NETWORK_FILE) {
+            settings.network = network;
+        }
+        if let Some(browser) = read_legacy_file(LEGACY_BROWSER_FILE) {
+            settings.browser = browser;
+        }
+        settings
+    }
+
+    /// Reads the API keys from `api_key.json` and the proxy passwords from `network.json`,
+    /// unless a vault exists, as they were moved into it.
+    fn read_legacy_secrets(&mut self) {
+        if vault_exists() {
+            return;
+        }
+        if let Some(api_keys) = read_legacy_file::<DebridSettings>(LEGACY_API_KEY_FILE) {
+            self.debrid.all_debrid_api_key = api_keys.all_debrid_api_key;
+            self.debrid.real_debrid_api_key = api_keys.real_debrid_api_key;
+        }
+        if let Some(network) = read_legacy_file::<NetworkSettings>(LEGACY_NETWORK_FILE) {
+            self.network.fill_passwords(&network);
+        }
+    }
+}
+
+/// Deletes `api_key.json` from the working directory, so the plain-text API keys do not outlive the vault they were moved into.
+pub fn remove_legacy_api_keys() -> Result<(), String> {
+    match fs::remove_file(LEGACY_API_KEY_FILE) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(format!("Could not delete {}: {}", LEGACY_API_KEY_FILE, error)),
+        _ => Ok(()),
+    }
+}
+
+fn read_legacy_file<T: for<'de> Deserialize<'de>>(path: &str) -> Option<T> {
+    let json_string = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json_string).ok()
+}
+
+/// Upgrades settings from older versions to [`SETTINGS_VERSION`].
+///
+/// Each step upgrades the settings by one version, so any older file can be read.
+/// Files without a version were written before versioning and are treated as version 1.
+fn migrate(mut value: Value) -> Result<Value, String> {
+    let mut version = value.get("version").and_then(Value::as_u64).unwrap_or(1) as u32;
+    if version > SETTINGS_VERSION {
+        return Err(format!("The settings were saved by a newer version of the app (version {})", version));
+    }
+
+    while version < SETTINGS_VERSION {
+        // Add a step here when a setting is renamed or moved, e.g. `2 => { ... }`
+        version += 1;
+    }
+
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    }
+    Ok(value)
+}
+
+/// Returns the path of a file in the app's directory in the user's config directory.
+///
+/// Falls back to the working directory if there is no config directory.
+pub fn config_path(file_name: &str) -> PathBuf {
+    match dirs::config_dir() {
+        Some(directory) => directory.join(APP_DIRECTORY).join(file_name),
+        None => PathBuf::from(file_name),
+    }
+}
+
+/// Reads a JSON file from the app's config directory, or returns the default if there is no file.
+///
+/// A file which cannot be parsed is moved to `<file>.bak`, so the next save does not overwrite it,
+/// and the error is returned so it can be shown.
+pub fn load_config_file<T: DeserializeOwned + Default>(file_name: &str) -> (T, Option<String>) {
+    let path = config_path(file_name);
+    let Ok(json_string) = fs::read_to_string(&path) else {
+        return (T::default(), None);
+    };
+    match serde_json::from_str(&json_string) {
+        Ok(value) => (value, None),
+        Err(error) => {
+            let backup_path = path.with_extension("json.bak");
+            let error = match fs::rename(&path, &backup_path) {
+                Ok(()) => format!("{} could not be read and was moved to {}: {}", file_name, backup_path.display(), error),
+                Err(rename_error) => format!("{} could not be read: {}. It could not be moved aside either: {}", file_name, error, rename_error),
+            };
+            (T::default(), Some(error))
+        }
+    }
+}
+
+/// Returns the path of the settings file.
+pub fn settings_path() -> PathBuf {
+    config_path(SETTINGS_FILE)
+}
+
+fn settings_lock() -> &'static RwLock<Settings> {
+    SETTINGS.get_or_init(|| RwLock::new(Settings::load()))
+}
+
+/// Returns a copy of the current settings.
+pub fn settings() -> Settings {
+    settings_lock().read().unwrap().clone()
+}
+
+/// Replaces the current settings and saves them.
+pub fn set_settings(settings: Settings) -> Result<(), String> {
+    settings.validate()?;
+    settings.save()?;
+    *settings_lock().write().unwrap() = settings;
+    Ok(())
+}
+
+/// Changes part of the current settings and saves them.
+pub fn update_settings(update: impl FnOnce(&mut Settings)) -> Result<(), String> {
+    let mut settings = settings();
+    update(&mut settings);
+    set_settings(settings)
+}
+
+#[derive(Default)]
+pub struct SettingsUI {
+    pub show_window: bool,
+    settings: Option<Settings>,
+    message: Option<Result<String, String>>,
+    import_dialog: Option<FileDialog>,
+    export_dialog: Option<FileDialog>,
+}
+
+impl SettingsUI {
+    pub fn show_settings_window(&mut self, ctx: &Context) {
+        let mut show_window = self.show_window;
+        Window::new("Settings")
+            .default_width(500.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let settings = self.settings.get_or_insert_with(settings);
+
+                ui.label(format!("Stored in {}", settings_path().display()));
+                ui.label("Defaults apply to the tabs the next time the app starts. Limits apply to the next job.");
+
+                ui.separator();
+                ui.heading("Extract");
+                ui.checkbox(&mut settings.extract.recheck_validity, "Recheck link validity by default");
+                Grid::new("Extract Settings").show(ui, |ui| {
+                    ui.label("Projects loaded at once:");
+                    ui.add(DragValue::new(&mut settings.extract.project_concurrency).clamp_range(1..=500));
+                    ui.end_row();
+                    ui.label("Mirror pages loaded at once:");
+                    ui.add(DragValue::new(&mut settings.extract.link_concurrency).clamp_range(1..=500));
+                    ui.end_row();
+                    ui.label("Files cloned at once:");
+                    ui.add(DragValue::new(&mut settings.extract.clone_concurrency).clamp_range(1..=50));
+                    ui.end_row();
+                });
+
+                ui.separator();
+                ui.heading("Debrid");
+                ui.horizontal(|ui| {
+                    ui.label("Default service:");
+                    ComboBox::from_id_source("Default Debrid Service")
+                        .selected_text(settings.debrid.service.name())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut settings.debrid.service, DebridService::AllDebrid, DebridService::AllDebrid.name());
+                            ui.selectable_value(&mut settings.debrid.service, DebridService::RealDebrid, DebridService::RealDebrid.name());
+                        });
+                });
+                ui.checkbox(&mut settings.debrid.use_remote_traffic, "Use RealDebrid remote traffic by default");
+                ui.label("API keys are stored in the credential vault.");
+
+                ui.separator();
+                ui.heading("Upload");
+                ui.horizontal(|ui| {
+                    ui.label("Default upload type:");
+                    ui.selectable_value(&mut settings.upload.upload_type, UploadType::Remote, "Remote upload");
+                    ui.selectable_value(&mut settings.upload.upload_type, UploadType::Disk, "Disk upload");
+                });
+                ui.checkbox(&mut settings.upload.data_streaming, "Use data streaming for remote uploads by default");
+                ui.checkbox(&mut settings.upload.separate_requests, "Upload disk files separately by default");
+                ui.checkbox(&mut settings.upload.deselect_oversized_hosts, "Deselect hosts which cannot accept a file's size without asking");
+                ui.checkbox(&mut settings.upload.upload_manifests, "Upload checksum manifests of disk files by default");
+                ui.checkbox(&mut settings.upload.track_remote_uploads, "Check the hosts of remote uploads until they have received the file");
+                ui.checkbox(&mut settings.upload.verify_after_upload, "Extract and recheck the uploaded links after each upload");
+                Grid::new("Upload Settings").show(ui, |ui| {
+                    ui.label("Files uploaded at once:");
+                    ui.add(DragValue::new(&mut settings.upload.file_concurrency).clamp_range(1..=20));
+                    ui.end_row();
+                    ui.label("Retries per file:");
+                    ui.add(DragValue::new(&mut settings.upload.retries).clamp_range(0..=10));
+                    ui.end_row();
+                    ui.label("Upload rate limit (0 for none):");
+                    ui.add(DragValue::new(&mut settings.upload.rate_limit).suffix(" KiB/s"));
+                    ui.end_row();
+                    ui.label("Remote upload check interval:");
+                    ui.add(DragValue::new(&mut settings.upload.remote_poll_interval).clamp_range(5..=3600).suffix(" s"));
+                    ui.end_row();
+                    ui.label("Alert when no host changes for:");
+                    ui.add(DragValue::new(&mut settings.upload.remote_stall_minutes).clamp_range(1..=1440).suffix(" min"));
+                    ui.end_row();
+                    ui.label("Verify uploaded links after:");
+                    ui.add(DragValue::new(&mut settings.upload.verify_delay_minutes).clamp_range(0..=1440).suffix(" min"));
+                    ui.end_row();
+                });
+
+                ui.separator();
+                ui.heading("Upload Queue");
+                ui.checkbox(&mut settings.queue.scheduled, "Only start queued uploads at certain hours");
+                ui.add_enabled_ui(settings.queue.scheduled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("From");
+                        ui.add(DragValue::new(&mut settings.queue.start_hour).clamp_range(0..=23).suffix(":00"));
+                        ui.label("to");
+                        ui.add(DragValue::new(&mut settings.queue.end_hour).clamp_range(0..=23).suffix(":00"));
+                    });
+                });
+                ui.label(format!("Default hosts: {}", match settings.upload.hosts.is_empty() {
+                    true => "MultiUp's selection".to_string(),
+                    false => settings.upload.hosts.join(", ")
+                }));
+                if !settings.upload.hosts.is_empty() && ui.button("Clear default hosts").clicked() {
+                    settings.upload.hosts.clear();
+                }
+
+                ui.separator();
+                ui.heading("Watchlist");
+                ui.horizontal(|ui| {
+                    ui.label("Links checked at once:");
+                    ui.add(DragValue::new(&mut settings.watchlist.check_concurrency).clamp_range(1..=100));
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        self.message = Some(set_settings(settings.clone()).map(|_| "Saved".to_string()));
+                    }
+                    if ui.button("Revert").clicked() {
+                        *settings = self::settings();
+                        self.message = None;
+                    }
+                    if ui.button("Reset to defaults").clicked() {
+                        *settings = Settings::default();
+                        self.message = Some(Ok("Defaults restored. Save to keep them.".to_string()));
+                    }
+                    if ui.button("Import").clicked() {
+                        let mut dialog = FileDialog::open_file(None);
+                        dialog.open();
+                        self.import_dialog = Some(dialog);
+                    }
+                    if ui.button("Export").clicked() {
+                        let mut dialog = FileDialog::save_file(None).default_filename(SETTINGS_FILE);
+                        dialog.open();
+                        self.export_dialog = Some(dialog);
+                    }
+                });
+
+                if let Some(dialog) = &mut self.import_dialog {
+                    if dialog.show(ctx).selected() {
+                        if let Some(path) = dialog.path() {
+                            self.message = Some(match Settings::import(path) {
+                                Ok(imported) => {
+                                    *settings = imported;
+                                    Ok("Imported. Save to keep the imported settings.".to_string())
+                                }
+                                Err(error) => Err(format!("Failed to import settings: {}", error)),
+                            });
+                        }
+                        self.import_dialog = None;
+                    }
+                }
+
+                if let Some(dialog) = &mut self.export_dialog {
+                    if dialog.show(ctx).selected() {
+                        if let Some(path) = dialog.path() {
+                            self.message = Some(match settings.save_to(path) {
+                                Ok(()) => Ok(format!("Exported to {}", path.display())),
+                                Err(error) => Err(format!("Failed to export settings: {}", error)),
+                            });
+                        }
+                        self.export_dialog = None;
+                    }
+                }
+
+                match &self.message {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    None => {}
+                }
+            });
+        self.show_window = show_window;
+    }
+}
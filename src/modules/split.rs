@@ -0,0 +1,301 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use chrono::Local;
+use eframe::egui::{Button, Context, DragValue, Grid, TextEdit, Window};
+use egui_file::FileDialog;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use zip::{CompressionMethod, ZipWriter};
+use zip::write::FileOptions;
+use crate::modules::general::format_size;
+use crate::modules::progress::FileProgress;
+
+/// The space left under the smallest host limit, so the volumes are not rejected by hosts which round their limits.
+const VOLUME_MARGIN: u64 = 1024 * 1024;
+/// The largest volume size which can be set by hand, in MB (1 TB).
+const MAX_MANUAL_SIZE: u64 = 1024 * 1024;
+
+/// Represents files and folders which are packed into archive volumes before they are uploaded.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SplitSettings {
+    pub sources: Vec<PathBuf>,
+    /// The name of the archive and the project, without the extension.
+    pub archive_name: String,
+    /// The maximum size of each volume in bytes.
+    pub volume_size: u64,
+}
+
+/// Writes a single stream across numbered volume files of a fixed size.
+///
+/// Seeking is supported across volumes, as the archive writer goes back to fill in the size of each entry.
+struct SplitWriter {
+    directory: PathBuf,
+    base_name: String,
+    volume_size: u64,
+    volumes: Vec<File>,
+    paths: Vec<PathBuf>,
+    position: u64,
+    length: u64,
+}
+
+impl SplitWriter {
+    fn new(directory: &Path, base_name: &str, volume_size: u64) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            base_name: base_name.to_string(),
+            volume_size,
+            volumes: vec![],
+            paths: vec![],
+            position: 0,
+            length: 0,
+        }
+    }
+
+    fn volume(&mut self, index: usize) -> io::Result<&mut File> {
+        while self.volumes.len() <= index {
+            let path = self.directory.join(format!("{}.{:03}", self.base_name, self.volumes.len() + 1));
+            let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+            self.volumes.push(file);
+            self.paths.push(path);
+        }
+        Ok(&mut self.volumes[index])
+    }
+}
+
+impl Write for SplitWriter {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        let index = (self.position / self.volume_size) as usize;
+        let offset = self.position % self.volume_size;
+        let available = (self.volume_size - offset) as usize;
+        let volume = self.volume(index)?;
+        volume.seek(SeekFrom::Start(offset))?;
+        let written = volume.write(&buffer[..buffer.len().min(available)])?;
+        self.position += written as u64;
+        self.length = self.length.max(self.position);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.volumes.iter_mut().try_for_each(|volume| volume.flush())
+    }
+}
+
+impl Seek for SplitWriter {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or(io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek position"))?;
+        Ok(self.position)
+    }
+}
+
+/// Returns the volume size for the smallest host limit in MB, or `None` if no selected host has a limit.
+pub fn automatic_volume_size(host_limits: impl Iterator<Item = u64>) -> Option<u64> {
+    host_limits.filter(|limit| *limit > 0)
+        .min()
+        .map(|limit| (limit * 1024 * 1024).saturating_sub(VOLUME_MARGIN).max(VOLUME_MARGIN))
+}
+
+/// Returns a new directory for the volumes of an upload in the system's temp directory.
+pub fn temp_directory() -> PathBuf {
+    std::env::temp_dir().join(format!("MultiUp-Direct-{}", Local::now().format("%Y%m%d-%H%M%S-%f")))
+}
+
+/// Packs the files and folders into an uncompressed zip archive split into volumes, such as `name.zip.001`.
+///
+/// Folders are packed with their subfolders. The volumes can be joined by opening the first one with 7-Zip.
+/// Returns the paths of the volumes in order.
+pub fn create_split_archive(split_settings: &SplitSettings, directory: &Path, file_progress: &FileProgress) -> Result<Vec<PathBuf>, String> {
+    if split_settings.volume_size == 0 {
+        return Err("The volume size must be greater than 0".to_string());
+    }
+    fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    let base_name = format!("{}.zip", split_settings.archive_name);
+    let writer = BufWriter::new(SplitWriter::new(directory, &base_name, split_settings.volume_size));
+    let mut zip = ZipWriter::new(writer);
+    let mut buffer = vec![0; 1024 * 1024];
+
+    for source in &split_settings.sources {
+        let parent = source.parent().unwrap_or(Path::new(""));
+        for entry in WalkDir::new(source).sort_by_file_name() {
+            let entry = entry.map_err(|error| format!("Could not read {}: {}", source.display(), error))?;
+            let name = entry.path().strip_prefix(parent).unwrap_or(entry.path())
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or_default();
+            let options = FileOptions::default()
+                .compression_method(CompressionMethod::Stored)
+                .large_file(size >= u32::MAX as u64);
+            if entry.file_type().is_dir() {
+                zip.add_directory(name, options).map_err(|error| error.to_string())?;
+                continue;
+            }
+
+            zip.start_file(name, options).map_err(|error| error.to_string())?;
+            let mut file = File::open(entry.path()).map_err(|error| format!("Could not read {}: {}", entry.path().display(), error))?;
+            loop {
+                if file_progress.cancellation_token.is_cancelled() {
+                    return Err("Cancelled".to_string());
+                }
+                let read = file.read(&mut buffer).map_err(|error| format!("Could not read {}: {}", entry.path().display(), error))?;
+                if read == 0 {
+                    break;
+                }
+                zip.write_all(&buffer[..read]).map_err(|error| error.to_string())?;
+                file_progress.add_sent(read);
+            }
+        }
+    }
+
+    let writer = zip.finish().map_err(|error| error.to_string())?;
+    let split_writer = writer.into_inner().map_err(|error| error.to_string())?;
+    Ok(split_writer.paths)
+}
+
+/// Returns the combined size of the files in the sources and their subfolders.
+pub fn sources_size(sources: &[PathBuf]) -> u64 {
+    sources.iter()
+        .flat_map(WalkDir::new)
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+pub struct SplitUI {
+    pub show_window: bool,
+    sources: Vec<PathBuf>,
+    archive_name: String,
+    automatic_size: bool,
+    /// The volume size in MB when it is set by hand.
+    manual_size: u64,
+    dialog: Option<FileDialog>,
+}
+
+impl Default for SplitUI {
+    fn default() -> Self {
+        Self {
+            show_window: false,
+            sources: vec![],
+            archive_name: String::new(),
+            automatic_size: true,
+            manual_size: 1024,
+            dialog: None,
+        }
+    }
+}
+
+impl SplitUI {
+    /// Shows the window for splitting files into volumes.
+    ///
+    /// The automatic volume size is taken from the size limits of the selected hosts in MB.
+    /// Returns the split settings and whether to queue the upload, once the user chooses to upload.
+    pub fn show_split_window(&mut self, ctx: &Context, host_limits: Vec<u64>) -> Option<(SplitSettings, bool)> {
+        let mut upload = None;
+        let mut show_window = self.show_window;
+        let automatic_volume_size = automatic_volume_size(host_limits.into_iter());
+        Window::new("Split Into Volumes")
+            .default_width(600.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                ui.label("Packs files and folders into an archive split into volumes which every selected host accepts. \
+                The volumes are uploaded as one project and deleted afterwards.");
+                ui.horizontal(|ui| {
+                    if ui.button("Add files").clicked() {
+                        let mut dialog = FileDialog::open_file(None).multi_select(true);
+                        dialog.open();
+                        self.dialog = Some(dialog);
+                    }
+                    if ui.button("Add folder").clicked() {
+                        let mut dialog = FileDialog::select_folder(None);
+                        dialog.open();
+                        self.dialog = Some(dialog);
+                    }
+                    if ui.add_enabled(!self.sources.is_empty(), Button::new("Clear")).clicked() {
+                        self.sources.clear();
+                    }
+                });
+                let mut removed = None;
+                for (index, source) in self.sources.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("-").clicked() {
+                            removed = Some(index);
+                        }
+                        ui.label(source.to_string_lossy());
+                    });
+                }
+                if let Some(index) = removed {
+                    self.sources.remove(index);
+                }
+
+                Grid::new("Split Settings").num_columns(2).show(ui, |ui| {
+                    ui.label("Archive and project name:");
+                    ui.add(TextEdit::singleline(&mut self.archive_name).desired_width(300.0));
+                    ui.end_row();
+                    ui.label("Volume size:");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.automatic_size, true, "Smallest host limit");
+                        ui.radio_value(&mut self.automatic_size, false, "Set by hand");
+                        if self.automatic_size {
+                            ui.label(match automatic_volume_size {
+                                Some(size) => format_size(size),
+                                None => "The selected hosts have no size limit".to_string()
+                            });
+                        } else {
+                            ui.add(DragValue::new(&mut self.manual_size).clamp_range(1..=MAX_MANUAL_SIZE).suffix(" MB"));
+                        }
+                    });
+                    ui.end_row();
+                });
+
+                let volume_size = match self.automatic_size {
+                    true => automatic_volume_size,
+                    false => Some(self.manual_size.clamp(1, MAX_MANUAL_SIZE) * 1024 * 1024)
+                };
+                let ready = !self.sources.is_empty() && !self.archive_name.trim().is_empty() && volume_size.is_some();
+                ui.horizontal(|ui| {
+                    let split_settings = SplitSettings {
+                        sources: self.sources.clone(),
+                        archive_name: self.archive_name.trim().to_string(),
+                        volume_size: volume_size.unwrap_or_default(),
+                    };
+                    if ui.add_enabled(ready, Button::new("Split and upload")).clicked() {
+                        upload = Some((split_settings.clone(), false));
+                    }
+                    if ui.add_enabled(ready, Button::new("Split and add to queue")).clicked() {
+                        upload = Some((split_settings, true));
+                    }
+                });
+            });
+        self.show_window = show_window;
+
+        if let Some(dialog) = &mut self.dialog {
+            if dialog.show(ctx).selected() {
+                let mut paths: Vec<PathBuf> = dialog.selection().into_iter().map(|path| path.to_path_buf()).collect();
+                if paths.is_empty() {
+                    paths.extend(dialog.path().map(|path| path.to_path_buf()));
+                }
+                for path in paths {
+                    if self.archive_name.is_empty() {
+                        self.archive_name = path.file_stem().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                    }
+                    if !self.sources.contains(&path) {
+                        self.sources.push(path);
+                    }
+                }
+            }
+        }
+        upload
+    }
+}
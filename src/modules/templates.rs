@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use crate::modules::checksums::{FileHashes, Hasher};
+use crate::modules::general::format_size;
+use crate::modules::progress::FileProgress;
+
+/// The placeholders which are replaced in project descriptions, with what they are replaced by.
+pub const PLACEHOLDERS: [(&str, &str); 6] = [
+    ("{file_list}", "Each file and its size, one per line"),
+    ("{total_size}", "The combined size of the files"),
+    ("{file_count}", "The number of files"),
+    ("{hashes}", "The MD5 and SHA-256 of each disk file, which are computed before uploading"),
+    ("{upload_date}", "The date the upload started"),
+    ("{host_list}", "The selected hosts"),
+];
+
+/// Represents a named project description with placeholders.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct DescriptionTemplate {
+    pub name: String,
+    pub text: String,
+}
+
+impl DescriptionTemplate {
+    pub fn release() -> Self {
+        Self {
+            name: "Release".to_string(),
+            text: "{file_count} files, {total_size}\nUploaded on {upload_date} to {host_list}\n\n{file_list}\n\n{hashes}".to_string(),
+        }
+    }
+}
+
+/// Represents a file being uploaded, as it is described in a project description.
+pub struct TemplateFile {
+    pub name: String,
+    /// The size in bytes, which is unknown for remote files.
+    pub size: Option<u64>,
+    pub hashes: Option<FileHashes>,
+}
+
+pub fn has_placeholders(text: &str) -> bool {
+    PLACEHOLDERS.iter().any(|(placeholder, _)| text.contains(placeholder))
+}
+
+pub fn needs_hashes(text: &str) -> bool {
+    text.contains("{hashes}")
+}
+
+/// Replaces the placeholders in the text with the details of the files and hosts.
+///
+/// The text is read once, so placeholders inside the inserted details, e.g. in file names, are kept as they are.
+pub fn render(text: &str, files: &[TemplateFile], hosts: &HashSet<String>) -> String {
+    let file_list = files.iter()
+        .map(|file| match file.size {
+            Some(size) => format!("{} ({})", file.name, format_size(size)),
+            None => file.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let total_size = match files.iter().map(|file| file.size).sum::<Option<u64>>() {
+        Some(size) => format_size(size),
+        None => "Unknown".to_string(),
+    };
+    let hashes = files.iter()
+        .filter_map(|file| Some((&file.name, file.hashes.as_ref()?)))
+        .map(|(name, hashes)| format!("{}\nMD5: {}\nSHA-256: {}", name, hashes.md5, hashes.sha256))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let mut hosts: Vec<&String> = hosts.iter().collect();
+    hosts.sort();
+    let host_list = hosts.into_iter().cloned().collect::<Vec<_>>().join(", ");
+
+    let values = [
+        ("{file_list}", file_list),
+        ("{total_size}", total_size),
+        ("{file_count}", files.len().to_string()),
+        ("{hashes}", hashes),
+        ("{upload_date}", Local::now().format("%Y-%m-%d").to_string()),
+        ("{host_list}", host_list),
+    ];
+
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        rendered += &rest[..start];
+        rest = &rest[start..];
+        match values.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                rendered += value;
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered + rest
+}
+
+/// Reads a file on disk and returns its checksums. The bytes read are counted on the file's progress.
+pub fn hash_file(path: &Path, file_progress: &FileProgress) -> Result<FileHashes, String> {
+    let mut file = File::open(path).map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+    let mut hasher = Hasher::default();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        if file_progress.cancellation_token.is_cancelled() {
+            return Err("Cancelled".to_string());
+        }
+        let read = file.read(&mut buffer).map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        file_progress.add_sent(read);
+    }
+    Ok(hasher.hashes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_does_not_expand_placeholders_in_inserted_values() {
+        let files = [TemplateFile {
+            name: "{hashes}.bin".to_string(),
+            size: None,
+            hashes: Some(FileHashes {
+                crc32: 0,
+                md5: "md5".to_string(),
+                sha256: "sha256".to_string(),
+            }),
+        }];
+        let rendered = render("{file_count}: {file_list} {foo}\n{hashes}", &files, &HashSet::new());
+        assert_eq!(rendered, "1: {hashes}.bin {foo}\n{hashes}.bin\nMD5: md5\nSHA-256: sha256");
+    }
+}
@@ -1,25 +1,46 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
-use reqwest::{Client, multipart};
-use std::thread;
+use std::sync::Arc;
+use std::time::Duration;
+use reqwest::multipart;
 use crossbeam_channel::{Receiver, Sender};
 use eframe::egui;
 use eframe::egui::{Align2, Button, Checkbox, ComboBox, Context, FontFamily, FontId, Id, Label, ScrollArea, TextEdit, TextStyle, Ui, Window};
 use eframe::egui::Direction::TopDown;
 use egui_file::FileDialog;
 use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
+use serde::{Deserialize, Serialize};
 use tokio::io::AsyncReadExt;
-use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
 use tokio_util::codec::{BytesCodec, FramedRead};
+use tokio_util::sync::CancellationToken;
+use crate::modules::accounts::{self, AccountProfile};
+use crate::modules::checksums;
+use crate::modules::folders::{FolderAction, FolderUploadUI};
+use crate::modules::general::add_alerts;
+use crate::modules::host_check::{HostCheckChoice, HostCheckUI, HostMatrix, hosts_for_file};
 use crate::modules::api::{AddProject, AvailableHosts, get_fastest_server, Login, LoginResponse, MultiUpFileUploadResponse, MultiUpRemoteUploadResponse, MultiUpUploadResponses, UploadedFileDetails};
+use crate::modules::ledger::{LedgerUI, record_uploads, UploadContext};
+use crate::modules::jobs::{Job, JobKind, JobRerun, JobStatus, spawn_job};
 use crate::modules::links::LinkError;
+use crate::modules::network::{self, Service};
+use crate::modules::progress::{FileProgress, FileStatus, track_stream, UploadProgress};
+use crate::modules::queue::{self, QueueStatus, QueueUI};
+use crate::modules::remote_status::RemoteTracker;
+use crate::modules::settings::{Settings, settings, update_settings};
+use crate::modules::split::{self, SplitSettings, SplitUI};
+use crate::modules::templates::{self, DescriptionTemplate, TemplateFile};
+use crate::modules::vault::{self, Credentials};
+use crate::modules::verification::{display_verifications, LinkVerification, verify_uploaded_links};
 
 #[derive(Default)]
 struct Channels {
     login: Option<Receiver<Result<LoginResponse, LinkError>>>,
     hosts: Option<Receiver<Result<AvailableHosts, LinkError>>>,
     upload: Option<Receiver<Vec<MultiUpUploadResponses>>>,
+    verification: Option<Receiver<BTreeMap<String, LinkVerification>>>,
+    cancel: Option<CancellationToken>,
 }
 
 // impl Channels {
@@ -35,14 +56,15 @@ struct Channels {
 //     }
 // }
 
-#[derive(Clone, Default, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub enum UploadType {
     Disk,
     #[default]
     Remote,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct ProjectSettings {
     is_project: bool,
     name: String,
@@ -50,7 +72,23 @@ pub struct ProjectSettings {
     description: String,
 }
 
-#[derive(Clone)]
+impl ProjectSettings {
+    /// Returns the settings with the placeholders in the description filled in with the files and hosts.
+    fn render_description(&self, files: &[TemplateFile], hosts: &HashSet<String>) -> Self {
+        Self {
+            description: templates::render(&self.description, files, hosts),
+            ..self.clone()
+        }
+    }
+
+    /// Returns `true` if the description has placeholders to fill in when the project is created.
+    fn is_templated(&self) -> bool {
+        self.is_project && templates::has_placeholders(&self.description)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct RemoteUploadSettings {
     project_settings: ProjectSettings,
     upload_links: String,
@@ -73,12 +111,84 @@ impl Default for RemoteUploadSettings {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct DiskUploadSettings {
     project_settings: ProjectSettings,
     pub file_paths: Vec<PathBuf>,
     pub file_names: Vec<String>,
-    hosts: HashSet<String>
+    hosts: HashSet<String>,
+    /// Upload each file in its own request.
+    separate_requests: bool,
+    /// The size limit in MB of each selected host, so each file can be sent to the hosts which accept it.
+    host_limits: BTreeMap<String, u64>,
+    /// Files and folders packed into archive volumes when the upload starts, which are uploaded instead of the file paths.
+    split: Option<SplitSettings>,
+    /// Upload checksum manifests of the files after they are uploaded.
+    upload_manifests: bool,
+}
+
+/// Represents everything needed to start an upload, so that it can be run again later.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UploadRequest {
+    upload_type: UploadType,
+    remote_upload_settings: RemoteUploadSettings,
+    disk_upload_settings: DiskUploadSettings,
+    login: Login,
+    /// The account profile the upload was made with.
+    profile: Option<String>,
+}
+
+impl UploadRequest {
+    pub fn description(&self) -> String {
+        let description = match self.upload_type {
+            UploadType::Remote => format!("Remote upload of {} links", process_urls_and_names(&self.remote_upload_settings.upload_links, &self.remote_upload_settings.file_names).0.len()),
+            UploadType::Disk => match &self.disk_upload_settings.split {
+                Some(split_settings) => format!("Split upload of {}", split_settings.archive_name),
+                None => format!("Disk upload of {} files", self.disk_upload_settings.file_paths.len()),
+            },
+        };
+        match &self.profile {
+            Some(profile) => format!("{} as {}", description, profile),
+            None => description
+        }
+    }
+
+    /// Returns the hosts selected for the upload type.
+    fn hosts_mut(&mut self) -> &mut HashSet<String> {
+        match self.upload_type {
+            UploadType::Remote => &mut self.remote_upload_settings.hosts,
+            UploadType::Disk => &mut self.disk_upload_settings.hosts,
+        }
+    }
+
+    pub fn clear_password(&mut self) {
+        self.login.password.clear();
+    }
+
+    /// Fills in the MultiUp password from the vault, as it is not saved with queued uploads.
+    ///
+    /// Returns `false` if the upload needs a password which is not available.
+    pub fn fill_password(&mut self) -> bool {
+        if !self.login.password.is_empty() || self.login.username.is_empty() {
+            return true;
+        }
+        let password = match &self.profile {
+            Some(profile) => accounts::profile(profile).and_then(|profile| profile.password()),
+            None => vault::credentials()
+                .filter(|credentials| credentials.multiup_username == self.login.username)
+                .map(|credentials| credentials.multiup_password)
+        };
+        match password {
+            Some(password) => {
+                self.login.password = password;
+                true
+            }
+            // Disk uploads only need the user ID
+            None => self.upload_type == UploadType::Disk
+        }
+    }
 }
 
 #[derive(Default)]
@@ -88,20 +198,86 @@ pub struct UploadUI {
     show_login_window: bool,
     login_details: Login,
     login_response: LoginResponse,
+    profile: Option<String>,
+    new_profile_name: String,
     pub upload_type: UploadType,
     pub disk_upload_settings: DiskUploadSettings,
     open_file_dialogue: Option<FileDialog>,
+    folder_ui: FolderUploadUI,
+    split_ui: SplitUI,
     remote_upload_settings: RemoteUploadSettings,
     hosts: AvailableHosts,
     uploading: bool,
     multiup_links: Vec<String>,
+    /// The links of the files which were uploaded successfully, which can be verified.
+    uploaded_links: Vec<String>,
+    /// The hosts found by extracting the uploaded links, keyed by link.
+    verifications: BTreeMap<String, LinkVerification>,
+    verification_job: Option<Arc<Job>>,
+    /// The progress of each file in the running or last upload.
+    progress: Option<Arc<UploadProgress>>,
+    /// The name of the checksum manifests of the running or last upload.
+    manifest_name: String,
+    manifest_dialog: Option<FileDialog>,
+    /// The name the project description is saved under as a template.
+    template_name: String,
+    /// Remote uploads whose hosts are checked until MultiUp has sent them the file.
+    remote_tracker: Arc<RemoteTracker>,
+    ledger_ui: LedgerUI,
+    queue_ui: QueueUI,
+    host_check_ui: HostCheckUI,
+    /// An upload waiting for the user to decide about hosts which cannot accept its files, and whether to queue it.
+    pending_upload: Option<(UploadRequest, bool)>,
+    /// The queued upload which is running.
+    queue_item: Option<u64>,
+    /// The profile of the running upload and of the links shown.
+    upload_profile: (Option<String>, Option<String>),
 }
 
 impl UploadUI {
-    pub fn display(ctx: &Context, ui: &mut Ui, upload_ui: &mut UploadUI) {
+    /// Creates the tab with the defaults from the settings.
+    pub fn new(settings: &Settings) -> Self {
+        let mut upload_ui = Self {
+            upload_type: settings.upload.upload_type.clone(),
+            ..Default::default()
+        };
+        upload_ui.remote_upload_settings.data_streaming = settings.upload.data_streaming;
+        upload_ui.disk_upload_settings.separate_requests = settings.upload.separate_requests;
+        upload_ui.disk_upload_settings.upload_manifests = settings.upload.upload_manifests;
+        if let Some(profile) = accounts::active_profile() {
+            upload_ui.use_profile(&profile);
+        }
+        upload_ui
+    }
+
+    /// Switches to the account of the profile using its cached login.
+    fn use_profile(&mut self, profile: &AccountProfile) {
+        self.login_details = Login {
+            username: profile.username.clone(),
+            password: profile.password().unwrap_or_default(),
+            user_id: profile.user_id.map(|user| user.to_string()),
+        };
+        self.login_response = profile.login_response();
+        self.profile = Some(profile.name.clone());
+        if !profile.favourite_hosts.is_empty() {
+            for (host, details) in self.hosts.hosts.iter_mut() {
+                details.selected = profile.favourite_hosts.contains(host);
+            }
+        }
+    }
+
+    /// Switches back to an anonymous upload.
+    fn clear_profile(&mut self) {
+        self.login_details = Login::default();
+        self.login_response = LoginResponse::default();
+        self.profile = None;
+    }
+
+    pub fn display(ctx: &Context, ui: &mut Ui, upload_ui: &mut UploadUI, alerts: Vec<String>) {
         upload_ui.toasts = Toasts::new()
             .anchor(Align2::RIGHT_TOP, (10.0, 10.0))
             .direction(TopDown);
+        add_alerts(&mut upload_ui.toasts, alerts);
 
         upload_ui.display_login_information(ui);
         upload_ui.display_login_window(ctx);
@@ -111,9 +287,330 @@ impl UploadUI {
             UploadType::Remote => upload_ui.display_remote_upload_area(ui),
         };
         upload_ui.display_hosts_selection(ui);
+        upload_ui.display_progress_area(ui);
+        upload_ui.display_remote_status_area(ui);
         upload_ui.display_uploaded_links_area(ui);
+        upload_ui.ledger_ui.show_ledger_window(ctx);
+        upload_ui.queue_ui.show_queue_window(ctx);
+        if let Some(action) = upload_ui.folder_ui.show_folder_window(ctx) {
+            upload_ui.handle_folder_action(action);
+        }
+        let host_limits = upload_ui.hosts.hosts.values()
+            .filter(|details| details.selected)
+            .map(|details| details.size)
+            .collect();
+        if let Some((split_settings, queue)) = upload_ui.split_ui.show_split_window(ctx, host_limits) {
+            upload_ui.upload_split(split_settings, queue);
+        }
+        if let Some(choice) = upload_ui.host_check_ui.show_host_check_window(ctx) {
+            upload_ui.handle_host_check_choice(choice);
+        }
 
         upload_ui.toasts.show(ctx);
+        if upload_ui.uploading {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+    }
+
+    /// Fills in the MultiUp login from the unlocked vault, or clears the password when it locks.
+    pub fn set_credentials(&mut self, credentials: Option<&Credentials>) {
+        match credentials {
+            Some(credentials) => {
+                if let Some(password) = credentials.account_passwords.get(&self.login_details.username).filter(|_| self.profile.is_some()) {
+                    self.login_details.password = password.clone();
+                } else if self.profile.is_none() && !credentials.multiup_username.is_empty() {
+                    self.login_details.username = credentials.multiup_username.clone();
+                    self.login_details.password = credentials.multiup_password.clone();
+                }
+            }
+            None => self.login_details.password.clear()
+        }
+    }
+
+    /// Returns the login details of the current MultiUp account.
+    pub fn login(&self) -> &Login {
+        &self.login_details
+    }
+
+    /// Receives the results of the running upload and starts the next queued upload.
+    ///
+    /// Runs every frame, so the queue keeps running while another tab is shown.
+    pub fn update(&mut self, ctx: &Context) {
+        self.receive_upload_results();
+        self.receive_verifications();
+        if self.remote_tracker.is_tracking() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+        if queue::has_waiting_items() && !queue::is_paused() {
+            // Check the schedule again even if nothing else causes a repaint
+            ctx.request_repaint_after(Duration::from_secs(30));
+        }
+        if self.uploading {
+            return;
+        }
+        if let Some(mut item) = queue::next_item() {
+            if item.request.fill_password() {
+                self.queue_ui.notice = None;
+                let _ = queue::set_status(item.id, QueueStatus::Running);
+                self.queue_item = Some(item.id);
+                self.start_upload(item.request);
+            } else {
+                self.queue_ui.notice = Some("Unlock the credential vault to start the next upload, as MultiUp passwords are not saved in the queue.".to_string());
+            }
+        }
+    }
+
+    fn receive_upload_results(&mut self) {
+        if let Some(response) = &self.channels.upload {
+            if let Ok(result) = response.try_recv() {
+                self.uploading = false;
+                if let Some(id) = self.queue_item.take() {
+                    let status = match failed_uploads(&result) {
+                        0 => QueueStatus::Completed("Uploaded".to_string()),
+                        failed => QueueStatus::Failed(format!("{} uploads failed", failed))
+                    };
+                    let _ = queue::set_status(id, status);
+                }
+                self.upload_profile.1 = self.upload_profile.0.take();
+                let mut multiup_links = vec![];
+                let mut uploaded_links = vec![];
+                let mut remote_links = vec![];
+                let mut project_hash = String::new();
+                for response in result {
+                    match response {
+                        MultiUpUploadResponses::MultiUpFileUpload(result) => {
+                            if let Ok(response) = &result {
+                                uploaded_links.extend(response.files.iter().filter_map(|file| file.url.clone()));
+                            }
+                            let response = result.unwrap_or_else(|error| MultiUpFileUploadResponse {
+                                files: vec![UploadedFileDetails {
+                                    name: None,
+                                    hash: None,
+                                    size: None,
+                                    file_type: None,
+                                    url: Some(format!("{:?}", error)),
+                                    sid: None,
+                                    user: None,
+                                    delete_url: None,
+                                    delete_type: None
+                                }],
+                                project_hash: None
+                            });
+
+                            for file in response.files {
+                                if let Some(url) = file.url {
+                                    multiup_links.push(url);
+                                }
+                            }
+
+                            if let Some(hash) = response.project_hash {
+                                project_hash = hash;
+                            }
+                        }
+                        MultiUpUploadResponses::MultiUpRemoteUpload(result) => {
+                            let response = result.unwrap_or_else(|error| MultiUpRemoteUploadResponse {
+                                error: format!("{:?}", error),
+                                link: None,
+                                size: None,
+                                file_name: None,
+                                project_hash: None,
+                            });
+                            match response.error.as_str() {
+                                "success" => {
+                                    if let Some(link) = response.link {
+                                        let file_name = response.file_name.unwrap_or_else(|| link.clone());
+                                        remote_links.push((link.clone(), file_name));
+                                        uploaded_links.push(link.clone());
+                                        multiup_links.push(link);
+                                    }
+
+                                    if let Some(hash) = response.project_hash {
+                                        project_hash = hash;
+                                    }
+                                },
+                                _ => {
+                                    multiup_links.push(format!("{:?}", response.error));
+                                }
+                            }
+                        }
+                    }
+                }
+
+                self.verifications.clear();
+                self.uploaded_links = uploaded_links;
+                let upload_settings = settings().upload;
+                match upload_settings.verify_after_upload {
+                    true => self.verify_links(Duration::from_secs(upload_settings.verify_delay_minutes * 60)),
+                    false => self.cancel_verification(),
+                }
+
+                if !project_hash.is_empty() {
+                    multiup_links.insert(0, format!("https://multiup.io/en/project/{}", project_hash));
+                }
+
+                self.multiup_links = multiup_links;
+                if upload_settings.track_remote_uploads {
+                    self.remote_tracker.track(remote_links);
+                }
+            }
+        }
+    }
+
+    /// Extracts the uploaded links after the delay, so they can be annotated with the hosts which came up.
+    ///
+    /// Replaces any pending verification. Failed uploads and the project link are left out.
+    fn verify_links(&mut self, delay: Duration) {
+        self.cancel_verification();
+        let (verification_sender, verification_receiver) = crossbeam_channel::unbounded();
+        self.verification_job = verify_uploaded_links(self.uploaded_links.clone(), delay, verification_sender);
+        if self.verification_job.is_some() {
+            self.channels.verification = Some(verification_receiver);
+        }
+    }
+
+    fn cancel_verification(&mut self) {
+        if let Some(job) = self.verification_job.take() {
+            job.cancel();
+        }
+        self.channels.verification = None;
+    }
+
+    fn receive_verifications(&mut self) {
+        if let Some(receiver) = &self.channels.verification {
+            if let Ok(verifications) = receiver.try_recv() {
+                self.verifications = verifications;
+                self.channels.verification = None;
+                self.verification_job = None;
+            }
+        }
+    }
+
+    /// Uploads the files described by the request as a background job.
+    fn start_upload(&mut self, request: UploadRequest) {
+        self.uploading = true;
+        let (upload_sender, upload_receiver) = crossbeam_channel::unbounded();
+        self.channels.upload = Some(upload_receiver);
+
+        let description = request.description();
+        self.upload_profile.0 = request.profile.clone();
+        let upload_context = UploadContext {
+            profile: request.profile.clone(),
+            username: Some(request.login.username.clone()).filter(|username| !username.is_empty()),
+            remote: request.upload_type == UploadType::Remote,
+            hosts: match request.upload_type {
+                UploadType::Remote => request.remote_upload_settings.hosts.iter().cloned().collect(),
+                UploadType::Disk => request.disk_upload_settings.hosts.iter().cloned().collect(),
+            },
+            sources: match request.upload_type {
+                UploadType::Remote => process_urls_and_names(&request.remote_upload_settings.upload_links, &request.remote_upload_settings.file_names).0,
+                UploadType::Disk => request.disk_upload_settings.file_paths.iter().map(|path| path.to_string_lossy().to_string()).collect(),
+            },
+        };
+        let progress = Arc::new(UploadProgress::default());
+        self.progress = Some(Arc::clone(&progress));
+        self.manifest_name = manifest_name(&request.disk_upload_settings.project_settings);
+        let rerun = JobRerun::Upload(Box::new(request.clone()));
+        let job = spawn_job(JobKind::Upload, description, Some(rerun), |job| async move {
+            let UploadRequest { upload_type, remote_upload_settings, disk_upload_settings, login, .. } = request;
+            let split_directory = disk_upload_settings.split.as_ref().map(|_| split::temp_directory());
+            let upload = async {
+                match upload_type {
+                    UploadType::Remote => {
+                        let (urls, file_names) = process_urls_and_names(&remote_upload_settings.upload_links, &remote_upload_settings.file_names);
+                        let mut project_settings = remote_upload_settings.project_settings.clone();
+                        if project_settings.is_templated() {
+                            let files: Vec<TemplateFile> = urls.iter().enumerate()
+                                .map(|(index, url)| TemplateFile {
+                                    name: file_names.get(index).filter(|name| !name.is_empty()).unwrap_or(url).clone(),
+                                    size: None,
+                                    hashes: None,
+                                })
+                                .collect();
+                            project_settings = project_settings.render_description(&files, &remote_upload_settings.hosts);
+                        }
+                        let project_hash = get_project_hash(&project_settings, login.user_id.clone(), upload_sender.clone()).await;
+                        let responses = if remote_upload_settings.force_data_streaming {
+                            vec![MultiUpUploadResponses::MultiUpFileUpload(stream_file(&urls, &file_names, login.user_id, remote_upload_settings.hosts, project_hash.clone(), &progress).await)]
+                        } else {
+                            remote_upload_files(&urls, &file_names, login, remote_upload_settings.hosts, project_hash.clone(), &progress).await
+                        };
+                        (responses, vec![])
+                    },
+                    UploadType::Disk => {
+                        let mut disk_upload_settings = disk_upload_settings;
+                        if let (Some(split_settings), Some(directory)) = (disk_upload_settings.split.clone(), split_directory.clone()) {
+                            let packing_progress = Arc::clone(&progress);
+                            let packed = tokio::task::spawn_blocking(move || {
+                                let file_progress = packing_progress.add_file(format!("Packing {}", split_settings.archive_name), Some(split::sources_size(&split_settings.sources)));
+                                let result = split::create_split_archive(&split_settings, &directory, &file_progress);
+                                file_progress.finish(match &result {
+                                    Ok(_) => FileStatus::Done,
+                                    Err(error) => FileStatus::Failed(error.clone()),
+                                });
+                                result
+                            }).await.unwrap_or_else(|error| Err(error.to_string()));
+                            match packed {
+                                Ok(volumes) => {
+                                    disk_upload_settings.file_names = vec![String::new(); volumes.len()];
+                                    disk_upload_settings.file_paths = volumes;
+                                }
+                                Err(error) => return (vec![MultiUpUploadResponses::MultiUpFileUpload(Err(LinkError::File(error)))], vec![])
+                            }
+                        }
+                        let mut project_settings = disk_upload_settings.project_settings.clone();
+                        if project_settings.is_templated() {
+                            let files = disk_template_files(&disk_upload_settings, templates::needs_hashes(&project_settings.description), &progress).await;
+                            project_settings = project_settings.render_description(&files, &disk_upload_settings.hosts);
+                        }
+                        let project_hash = get_project_hash(&project_settings, login.user_id.clone(), upload_sender.clone()).await;
+                        let responses = if disk_upload_settings.separate_requests {
+                            disk_upload_files_separately(&disk_upload_settings, login.clone(), project_hash.clone(), &progress).await
+                        } else {
+                            disk_upload_files(&disk_upload_settings.file_paths, &disk_upload_settings.file_names, login.clone(), disk_upload_settings.hosts.clone(), project_hash.clone(), &progress).await
+                        };
+                        if !disk_upload_settings.upload_manifests {
+                            return (responses, vec![]);
+                        }
+                        let base_name = manifest_name(&disk_upload_settings.project_settings);
+                        let manifest_responses = upload_manifests(&base_name, &progress, login, disk_upload_settings.hosts, project_hash).await;
+                        (responses, manifest_responses)
+                    }
+                }
+            };
+
+            // Dropping the upload future aborts any requests which are still in flight
+            let (mut responses, manifest_responses) = tokio::select! {
+                responses = upload => responses,
+                _ = job.cancellation_token.cancelled() => {
+                    progress.cancel();
+                    (vec![MultiUpUploadResponses::MultiUpFileUpload(Err(LinkError::Cancelled))], vec![])
+                }
+            };
+            if let Some(directory) = split_directory {
+                let _ = tokio::fs::remove_dir_all(directory).await;
+            }
+            let failed = failed_uploads(&responses) + failed_uploads(&manifest_responses);
+            let mismatches = progress.hash_mismatches();
+            // The manifests have no sources, so they are recorded apart from the files they describe
+            let mut entries = upload_context.entries(&responses);
+            entries.extend(UploadContext { sources: vec![], ..upload_context }.entries(&manifest_responses));
+            let _ = record_uploads(entries);
+            responses.extend(manifest_responses);
+            let _ = upload_sender.send(responses);
+            match (failed, mismatches) {
+                (0, 0) => JobStatus::Completed("Uploaded".to_string()),
+                (0, mismatches) => JobStatus::Failed(format!("{} files did not match the hash returned by MultiUp", mismatches)),
+                (failed, _) => JobStatus::Failed(format!("{} uploads failed", failed))
+            }
+        });
+        self.channels.cancel = Some(job.cancellation_token.clone());
+    }
+
+    /// Runs a previous upload again, unless an upload is already running.
+    pub fn rerun_upload(&mut self, request: UploadRequest) {
+        if !self.uploading {
+            self.start_upload(request);
+        }
     }
 
     fn display_login_information(&mut self, ui: &mut Ui) {
@@ -134,6 +631,16 @@ impl UploadUI {
                                 self.login_response = login_response;
                                 self.login_details.user_id = self.login_response.user.map(|user| user.to_string());
                                 self.show_login_window = false;
+                                // Keep the cached details of the active profile up to date
+                                if let Some(mut profile) = self.profile.as_deref().and_then(accounts::profile) {
+                                    if profile.username == self.login_details.username {
+                                        profile.update_from(&self.login_response);
+                                        let _ = accounts::save_profile(profile);
+                                    } else {
+                                        self.profile = None;
+                                        let _ = accounts::set_active_profile(None);
+                                    }
+                                }
                             }
                             _ => {
                                 self.toasts.add(Toast {
@@ -175,7 +682,42 @@ impl UploadUI {
         if let Some(id) = &self.login_response.user {
             user = format!("{} (ID: {})", user, id);
         }
+        if let Some(account_type) = &self.login_response.account_type {
+            user = format!("{}, {}", user, account_type);
+        }
+        if let Some(premium_days_left) = &self.login_response.premium_days_left {
+            user = format!("{}, {} premium days left", user, premium_days_left);
+        }
         ui.horizontal(|ui| {
+            let profiles = accounts::profiles();
+            ui.label("Profile:");
+            let mut selected_profile = self.profile.clone();
+            ComboBox::from_id_source("Account Profile")
+                .selected_text(selected_profile.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected_profile, None, "None");
+                    for profile in &profiles {
+                        ui.selectable_value(&mut selected_profile, Some(profile.name.clone()), &profile.name);
+                    }
+                });
+            if selected_profile != self.profile {
+                match selected_profile.as_ref().and_then(|name| profiles.iter().find(|profile| &profile.name == name)) {
+                    Some(profile) => self.use_profile(profile),
+                    None => self.clear_profile()
+                }
+                let _ = accounts::set_active_profile(self.profile.clone());
+            }
+            if let Some(profile) = self.profile.clone() {
+                if ui.button("Delete profile").clicked() {
+                    let _ = accounts::remove_profile(&profile);
+                    let _ = vault::update_credentials(|credentials| {
+                        credentials.account_passwords.remove(&self.login_details.username);
+                    });
+                    self.clear_profile();
+                }
+            }
+
+            ui.separator();
             ui.label(format!("Logged in as: {}", user));
             let login_text = if self.login_response.user.is_some() {
                 "Change user"
@@ -210,14 +752,72 @@ impl UploadUI {
                     if ui.button("Login").clicked() {
                         let (login_sender, login_receiver) = crossbeam_channel::unbounded();
                         self.channels.login = Some(login_receiver);
-                        let rt = Runtime::new().unwrap();
                         let login_details = self.login_details.clone();
-                        thread::spawn(move || {
-                            rt.block_on(async {
-                                let login_result = login_details.login().await;
-
-                                let _ = login_sender.send(login_result);
+                        spawn_job(JobKind::Login, format!("Log in as {}", login_details.username), None, |_| async move {
+                            let login_result = login_details.login().await;
+                            let status = match &login_result {
+                                Ok(_) => JobStatus::Completed("Logged in".to_string()),
+                                Err(error) => JobStatus::Failed(format!("{:?}", error))
+                            };
+
+                            let _ = login_sender.send(login_result);
+                            status
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        ui.add(TextEdit::singleline(&mut self.new_profile_name)
+                            .hint_text("Profile name")
+                            .desired_width(150.0));
+                        if ui.add_enabled(!self.new_profile_name.trim().is_empty(), Button::new("Save as profile")).clicked() {
+                            let mut profile = AccountProfile {
+                                name: self.new_profile_name.trim().to_string(),
+                                username: self.login_details.username.clone(),
+                                ..accounts::profile(self.new_profile_name.trim()).unwrap_or_default()
+                            };
+                            if self.login_response.login.as_ref() == Some(&self.login_details.username) {
+                                profile.update_from(&self.login_response);
+                            }
+                            let password = self.login_details.password.clone();
+                            let username = profile.username.clone();
+                            let password_saved = vault::update_credentials(|credentials| {
+                                credentials.account_passwords.insert(username, password);
+                            });
+                            let saved = accounts::save_profile(profile.clone())
+                                .and_then(|_| accounts::set_active_profile(Some(profile.name.clone())));
+                            let (text, kind) = match (saved, password_saved) {
+                                (Ok(()), Ok(())) => (format!("Saved profile {}", profile.name), ToastKind::Success),
+                                (Ok(()), Err(error)) => (format!("Saved profile {} without its password: {}", profile.name, error), ToastKind::Warning),
+                                (Err(error), _) => (format!("Failed to save profile: {}", error), ToastKind::Error)
+                            };
+                            self.profile = Some(profile.name);
+                            self.new_profile_name.clear();
+                            self.toasts.add(Toast {
+                                text: text.into(),
+                                kind,
+                                options: ToastOptions::default()
+                                    .duration_in_seconds(5.0)
+                                    .show_progress(true)
+                                    .show_icon(true)
                             });
+                        }
+                    });
+                    if ui.button("Save to vault").clicked() {
+                        let (username, password) = (self.login_details.username.clone(), self.login_details.password.clone());
+                        let saved = vault::update_credentials(|credentials| {
+                            credentials.multiup_username = username;
+                            credentials.multiup_password = password;
+                        });
+                        let (text, kind) = match saved {
+                            Ok(()) => ("Saved login to the vault".to_string(), ToastKind::Success),
+                            Err(error) => (format!("Failed to save login: {}", error), ToastKind::Error)
+                        };
+                        self.toasts.add(Toast {
+                            text: text.into(),
+                            kind,
+                            options: ToastOptions::default()
+                                .duration_in_seconds(5.0)
+                                .show_progress(true)
+                                .show_icon(true)
                         });
                     }
                 });
@@ -238,7 +838,7 @@ impl UploadUI {
                 });
         });
 
-        fn display_project_settings(ui: &mut Ui, project_settings: &mut ProjectSettings) {
+        fn display_project_settings(ui: &mut Ui, project_settings: &mut ProjectSettings, template_name: &mut String) {
             ui.checkbox(&mut project_settings.is_project, "Upload as project");
             if project_settings.is_project {
                 ui.vertical(|ui| {
@@ -246,15 +846,68 @@ impl UploadUI {
                         .hint_text("Enter project name"));
                     ui.add(TextEdit::singleline(&mut project_settings.password)
                         .hint_text("Enter project password (optional)"));
-                    ui.add(TextEdit::singleline(&mut project_settings.description)
-                        .hint_text("Enter project description (optional"));
+                    ui.add(TextEdit::multiline(&mut project_settings.description)
+                        .hint_text("Enter project description (optional)")
+                        .desired_rows(2));
+                    display_description_templates(ui, project_settings, template_name);
                 });
             }
         }
+
+        /// Fills in the description from a saved template, or saves the description as a template.
+        fn display_description_templates(ui: &mut Ui, project_settings: &mut ProjectSettings, template_name: &mut String) {
+            let description_templates = settings().upload.description_templates;
+            ui.horizontal(|ui| {
+                ComboBox::from_id_source("Description Template")
+                    .selected_text("Use template")
+                    .show_ui(ui, |ui| {
+                        for template in &description_templates {
+                            if ui.selectable_label(false, &template.name).clicked() {
+                                project_settings.description = template.text.clone();
+                                *template_name = template.name.clone();
+                            }
+                        }
+                    });
+                if ui.label("(?)").hovered() {
+                    egui::show_tooltip(ui.ctx(), Id::new("Description Template Tooltip"), |ui| {
+                        ui.label("These placeholders in the description are filled in when the project is created:");
+                        for (placeholder, description) in templates::PLACEHOLDERS {
+                            ui.label(format!("{} - {}", placeholder, description));
+                        }
+                    });
+                };
+                ui.add(TextEdit::singleline(template_name)
+                    .hint_text("Template name")
+                    .desired_width(120.0));
+                let name = template_name.trim().to_string();
+                let exists = description_templates.iter().any(|template| template.name == name);
+                let save_text = match exists {
+                    true => "Update template",
+                    false => "Save as template"
+                };
+                if ui.add_enabled(!name.is_empty() && !project_settings.description.is_empty(), Button::new(save_text)).clicked() {
+                    let template = DescriptionTemplate {
+                        name: name.clone(),
+                        text: project_settings.description.clone(),
+                    };
+                    let _ = update_settings(|settings| {
+                        let templates = &mut settings.upload.description_templates;
+                        match templates.iter_mut().find(|template| template.name == name) {
+                            Some(existing) => *existing = template,
+                            None => templates.push(template),
+                        }
+                    });
+                }
+                if ui.add_enabled(exists, Button::new("Delete template")).clicked() {
+                    let _ = update_settings(|settings| settings.upload.description_templates.retain(|template| template.name != name));
+                }
+            });
+        }
+
         ui.horizontal(|ui| {
             match self.upload_type {
-                UploadType::Disk => display_project_settings(ui, &mut self.disk_upload_settings.project_settings),
-                UploadType::Remote => display_project_settings(ui, &mut self.remote_upload_settings.project_settings)
+                UploadType::Disk => display_project_settings(ui, &mut self.disk_upload_settings.project_settings, &mut self.template_name),
+                UploadType::Remote => display_project_settings(ui, &mut self.remote_upload_settings.project_settings, &mut self.template_name)
             }
         });
     }
@@ -295,6 +948,22 @@ impl UploadUI {
     }
 
     fn display_disk_upload_area(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.disk_upload_settings.separate_requests, "Upload files separately");
+            if ui.label("(?)").hovered() {
+                egui::show_tooltip(ui.ctx(), Id::new("Separate Requests Tooltip"), |ui| {
+                    ui.label("Uploads each file in its own request, so a failed file does not affect the others and can be cancelled on its own. \
+                Failed files are retried. The number of files uploaded at once and the number of retries can be changed in the settings.");
+                });
+            };
+            ui.checkbox(&mut self.disk_upload_settings.upload_manifests, "Upload checksum manifests");
+            if ui.label("(?)").hovered() {
+                egui::show_tooltip(ui.ctx(), Id::new("Upload Manifests Tooltip"), |ui| {
+                    ui.label("Uploads SFV, MD5 and SHA-256 manifests of the uploaded files once they are done, into the same project if one is created.");
+                });
+            };
+        });
+
         ScrollArea::vertical().id_source("Disk Upload")
             .max_height(ui.available_height() / 2.0)
             .show(ui, |ui| {
@@ -317,11 +986,19 @@ impl UploadUI {
                                 .desired_width(half_width));
                         }
                         let button_text = format!("Add file(s) - {} files added", self.disk_upload_settings.file_paths.len());
-                        if columns[0].button(button_text).clicked() {
-                            let mut dialog = FileDialog::open_file(None).multi_select(true);
-                            dialog.open();
-                            self.open_file_dialogue = Some(dialog);
-                        }
+                        columns[0].horizontal(|ui| {
+                            if ui.button(button_text).clicked() {
+                                let mut dialog = FileDialog::open_file(None).multi_select(true);
+                                dialog.open();
+                                self.open_file_dialogue = Some(dialog);
+                            }
+                            if ui.button("Upload folders").clicked() {
+                                self.folder_ui.show_window = true;
+                            }
+                            if ui.button("Split into volumes").clicked() {
+                                self.split_ui.show_window = true;
+                            }
+                        });
                     });
 
                     if let Some(dialog) = &mut self.open_file_dialogue {
@@ -341,7 +1018,21 @@ impl UploadUI {
     }
 
     fn display_hosts_selection(&mut self, ui: &mut Ui) {
-        ui.heading("Hosts");
+        ui.horizontal(|ui| {
+            ui.heading("Hosts");
+            let selected = self.hosts.hosts.values().filter(|details| details.selected).count();
+            let max_hosts = self.hosts.max_hosts as usize;
+            match max_hosts {
+                0 => ui.label(format!("{} selected", selected)),
+                max_hosts if selected > max_hosts => ui.colored_label(ui.visuals().error_fg_color, format!("{} selected, MultiUp accepts at most {}", selected, max_hosts)),
+                max_hosts => ui.label(format!("{} of at most {} selected", selected, max_hosts)),
+            };
+            if ui.button("Check file sizes").clicked() {
+                let request = self.current_request();
+                let matrix = self.host_matrix(&request);
+                self.host_check_ui.open(matrix, false);
+            }
+        });
 
         if self.hosts.hosts.is_empty() && self.channels.hosts.is_none() {
             self.toasts.add(Toast {
@@ -355,13 +1046,15 @@ impl UploadUI {
 
             let (hosts_sender, hosts_receiver) = crossbeam_channel::unbounded();
             self.channels.hosts = Some(hosts_receiver);
-            let rt = Runtime::new().unwrap();
-            thread::spawn(move || {
-                rt.block_on(async {
-                    let hosts = AvailableHosts::get().await;
+            spawn_job(JobKind::Hosts, "Get MultiUp hosts".to_string(), None, |_| async move {
+                let hosts = AvailableHosts::get().await;
+                let status = match &hosts {
+                    Ok(hosts) => JobStatus::Completed(format!("{} hosts", hosts.hosts.len())),
+                    Err(error) => JobStatus::Failed(format!("{:?}", error))
+                };
 
-                    hosts_sender.send(hosts).unwrap();
-                });
+                let _ = hosts_sender.send(hosts);
+                status
             });
         }
 
@@ -369,8 +1062,14 @@ impl UploadUI {
             if let Ok(hosts) = hosts.try_recv() {
                 match hosts {
                     Ok(mut hosts) => {
-                        for (_host, details) in hosts.hosts.iter_mut() {
-                            if details.selection == "true" {
+                        let default_hosts = match self.profile.as_deref().and_then(accounts::profile) {
+                            Some(profile) if !profile.favourite_hosts.is_empty() => profile.favourite_hosts,
+                            _ => settings().upload.hosts
+                        };
+                        for (host, details) in hosts.hosts.iter_mut() {
+                            if !default_hosts.is_empty() {
+                                details.selected = default_hosts.contains(host);
+                            } else if details.selection == "true" {
                                 details.selected = true;
                             } else if details.selection == "false" {
                                 details.selected = false;
@@ -417,6 +1116,34 @@ impl UploadUI {
                     details.selected = false;
                 }
             }
+
+            let save_text = match self.profile {
+                Some(_) => "Save selection as profile favourites",
+                None => "Save selection as default"
+            };
+            if ui.button(save_text).clicked() {
+                let selected_hosts: Vec<String> = self.hosts.hosts.iter()
+                    .filter(|(_, details)| details.selected)
+                    .map(|(host, _)| host.to_string())
+                    .collect();
+                let saved = match self.profile.as_deref().and_then(accounts::profile) {
+                    Some(mut profile) => {
+                        profile.favourite_hosts = selected_hosts;
+                        accounts::save_profile(profile)
+                    }
+                    None => update_settings(|settings| settings.upload.hosts = selected_hosts)
+                };
+                if let Err(error) = saved {
+                    self.toasts.add(Toast {
+                        text: format!("Failed to save default hosts: {}", error).into(),
+                        kind: ToastKind::Error,
+                        options: ToastOptions::default()
+                            .duration_in_seconds(5.0)
+                            .show_progress(true)
+                            .show_icon(true)
+                    });
+                }
+            }
         });
 
         ui.columns(5, |columns| {
@@ -428,130 +1155,366 @@ impl UploadUI {
 
         ui.horizontal(|ui| {
             if ui.add_enabled(!self.uploading, Button::new("Upload to MultiUp")).clicked() {
-                self.uploading = true;
-                let (upload_sender, upload_receiver) = crossbeam_channel::unbounded();
-                self.channels.upload = Some(upload_receiver);
-                self.remote_upload_settings.hosts = self.hosts.hosts.iter()
-                    .filter(|(_, details)| details.selected)
-                    .map(|(host, _)| host.to_string())
-                    .collect();
-                self.disk_upload_settings.hosts = self.hosts.hosts.iter()
-                    .filter(|(_, details)| details.selected)
-                    .map(|(host, _)| host.to_string())
-                    .collect();
-
-                let remote_upload_settings = self.remote_upload_settings.clone();
-                let disk_upload_settings = self.disk_upload_settings.clone();
-                let login = self.login_details.clone();
-                let upload_type = self.upload_type.clone();
-                let rt = Runtime::new().unwrap();
-
-                thread::spawn(move || {
-                    rt.block_on(async {
-                        match upload_type {
-                            UploadType::Remote => {
-                                let (urls, file_names) = process_urls_and_names(&remote_upload_settings.upload_links, &remote_upload_settings.file_names);
-                                let project_hash = get_project_hash(&remote_upload_settings.project_settings, login.user_id.clone(), upload_sender.clone()).await;
-                                let response = if remote_upload_settings.force_data_streaming {
-                                    vec![MultiUpUploadResponses::MultiUpFileUpload(stream_file(&urls, &file_names, login.user_id, remote_upload_settings.hosts, project_hash.clone()).await)]
-                                } else {
-                                    remote_upload_files(&urls, &file_names, login, remote_upload_settings.hosts, project_hash.clone()).await
-                                };
-                                upload_sender.send(response).unwrap();
-                            },
-                            UploadType::Disk => {
-                                let project_hash = get_project_hash(&disk_upload_settings.project_settings, login.user_id.clone(), upload_sender.clone()).await;
-                                let responses = disk_upload_files(&disk_upload_settings.file_paths, &disk_upload_settings.file_names, login, disk_upload_settings.hosts, project_hash).await;
-                                upload_sender.send(responses).unwrap();
-                            }
-                        }
-                    });
-                });
+                let request = self.current_request();
+                self.submit_upload(request, false);
+            }
+            if ui.button("Add to queue").clicked() {
+                let request = self.current_request();
+                self.submit_upload(request, true);
             }
 
             if self.uploading {
                 ui.spinner();
                 ui.label("Uploading...");
+                if ui.button("Cancel").clicked() {
+                    if let Some(cancellation_token) = self.channels.cancel.take() {
+                        cancellation_token.cancel();
+                    }
+                }
             }
         });
     }
 
-    fn display_uploaded_links_area(&mut self, ui: &mut Ui) {
-        ui.heading("MultiUp Links");
-        ScrollArea::vertical().id_source("Uploaded MultiUp Links").show(ui, |ui| {
-            // ui.add(TextEdit::multiline(&mut self.multiup_links))
-            if let Some(response) = &self.channels.upload {
-                if let Ok(result) = response.try_recv() {
-                    self.uploading = false;
-                    let mut multiup_links = vec![];
-                    let mut project_hash = String::new();
-                    for response in result {
-                        match response {
-                            MultiUpUploadResponses::MultiUpFileUpload(result) => {
-                                let response = result.unwrap_or_else(|error| MultiUpFileUploadResponse {
-                                    files: vec![UploadedFileDetails {
-                                        name: None,
-                                        hash: None,
-                                        size: None,
-                                        file_type: None,
-                                        url: Some(format!("{:?}", error)),
-                                        sid: None,
-                                        user: None,
-                                        delete_url: None,
-                                        delete_type: None
-                                    }],
-                                    project_hash: None
-                                });
+    /// Shows the progress of each file streamed through this app.
+    ///
+    /// Files sent in the same request are cancelled together, as MultiUp only accepts the complete request.
+    /// Checksum manifests of the files sent completely can be saved to a folder.
+    fn display_progress_area(&mut self, ui: &mut Ui) {
+        let Some(progress) = self.progress.clone().filter(|progress| !progress.files().is_empty()) else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.heading("Upload Progress");
+            let hashed = !self.uploading && !progress.hashed_files().is_empty();
+            if ui.add_enabled(hashed, Button::new("Save checksum manifests")).clicked() {
+                let mut dialog = FileDialog::select_folder(None);
+                dialog.open();
+                self.manifest_dialog = Some(dialog);
+            }
+        });
+        ScrollArea::vertical().id_source("Upload Progress").max_height(200.0).show(ui, |ui| {
+            progress.display(ui);
+        });
 
-                                for file in response.files {
-                                    if let Some(url) = file.url {
-                                        multiup_links.push(url);
-                                    }
-                                }
+        if let Some(dialog) = &mut self.manifest_dialog {
+            if dialog.show(ui.ctx()).selected() {
+                if let Some(directory) = dialog.path() {
+                    let (text, kind) = match checksums::write_manifests(directory, &self.manifest_name, &progress.hashed_files()) {
+                        Ok(_) => (format!("Saved the checksum manifests to {}", directory.display()), ToastKind::Success),
+                        Err(error) => (format!("Could not save the checksum manifests: {}", error), ToastKind::Error)
+                    };
+                    self.toasts.add(Toast {
+                        text: text.into(),
+                        kind,
+                        options: ToastOptions::default()
+                            .duration_in_seconds(5.0)
+                            .show_progress(true)
+                    });
+                }
+            }
+        }
+    }
 
-                                if let Some(hash) = response.project_hash {
-                                    project_hash = hash;
-                                }
-                            }
-                            MultiUpUploadResponses::MultiUpRemoteUpload(result) => {
-                                let response = result.unwrap_or_else(|error| MultiUpRemoteUploadResponse {
-                                    error: format!("{:?}", error),
-                                    link: None,
-                                    size: None,
-                                    file_name: None,
-                                    project_hash: None,
-                                });
-                                match response.error.as_str() {
-                                    "success" => {
-                                        if let Some(link) = response.link {
-                                            multiup_links.push(link);
-                                        }
-
-                                        if let Some(hash) = response.project_hash {
-                                            project_hash = hash;
-                                        }
-                                    },
-                                    _ => {
-                                        multiup_links.push(format!("{:?}", response.error));
-                                    }
-                                }
-                            }
-                        }
-                    }
+    /// Shows the validity of each host of the remote uploads being checked.
+    fn display_remote_status_area(&mut self, ui: &mut Ui) {
+        if self.remote_tracker.is_empty() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.heading("Remote Upload Status");
+            if ui.button("Clear finished").clicked() {
+                self.remote_tracker.clear_finished();
+            }
+        });
+        ScrollArea::vertical().id_source("Remote Upload Status").max_height(200.0).show(ui, |ui| {
+            self.remote_tracker.display(ui);
+        });
+    }
 
-                    if !project_hash.is_empty() {
-                        multiup_links.insert(0, format!("https://multiup.io/en/project/{}", project_hash));
-                    }
+    /// Returns alerts about remote uploads which stalled or ended without a valid host, which the selected tab shows.
+    pub fn take_remote_alerts(&self) -> Vec<String> {
+        self.remote_tracker.take_alerts()
+    }
+
+    /// Adds folders to the folder upload window, including the files in their subfolders.
+    pub fn add_folders(&mut self, folders: Vec<PathBuf>) {
+        self.folder_ui.add_folders(folders);
+    }
 
-                    self.multiup_links = multiup_links;
+    /// Queues a disk upload for each folder project, or adds their files to the disk upload list.
+    ///
+    /// Queued projects use the hosts and the project password and description of the tab.
+    fn handle_folder_action(&mut self, action: FolderAction) {
+        let result = match action {
+            FolderAction::QueueProjects(projects) => {
+                let mut template = self.current_request();
+                if !self.check_host_count(&mut template) {
+                    return;
+                }
+                let count = projects.len();
+                projects.into_iter()
+                    .try_for_each(|project| {
+                        let mut request = template.clone();
+                        request.upload_type = UploadType::Disk;
+                        request.disk_upload_settings.project_settings = ProjectSettings {
+                            is_project: true,
+                            name: project.name,
+                            ..template.disk_upload_settings.project_settings.clone()
+                        };
+                        (request.disk_upload_settings.file_paths, request.disk_upload_settings.file_names) = project.files.into_iter()
+                            .map(|file| (file.path, file.file_name))
+                            .unzip();
+                        queue::enqueue(request)
+                    })
+                    .map(|_| format!("Queued {} projects", count))
+            }
+            FolderAction::AddFiles(projects) => {
+                self.upload_type = UploadType::Disk;
+                for file in projects.into_iter().flat_map(|project| project.files) {
+                    if !self.disk_upload_settings.file_paths.contains(&file.path) {
+                        self.disk_upload_settings.file_paths.push(file.path);
+                        self.disk_upload_settings.file_names.push(file.file_name);
+                    }
                 }
+                Ok("Added the files to the upload list".to_string())
+            }
+        };
+        self.toasts.add(match result {
+            Ok(message) => Toast {
+                text: message.into(),
+                kind: ToastKind::Success,
+                options: ToastOptions::default()
+                    .duration_in_seconds(3.0)
+                    .show_progress(true)
+                    .show_icon(true)
+            },
+            Err(error) => Toast {
+                text: format!("Failed to save the queue: {}", error).into(),
+                kind: ToastKind::Error,
+                options: ToastOptions::default()
+                    .duration_in_seconds(5.0)
+                    .show_progress(true)
+                    .show_icon(true)
+            },
+        });
+    }
+
+    /// Uploads files and folders packed into archive volumes as a project named after the archive.
+    fn upload_split(&mut self, split_settings: SplitSettings, queue: bool) {
+        let mut request = self.current_request();
+        request.upload_type = UploadType::Disk;
+        request.disk_upload_settings.project_settings = ProjectSettings {
+            is_project: true,
+            name: split_settings.archive_name.clone(),
+            ..request.disk_upload_settings.project_settings.clone()
+        };
+        request.disk_upload_settings.file_paths.clear();
+        request.disk_upload_settings.file_names.clear();
+        request.disk_upload_settings.split = Some(split_settings);
+        self.split_ui.show_window = false;
+        self.submit_upload(request, queue);
+    }
+
+    /// Checks the upload against the maximum number of hosts and the size limit of each host before running it.
+    ///
+    /// Hosts which cannot accept every file are deselected, or the user is asked what to do, depending on the settings.
+    fn submit_upload(&mut self, mut request: UploadRequest, queue: bool) {
+        if !self.check_host_count(&mut request) {
+            return;
+        }
+        let host_count = request.hosts_mut().len();
+
+        let matrix = self.host_matrix(&request);
+        let rejecting_hosts = matrix.rejecting_hosts();
+        if rejecting_hosts.is_empty() || (matrix.per_file_hosts && matrix.rejected_files().is_empty()) {
+            self.run_upload(request, queue);
+        } else if !matrix.per_file_hosts && settings().upload.deselect_oversized_hosts && rejecting_hosts.len() < host_count {
+            self.deselect_hosts(&mut request, &rejecting_hosts);
+            self.toasts.add(Toast {
+                text: format!("Deselected {}, which cannot accept every file", rejecting_hosts.join(", ")).into(),
+                kind: ToastKind::Warning,
+                options: ToastOptions::default()
+                    .duration_in_seconds(5.0)
+                    .show_progress(true)
+                    .show_icon(true)
+            });
+            self.run_upload(request, queue);
+        } else {
+            self.host_check_ui.open(matrix, true);
+            self.pending_upload = Some((request, queue));
+        }
+    }
+
+    /// Returns `false` and shows an error if more hosts are selected than MultiUp accepts.
+    fn check_host_count(&mut self, request: &mut UploadRequest) -> bool {
+        let host_count = request.hosts_mut().len();
+        let max_hosts = self.hosts.max_hosts as usize;
+        if max_hosts > 0 && host_count > max_hosts {
+            self.toasts.add(Toast {
+                text: format!("MultiUp accepts at most {} hosts, but {} are selected", max_hosts, host_count).into(),
+                kind: ToastKind::Error,
+                options: ToastOptions::default()
+                    .duration_in_seconds(5.0)
+                    .show_progress(true)
+                    .show_icon(true)
+            });
+            return false;
+        }
+        true
+    }
+
+    fn handle_host_check_choice(&mut self, choice: HostCheckChoice) {
+        let Some((mut request, queue)) = self.pending_upload.take() else {
+            return;
+        };
+        if let HostCheckChoice::DeselectAndUpload = choice {
+            let rejecting_hosts = self.host_matrix(&request).rejecting_hosts();
+            self.deselect_hosts(&mut request, &rejecting_hosts);
+        }
+        self.run_upload(request, queue);
+    }
+
+    fn deselect_hosts(&mut self, request: &mut UploadRequest, hosts: &[String]) {
+        request.hosts_mut().retain(|host| !hosts.contains(host));
+        for host in hosts {
+            if let Some(details) = self.hosts.hosts.get_mut(host) {
+                details.selected = false;
+            }
+        }
+    }
+
+    fn run_upload(&mut self, request: UploadRequest, queue: bool) {
+        if !queue {
+            if !self.uploading {
+                self.start_upload(request);
+            }
+            return;
+        }
+        match queue::enqueue(request) {
+            Ok(()) => self.toasts.add(Toast {
+                text: "Added the upload to the queue".into(),
+                kind: ToastKind::Success,
+                options: ToastOptions::default()
+                    .duration_in_seconds(3.0)
+                    .show_progress(true)
+                    .show_icon(true)
+            }),
+            Err(error) => self.toasts.add(Toast {
+                text: format!("Failed to save the queue: {}", error).into(),
+                kind: ToastKind::Error,
+                options: ToastOptions::default()
+                    .duration_in_seconds(5.0)
+                    .show_progress(true)
+                    .show_icon(true)
+            }),
+        };
+    }
+
+    /// Returns which of the request's hosts accept each of its files.
+    ///
+    /// The size of remotely uploaded files is not known, so every host is assumed to accept them.
+    fn host_matrix(&self, request: &UploadRequest) -> HostMatrix {
+        let (files, hosts) = match request.upload_type {
+            UploadType::Disk => {
+                let settings = &request.disk_upload_settings;
+                let files = settings.file_paths.iter().enumerate()
+                    .map(|(index, path)| {
+                        let name = match settings.file_names.get(index) {
+                            Some(name) if !name.is_empty() => name.clone(),
+                            _ => path.file_name().unwrap_or(OsStr::new("")).to_string_lossy().to_string()
+                        };
+                        (name, std::fs::metadata(path).ok().map(|metadata| metadata.len()))
+                    })
+                    .collect();
+                (files, &settings.hosts)
+            }
+            UploadType::Remote => {
+                let settings = &request.remote_upload_settings;
+                let (urls, names) = process_urls_and_names(&settings.upload_links, &settings.file_names);
+                let files = urls.into_iter().enumerate()
+                    .filter(|(_, url)| !url.is_empty())
+                    .map(|(index, url)| (names.get(index).filter(|name| !name.is_empty()).cloned().unwrap_or(url), None))
+                    .collect();
+                (files, &settings.hosts)
+            }
+        };
+        let mut hosts: Vec<(String, u64)> = hosts.iter()
+            .map(|host| (host.clone(), self.hosts.hosts.get(host).map(|details| details.size).unwrap_or_default()))
+            .collect();
+        hosts.sort();
+        HostMatrix {
+            files,
+            hosts,
+            per_file_hosts: request.upload_type == UploadType::Disk && request.disk_upload_settings.separate_requests,
+        }
+    }
+
+    /// Returns the upload described by the tab, with the selected hosts.
+    fn current_request(&mut self) -> UploadRequest {
+        self.remote_upload_settings.hosts = self.hosts.hosts.iter()
+            .filter(|(_, details)| details.selected)
+            .map(|(host, _)| host.to_string())
+            .collect();
+        self.disk_upload_settings.hosts = self.hosts.hosts.iter()
+            .filter(|(_, details)| details.selected)
+            .map(|(host, _)| host.to_string())
+            .collect();
+        self.disk_upload_settings.host_limits = self.hosts.hosts.iter()
+            .filter(|(_, details)| details.selected)
+            .map(|(host, details)| (host.to_string(), details.size))
+            .collect();
+
+        UploadRequest {
+            upload_type: self.upload_type.clone(),
+            remote_upload_settings: self.remote_upload_settings.clone(),
+            disk_upload_settings: self.disk_upload_settings.clone(),
+            login: self.login_details.clone(),
+            profile: self.profile.clone(),
+        }
+    }
+
+    fn display_uploaded_links_area(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            match &self.upload_profile.1 {
+                Some(profile) if !self.multiup_links.is_empty() => ui.heading(format!("MultiUp Links (uploaded as {})", profile)),
+                _ => ui.heading("MultiUp Links")
+            };
+            if ui.button("Upload history").clicked() {
+                self.ledger_ui.show_window = true;
+            }
+            let queued = queue::queue_items().iter().filter(|item| !item.status.is_finished()).count();
+            if ui.button(format!("Upload queue ({})", queued)).clicked() {
+                self.queue_ui.show_window = true;
+            }
+            if ui.add_enabled(!self.uploaded_links.is_empty(), Button::new("Verify links"))
+                .on_hover_text("Extracts the links and rechecks their validity, to see which hosts came up. \
+                Replaces a verification which is waiting for its delay.")
+                .clicked() {
+                self.verify_links(Duration::ZERO);
             }
+            if self.channels.verification.is_some() {
+                ui.spinner();
+            }
+        });
+        if !self.verifications.is_empty() {
+            ScrollArea::vertical().id_source("Upload Verification").max_height(150.0).show(ui, |ui| {
+                display_verifications(ui, &self.multiup_links, &self.verifications);
+            });
+        }
+        ScrollArea::vertical().id_source("Uploaded MultiUp Links").show(ui, |ui| {
+            // ui.add(TextEdit::multiline(&mut self.multiup_links))
             let mut links = self.multiup_links.join("\n");
             ui.add(TextEdit::multiline(&mut links).desired_width(ui.available_width()));
         });
     }
 }
 
+fn failed_uploads(responses: &[MultiUpUploadResponses]) -> usize {
+    responses.iter()
+        .filter(|response| matches!(response, MultiUpUploadResponses::MultiUpFileUpload(Err(_)) | MultiUpUploadResponses::MultiUpRemoteUpload(Err(_))))
+        .count()
+}
+
 fn process_urls_and_names(urls: &str, names: &str) -> (Vec<String>, Vec<String>) {
     let urls = urls.split('\n').map(|x| x.trim().to_string()).collect::<Vec<String>>();
     let names = names.split('\n').map(|x| x.trim().to_string()).collect::<Vec<String>>();
@@ -588,9 +1551,14 @@ async fn get_project_hash(project_settings: &ProjectSettings, user: Option<Strin
     }
 }
 
-async fn remote_upload_files(urls: &[String], file_names: &[String], login: Login, hosts: HashSet<String>, project_hash: Option<String>) -> Vec<MultiUpUploadResponses> {
+pub async fn remote_upload_files(urls: &[String], file_names: &[String], login: Login, hosts: HashSet<String>, project_hash: Option<String>, progress: &UploadProgress) -> Vec<MultiUpUploadResponses> {
     let mut responses = vec![];
-    let client = Client::new();
+    let client = match network::client(Service::Upload) {
+        Ok(client) => client,
+        Err(error) => return urls.iter()
+            .map(|_| MultiUpUploadResponses::MultiUpRemoteUpload(Err(LinkError::APIError(error.clone()))))
+            .collect()
+    };
 
     let username = login.username.clone();
     let password = login.password.clone();
@@ -641,7 +1609,8 @@ async fn remote_upload_files(urls: &[String], file_names: &[String], login: Logi
                                                  &[file_names.get(index).unwrap_or(&"".to_string()).to_string()],
                                                  login.user_id.clone(),
                                                  hosts.clone(),
-                                                 project_hash.clone()).await;
+                                                 project_hash.clone(),
+                                                 progress).await;
                         responses.push(MultiUpUploadResponses::MultiUpFileUpload(result));
                     }
                 }
@@ -654,11 +1623,11 @@ async fn remote_upload_files(urls: &[String], file_names: &[String], login: Logi
     responses
 }
 
-async fn stream_file(download_urls: &[String], file_names: &[String], user_id: Option<String>, hosts: HashSet<String>, project_hash: Option<String>) -> Result<MultiUpFileUploadResponse, LinkError> {
+pub async fn stream_file(download_urls: &[String], file_names: &[String], user_id: Option<String>, hosts: HashSet<String>, project_hash: Option<String>, progress: &UploadProgress) -> Result<MultiUpFileUploadResponse, LinkError> {
     let api_url = get_fastest_server().await?;
 
     // Create a reqwest client
-    let client = Client::new();
+    let client = network::client(Service::Upload).map_err(LinkError::APIError)?;
 
     // Download the file
     let mut responses = vec![];
@@ -672,6 +1641,7 @@ async fn stream_file(download_urls: &[String], file_names: &[String], user_id: O
     }
 
     let mut files = vec![];
+    let mut tracked_files = vec![];
     for (index, download_response) in responses.into_iter().enumerate() {
         let content_disposition = download_response.headers().get(reqwest::header::CONTENT_DISPOSITION);
         let file_name = match file_names.get(index) {
@@ -707,7 +1677,9 @@ async fn stream_file(download_urls: &[String], file_names: &[String], user_id: O
             .and_then(|cl| cl.parse::<u64>().ok());
 
         // Stream the file directly without saving to disk, converting it to a compatible stream
-        let file_stream = download_response.bytes_stream();
+        let file_progress = progress.add_file(file_name.clone(), content_length);
+        let file_stream = track_stream(download_response.bytes_stream(), Arc::clone(&file_progress));
+        tracked_files.push(file_progress);
 
         // Convert the stream into a Body for the multipart form
         let file_body = reqwest::Body::wrap_stream(file_stream);
@@ -741,7 +1713,7 @@ async fn stream_file(download_urls: &[String], file_names: &[String], user_id: O
     // Upload the file
     let response = match client.post(api_url).multipart(form).send().await {
         Ok(response) => response,
-        Err(error) => return Err(LinkError::APIError(error.to_string()))
+        Err(error) => return finish_files(&tracked_files, Err(LinkError::APIError(error.to_string())))
     };
 
     // let status = response.status();
@@ -757,7 +1729,7 @@ async fn stream_file(download_urls: &[String], file_names: &[String], user_id: O
             response.project_hash = project_hash;
             response
         },
-        Err(error) => return Err(LinkError::APIError(error.to_string()))
+        Err(error) => return finish_files(&tracked_files, Err(LinkError::APIError(error.to_string())))
     };
     // match upload_response.files.is_empty() {
     //     true => {
@@ -768,43 +1740,63 @@ async fn stream_file(download_urls: &[String], file_names: &[String], user_id: O
     //     }
     // }
     // println!("Upload Response: {:?}", upload_response);
-    Ok(upload_response)
+    finish_files(&tracked_files, Ok(upload_response))
 }
 
-async fn disk_upload_files(paths: &[PathBuf], file_names: &[String], login: Login, hosts: HashSet<String>, project_hash: Option<String>) -> Vec<MultiUpUploadResponses> {
+/// Records the result of a request on every file it sent and checks their hashes.
+///
+/// Cancelling one file aborts the whole request, so the error is reported as a cancellation.
+fn finish_files(files: &[Arc<FileProgress>], result: Result<MultiUpFileUploadResponse, LinkError>) -> Result<MultiUpFileUploadResponse, LinkError> {
+    let result = match result {
+        Err(_) if files.iter().any(|file| file.cancellation_token.is_cancelled()) => Err(LinkError::Cancelled),
+        result => result
+    };
+    for (index, file) in files.iter().enumerate() {
+        file.finish(match &result {
+            Ok(_) => FileStatus::Done,
+            Err(error) => FileStatus::Failed(error.message()),
+        });
+        if let Ok(response) = &result {
+            let uploaded = response.files.iter()
+                .find(|uploaded| uploaded.name.as_ref() == Some(&file.name))
+                .or(response.files.get(index));
+            file.verify(uploaded);
+        }
+    }
+    result
+}
+
+async fn disk_upload_files(paths: &[PathBuf], file_names: &[String], login: Login, hosts: HashSet<String>, project_hash: Option<String>, progress: &UploadProgress) -> Vec<MultiUpUploadResponses> {
     let mut responses = vec![];
 
     let mut files = vec![];
+    let mut tracked_files = vec![];
     for (index, path) in paths.iter().enumerate() {
-        match tokio::fs::File::open(path).await {
-            Ok(file) => {
-                let stream = FramedRead::new(file.try_clone().await.unwrap(), BytesCodec::new());
-                let file_body = reqwest::Body::wrap_stream(stream);
-                let length = match file.metadata().await {
-                    Ok(data) => data.len(),
-                    Err(error) => {
-                        responses.push(MultiUpUploadResponses::MultiUpFileUpload(Err(LinkError::Other)));
-                        continue;
-                    }
-                };
-                let file_name = match file_names.get(index) {
-                    Some(name) => if !name.is_empty() {
-                        name.to_string()
-                    } else {
-                        path.file_name().unwrap().to_str().unwrap().to_string()
-                    },
-                    None => {
-                        path.file_name().unwrap().to_str().unwrap().to_string()
-                    }
-                };
-
+        let file_name = match file_names.get(index) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => path.file_name().unwrap_or(OsStr::new("")).to_string_lossy().to_string()
+        };
+        let length = match tokio::fs::metadata(path).await {
+            Ok(data) => data.len(),
+            Err(error) => {
+                let error = file_error(path, error);
+                progress.add_file(file_name, None).finish(FileStatus::Failed(error.message()));
+                responses.push(MultiUpUploadResponses::MultiUpFileUpload(Err(error)));
+                continue;
+            }
+        };
+        let file_progress = progress.add_file(file_name.clone(), Some(length));
+        match open_disk_file(path, &file_progress).await {
+            Ok(file_body) => {
+                tracked_files.push(file_progress);
                 let part = multipart::Part::stream_with_length(file_body, length)
                     .file_name(file_name);
 
                 files.push(part);
             }
             Err(error) => {
-                responses.push(MultiUpUploadResponses::MultiUpFileUpload(Err(LinkError::Invalid)));
+                file_progress.finish(FileStatus::Failed(error.message()));
+                responses.push(MultiUpUploadResponses::MultiUpFileUpload(Err(error)));
             }
         };
     }
@@ -813,7 +1805,7 @@ async fn disk_upload_files(paths: &[PathBuf], file_names: &[String], login: Logi
     let api_url = match get_fastest_server().await {
         Ok(url) => url,
         Err(error) => {
-            return vec![MultiUpUploadResponses::MultiUpFileUpload(Err(error))];
+            return vec![MultiUpUploadResponses::MultiUpFileUpload(finish_files(&tracked_files, Err(error)))];
         }
     };
     let mut form = multipart::Form::new();
@@ -834,10 +1826,13 @@ async fn disk_upload_files(paths: &[PathBuf], file_names: &[String], login: Logi
         form = form.part("files[]", part);
     }
 
-    let client = Client::new();
+    let client = match network::client(Service::Upload) {
+        Ok(client) => client,
+        Err(error) => return vec![MultiUpUploadResponses::MultiUpFileUpload(finish_files(&tracked_files, Err(LinkError::APIError(error))))]
+    };
     let response = match client.post(api_url).multipart(form).send().await {
         Ok(response) => response,
-        Err(error) => return vec![MultiUpUploadResponses::MultiUpFileUpload(Err(LinkError::APIError(error.to_string())))]
+        Err(error) => return vec![MultiUpUploadResponses::MultiUpFileUpload(finish_files(&tracked_files, Err(LinkError::APIError(error.to_string()))))]
     };
 
 
@@ -846,9 +1841,162 @@ async fn disk_upload_files(paths: &[PathBuf], file_names: &[String], login: Logi
             response.project_hash = project_hash;
             response
         },
-        Err(error) => return vec![MultiUpUploadResponses::MultiUpFileUpload(Err(LinkError::APIError(error.to_string())))]
+        Err(error) => return vec![MultiUpUploadResponses::MultiUpFileUpload(finish_files(&tracked_files, Err(LinkError::APIError(error.to_string()))))]
     };
 
-    responses.push(MultiUpUploadResponses::MultiUpFileUpload(Ok(upload_response)));
+    responses.push(MultiUpUploadResponses::MultiUpFileUpload(finish_files(&tracked_files, Ok(upload_response))));
     responses
 }
+
+/// Returns the disk files as they are described in a project description.
+///
+/// Hashing reads every file before it is uploaded, so it is only done when the description needs the hashes.
+async fn disk_template_files(disk_upload_settings: &DiskUploadSettings, hash: bool, progress: &UploadProgress) -> Vec<TemplateFile> {
+    let mut files = vec![];
+    for (index, path) in disk_upload_settings.file_paths.iter().enumerate() {
+        let name = match disk_upload_settings.file_names.get(index) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => path.file_name().unwrap_or(OsStr::new("")).to_string_lossy().to_string()
+        };
+        let size = tokio::fs::metadata(path).await.map(|metadata| metadata.len()).ok();
+        let hashes = match hash {
+            true => {
+                let file_progress = progress.add_file(format!("Hashing {}", name), size);
+                let path = path.clone();
+                let hashing_progress = Arc::clone(&file_progress);
+                let result = tokio::task::spawn_blocking(move || templates::hash_file(&path, &hashing_progress))
+                    .await
+                    .unwrap_or_else(|error| Err(error.to_string()));
+                file_progress.finish(match &result {
+                    Ok(_) => FileStatus::Done,
+                    Err(error) => FileStatus::Failed(error.clone()),
+                });
+                result.ok()
+            }
+            false => None
+        };
+        files.push(TemplateFile { name, size, hashes });
+    }
+    files
+}
+
+/// Returns the name of the checksum manifests, which is the project name if there is one.
+fn manifest_name(project_settings: &ProjectSettings) -> String {
+    match project_settings.is_project && !project_settings.name.trim().is_empty() {
+        true => project_settings.name.trim().to_string(),
+        false => "checksums".to_string()
+    }
+}
+
+/// Uploads SFV, MD5 and SHA-256 manifests of every file which was sent completely, into the same project.
+///
+/// The manifests are written to a temporary directory, which is deleted afterwards.
+async fn upload_manifests(base_name: &str, progress: &UploadProgress, login: Login, hosts: HashSet<String>, project_hash: Option<String>) -> Vec<MultiUpUploadResponses> {
+    let files = progress.hashed_files();
+    if files.is_empty() {
+        return vec![];
+    }
+    let directory = split::temp_directory();
+    let responses = match checksums::write_manifests(&directory, base_name, &files) {
+        // The manifests are tracked apart from the files, so they are not listed in each other
+        Ok(paths) => disk_upload_files(&paths, &[], login, hosts, project_hash, &UploadProgress::default()).await,
+        Err(error) => vec![MultiUpUploadResponses::MultiUpFileUpload(Err(LinkError::File(format!("Could not write the checksum manifests: {}", error))))]
+    };
+    let _ = tokio::fs::remove_dir_all(directory).await;
+    responses
+}
+
+/// Opens a file on disk as a request body which counts the bytes sent.
+async fn open_disk_file(path: &Path, file_progress: &Arc<FileProgress>) -> Result<reqwest::Body, LinkError> {
+    let file = tokio::fs::File::open(path).await.map_err(|error| file_error(path, error))?;
+    let stream = track_stream(FramedRead::new(file, BytesCodec::new()), Arc::clone(file_progress));
+    Ok(reqwest::Body::wrap_stream(stream))
+}
+
+fn file_error(path: &Path, error: std::io::Error) -> LinkError {
+    LinkError::File(format!("Could not read {}: {}", path.display(), error))
+}
+
+/// Uploads each file in its own request, so a failed file does not affect the others.
+///
+/// Files are uploaded concurrently up to the limit in the settings and retried after failing.
+/// Each file is only sent to the selected hosts whose size limit accepts it.
+/// The responses are in the same order as the paths.
+async fn disk_upload_files_separately(disk_upload_settings: &DiskUploadSettings, login: Login, project_hash: Option<String>, progress: &UploadProgress) -> Vec<MultiUpUploadResponses> {
+    let upload_settings = settings().upload;
+    let semaphore = Semaphore::new(upload_settings.file_concurrency.max(1));
+    let tasks = disk_upload_settings.file_paths.iter().enumerate().map(|(index, path)| {
+        let file_name = match disk_upload_settings.file_names.get(index) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => path.file_name().unwrap_or(OsStr::new("")).to_string_lossy().to_string()
+        };
+        let semaphore = &semaphore;
+        let login = &login;
+        let project_hash = &project_hash;
+        async move {
+            let length = match tokio::fs::metadata(path).await {
+                Ok(data) => data.len(),
+                Err(error) => {
+                    let error = file_error(path, error);
+                    progress.add_file(file_name, None).finish(FileStatus::Failed(error.message()));
+                    return MultiUpUploadResponses::MultiUpFileUpload(Err(error));
+                }
+            };
+            let hosts = hosts_for_file(&disk_upload_settings.hosts, &disk_upload_settings.host_limits, length);
+            if hosts.is_empty() && !disk_upload_settings.hosts.is_empty() {
+                let error = LinkError::APIError(format!("No selected host accepts {}", file_name));
+                progress.add_file(file_name, Some(length)).finish(FileStatus::Failed(error.message()));
+                return MultiUpUploadResponses::MultiUpFileUpload(Err(error));
+            }
+            let file_progress = progress.add_file(file_name.clone(), Some(length));
+            let _permit = semaphore.acquire().await.unwrap();
+            let mut attempt = 0;
+            let result = loop {
+                let result = upload_disk_file(path, &file_name, length, login, &hosts, project_hash, &file_progress).await;
+                match result {
+                    Err(LinkError::File(_)) | Ok(_) => break result,
+                    Err(_) if attempt >= upload_settings.retries || file_progress.cancellation_token.is_cancelled() => break result,
+                    Err(_) => {
+                        attempt += 1;
+                        file_progress.restart();
+                        // Wait a little longer after each failure before trying again
+                        tokio::select! {
+                            _ = tokio::time::sleep(Duration::from_secs(2 * attempt as u64)) => {},
+                            _ = file_progress.cancellation_token.cancelled() => break Err(LinkError::Cancelled)
+                        }
+                    }
+                }
+            };
+            MultiUpUploadResponses::MultiUpFileUpload(finish_files(&[file_progress], result))
+        }
+    });
+    futures::future::join_all(tasks).await
+}
+
+/// Uploads a single file on disk in one request.
+async fn upload_disk_file(path: &Path, file_name: &str, length: u64, login: &Login, hosts: &HashSet<String>, project_hash: &Option<String>, file_progress: &Arc<FileProgress>) -> Result<MultiUpFileUploadResponse, LinkError> {
+    let file_body = open_disk_file(path, file_progress).await?;
+    let api_url = get_fastest_server().await?;
+
+    let mut form = multipart::Form::new();
+    if let Some(id) = login.user_id.clone() {
+        form = form.text("user", id);
+    }
+    if let Some(hash) = project_hash.clone() {
+        form = form.text("project-hash", hash);
+    }
+    for host in hosts {
+        form = form.text(host.clone(), "true");
+    }
+    let part = multipart::Part::stream_with_length(file_body, length)
+        .file_name(file_name.to_string());
+    form = form.part("files[]", part);
+
+    let client = network::client(Service::Upload).map_err(LinkError::APIError)?;
+    let response = client.post(api_url).multipart(form).send().await
+        .map_err(|error| LinkError::APIError(error.to_string()))?;
+    let mut upload_response = response.json::<MultiUpFileUploadResponse>().await
+        .map_err(|error| LinkError::APIError(error.to_string()))?;
+    upload_response.project_hash = project_hash.clone();
+    Ok(upload_response)
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{AeadCore, KeyInit, XChaCha20Poly1305, XNonce};
+use chacha20poly1305::aead::{Aead, OsRng};
+use chacha20poly1305::aead::rand_core::RngCore;
+use crossbeam_channel::Receiver;
+use eframe::egui::{Button, Context, DragValue, Grid, TextEdit, Window};
+use serde::{Deserialize, Serialize};
+use crate::modules::jobs::runtime;
+use crate::modules::settings::{config_path, remove_legacy_api_keys, settings, update_settings};
+
+const VAULT_FILE: &str = "vault.json";
+const VAULT_VERSION: u32 = 1;
+const SALT_LENGTH: usize = 16;
+const KEY_LENGTH: usize = 32;
+
+static VAULT: OnceLock<Mutex<Option<UnlockedVault>>> = OnceLock::new();
+
+/// Represents the secrets stored in the vault.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Credentials {
+    pub multiup_username: String,
+    pub multiup_password: String,
+    pub all_debrid_api_key: String,
+    pub real_debrid_api_key: String,
+    /// Passwords of the MultiUp account profiles, by username.
+    pub account_passwords: BTreeMap<String, String>,
+    /// Passwords of the proxies, by username and proxy URL.
+    pub proxy_passwords: BTreeMap<String, String>,
+}
+
+/// Represents the Argon2id parameters used to derive the key, so they can be raised later
+/// without breaking existing vaults.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// Represents the vault file. The credentials are encrypted with XChaCha20-Poly1305
+/// using a key derived from the master passphrase.
+#[derive(Deserialize, Serialize)]
+struct VaultFile {
+    version: u32,
+    kdf: KdfParams,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+struct UnlockedVault {
+    key: [u8; KEY_LENGTH],
+    kdf: KdfParams,
+    salt: Vec<u8>,
+    credentials: Credentials,
+    last_used: Instant,
+}
+
+fn vault_path() -> PathBuf {
+    config_path(VAULT_FILE)
+}
+
+fn vault_lock() -> &'static Mutex<Option<UnlockedVault>> {
+    VAULT.get_or_init(|| Mutex::new(None))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<[u8; KEY_LENGTH], String> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(KEY_LENGTH))
+        .map_err(|error| error.to_string())?;
+    let mut key = [0; KEY_LENGTH];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|error| error.to_string())?;
+    Ok(key)
+}
+
+/// Encrypts the credentials with a new nonce and writes the vault file.
+///
+/// The file is written next to the vault and then renamed over it, so the old vault stays intact if writing fails.
+fn write_vault(vault: &UnlockedVault) -> Result<(), String> {
+    let cipher = XChaCha20Poly1305::new((&vault.key).into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(&vault.credentials).map_err(|error| error.to_string())?;
+    let ciphertext = cipher.encrypt(&nonce, plaintext.as_slice()).map_err(|_| "Failed to encrypt the vault".to_string())?;
+    let vault_file = VaultFile {
+        version: VAULT_VERSION,
+        kdf: vault.kdf,
+        salt: vault.salt.clone(),
+        nonce: nonce.to_vec(),
+        ciphertext,
+    };
+
+    let path = vault_path();
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+    let json_string = serde_json::to_string(&vault_file).map_err(|error| error.to_string())?;
+    let temporary_path = path.with_extension("json.tmp");
+    fs::write(&temporary_path, json_string).map_err(|error| error.to_string())?;
+    fs::rename(&temporary_path, path).map_err(|error| {
+        let _ = fs::remove_file(&temporary_path);
+        error.to_string()
+    })
+}
+
+pub fn vault_exists() -> bool {
+    vault_path().is_file()
+}
+
+pub fn is_unlocked() -> bool {
+    vault_lock().lock().unwrap().is_some()
+}
+
+/// Creates a new vault containing the credentials and leaves it unlocked.
+///
+/// Deriving the key is deliberately slow, so this should not be called on the UI thread.
+pub fn create_vault(passphrase: &str, credentials: Credentials) -> Result<(), String> {
+    let mut salt = vec![0; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = KdfParams::default();
+    let vault = UnlockedVault {
+        key: derive_key(passphrase, &salt, kdf)?,
+        kdf,
+        salt,
+        credentials,
+        last_used: Instant::now(),
+    };
+    write_vault(&vault)?;
+    *vault_lock().lock().unwrap() = Some(vault);
+    Ok(())
+}
+
+/// Decrypts the vault with the passphrase and keeps the credentials in memory until it is locked.
+///
+/// Deriving the key is deliberately slow, so this should not be called on the UI thread.
+pub fn unlock(passphrase: &str) -> Result<(), String> {
+    let json_string = fs::read_to_string(vault_path()).map_err(|error| error.to_string())?;
+    let vault_file: VaultFile = serde_json::from_str(&json_string).map_err(|error| error.to_string())?;
+    if vault_file.version > VAULT_VERSION {
+        return Err("The vault was created by a newer version of the app".to_string());
+    }
+    if vault_file.nonce.len() != 24 {
+        return Err("The vault file is damaged".to_string());
+    }
+
+    let key = derive_key(passphrase, &vault_file.salt, vault_file.kdf)?;
+    let cipher = XChaCha20Poly1305::new((&key).into());
+    let plaintext = cipher.decrypt(XNonce::from_slice(&vault_file.nonce), vault_file.ciphertext.as_slice())
+        .map_err(|_| "Wrong passphrase".to_string())?;
+    let credentials = serde_json::from_slice(&plaintext).map_err(|error| error.to_string())?;
+
+    *vault_lock().lock().unwrap() = Some(UnlockedVault {
+        key,
+        kdf: vault_file.kdf,
+        salt: vault_file.salt,
+        credentials,
+        last_used: Instant::now(),
+    });
+    Ok(())
+}
+
+/// Forgets the key and the decrypted credentials.
+pub fn lock() {
+    *vault_lock().lock().unwrap() = None;
+}
+
+/// Returns the credentials if the vault is unlocked.
+pub fn credentials() -> Option<Credentials> {
+    let mut vault = vault_lock().lock().unwrap();
+    vault.as_mut().map(|vault| {
+        vault.last_used = Instant::now();
+        vault.credentials.clone()
+    })
+}
+
+/// Changes the stored credentials and saves the vault. The vault must be unlocked.
+pub fn update_credentials(update: impl FnOnce(&mut Credentials)) -> Result<(), String> {
+    let mut vault = vault_lock().lock().unwrap();
+    let vault = vault.as_mut().ok_or_else(|| match vault_exists() {
+        true => "Unlock the vault first".to_string(),
+        false => "Create a vault in Settings > Credential vault first".to_string(),
+    })?;
+    update(&mut vault.credentials);
+    vault.last_used = Instant::now();
+    write_vault(vault)
+}
+
+/// Encrypts the vault with a new passphrase. The vault must be unlocked.
+pub fn change_passphrase(passphrase: &str) -> Result<(), String> {
+    let credentials = credentials().ok_or_else(|| "Unlock the vault first".to_string())?;
+    create_vault(passphrase, credentials)
+}
+
+/// Locks the vault if it has not been used for the auto-lock timeout.
+///
+/// Returns the time left before it locks, or `None` if it is locked or never locks.
+pub fn auto_lock() -> Option<Duration> {
+    let minutes = settings().vault.auto_lock_minutes;
+    let mut vault = vault_lock().lock().unwrap();
+    let last_used = vault.as_ref()?.last_used;
+    if minutes == 0 {
+        return None;
+    }
+    let timeout = Duration::from_secs(minutes * 60);
+    match timeout.checked_sub(last_used.elapsed()) {
+        Some(remaining) if !remaining.is_zero() => Some(remaining),
+        _ => {
+            *vault = None;
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct VaultUI {
+    pub show_window: bool,
+    passphrase: String,
+    confirm_passphrase: String,
+    credentials: Option<Credentials>,
+    auto_lock_minutes: Option<u64>,
+    receiver: Option<Receiver<Result<String, String>>>,
+    message: Option<Result<String, String>>,
+}
+
+impl VaultUI {
+    pub fn show_vault_window(&mut self, ctx: &Context) {
+        if let Some(receiver) = &self.receiver {
+            if let Ok(result) = receiver.try_recv() {
+                self.message = Some(result);
+                self.receiver = None;
+            }
+        }
+        if !is_unlocked() {
+            self.credentials = None;
+        }
+
+        let mut show_window = self.show_window;
+        Window::new("Credential Vault")
+            .default_width(400.0)
+            .open(&mut show_window)
+            .show(ctx, |ui| {
+                let working = self.receiver.is_some();
+                if is_unlocked() {
+                    let credentials = self.credentials.get_or_insert_with(|| self::credentials().unwrap_or_default());
+                    Grid::new("Vault Credentials").show(ui, |ui| {
+                        ui.label("MultiUp username:");
+                        ui.text_edit_singleline(&mut credentials.multiup_username);
+                        ui.end_row();
+                        ui.label("MultiUp password:");
+                        ui.add(TextEdit::singleline(&mut credentials.multiup_password).password(true));
+                        ui.end_row();
+                        ui.label("AllDebrid API key:");
+                        ui.add(TextEdit::singleline(&mut credentials.all_debrid_api_key).password(true));
+                        ui.end_row();
+                        ui.label("RealDebrid API key:");
+                        ui.add(TextEdit::singleline(&mut credentials.real_debrid_api_key).password(true));
+                        ui.end_row();
+                    });
+                    if !credentials.account_passwords.is_empty() {
+                        ui.label(format!("Passwords saved for {} MultiUp account profiles", credentials.account_passwords.len()));
+                    }
+                    if !credentials.proxy_passwords.is_empty() {
+                        ui.label(format!("Passwords saved for {} proxies", credentials.proxy_passwords.len()));
+                    }
+
+                    let mut lock_now = false;
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            // Only the fields of this form are written, so passwords saved elsewhere since the window opened are kept
+                            let credentials = credentials.clone();
+                            self.message = Some(update_credentials(|stored| {
+                                stored.multiup_username = credentials.multiup_username;
+                                stored.multiup_password = credentials.multiup_password;
+                                stored.all_debrid_api_key = credentials.all_debrid_api_key;
+                                stored.real_debrid_api_key = credentials.real_debrid_api_key;
+                            }).map(|_| "Saved".to_string()));
+                        }
+                        lock_now = ui.button("Lock now").clicked();
+                    });
+                    if lock_now {
+                        lock();
+                        self.credentials = None;
+                        self.message = None;
+                    }
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("New passphrase:");
+                        ui.add(TextEdit::singleline(&mut self.passphrase).password(true));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Confirm:");
+                        ui.add(TextEdit::singleline(&mut self.confirm_passphrase).password(true));
+                    });
+                    let can_change = !working && !self.passphrase.is_empty() && self.passphrase == self.confirm_passphrase;
+                    if ui.add_enabled(can_change, Button::new("Change passphrase")).clicked() {
+                        let passphrase = std::mem::take(&mut self.passphrase);
+                        self.confirm_passphrase.clear();
+                        self.start("Passphrase changed", move || change_passphrase(&passphrase));
+                    }
+                } else if vault_exists() {
+                    ui.label("The vault is locked. Enter the master passphrase to use the saved credentials.");
+                    let response = ui.add(TextEdit::singleline(&mut self.passphrase).password(true));
+                    let submitted = response.lost_focus() && ui.input(|input| input.key_pressed(eframe::egui::Key::Enter));
+                    if (ui.add_enabled(!working, Button::new("Unlock")).clicked() || submitted) && !working {
+                        let passphrase = std::mem::take(&mut self.passphrase);
+                        self.start("Vault unlocked", move || unlock(&passphrase));
+                    }
+                } else {
+                    ui.label("Create a vault to store your MultiUp login, Debrid API keys and proxy passwords encrypted with a master passphrase. \
+                    API keys and proxy passwords saved by older versions are moved into the vault.");
+                    ui.horizontal(|ui| {
+                        ui.label("Passphrase:");
+                        ui.add(TextEdit::singleline(&mut self.passphrase).password(true));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Confirm:");
+                        ui.add(TextEdit::singleline(&mut self.confirm_passphrase).password(true));
+                    });
+                    if !self.confirm_passphrase.is_empty() && self.passphrase != self.confirm_passphrase {
+                        ui.colored_label(ui.visuals().error_fg_color, "The passphrases do not match");
+                    }
+                    let can_create = !working && !self.passphrase.is_empty() && self.passphrase == self.confirm_passphrase;
+                    if ui.add_enabled(can_create, Button::new("Create vault")).clicked() {
+                        let passphrase = std::mem::take(&mut self.passphrase);
+                        self.confirm_passphrase.clear();
+                        self.start("Vault created", move || create_vault_from_settings(&passphrase));
+                    }
+                }
+                if working {
+                    ui.spinner();
+                }
+
+                ui.separator();
+                let auto_lock_minutes = self.auto_lock_minutes.get_or_insert_with(|| settings().vault.auto_lock_minutes);
+                ui.horizontal(|ui| {
+                    ui.label("Lock after");
+                    ui.add(DragValue::new(auto_lock_minutes).clamp_range(0..=1440));
+                    ui.label("minutes without use (0 never locks)");
+                    if ui.button("Apply").clicked() {
+                        let minutes = *auto_lock_minutes;
+                        self.message = Some(update_settings(|settings| settings.vault.auto_lock_minutes = minutes).map(|_| "Saved".to_string()));
+                    }
+                });
+
+                match &self.message {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    None => {}
+                }
+            });
+        self.show_window = show_window;
+    }
+
+    /// Runs the key derivation on the shared runtime so the UI does not freeze.
+    fn start(&mut self, success_message: &str, task: impl FnOnce() -> Result<(), String> + Send + 'static) {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.receiver = Some(receiver);
+        self.message = None;
+        let success_message = success_message.to_string();
+        runtime().spawn_blocking(move || {
+            let _ = sender.send(task().map(|_| success_message));
+        });
+    }
+}
+
+/// Creates a vault holding the plain-text API keys and proxy passwords from the settings,
+/// then removes the keys from the settings and `api_key.json`.
+fn create_vault_from_settings(passphrase: &str) -> Result<(), String> {
+    let settings = settings();
+    create_vault(passphrase, Credentials {
+        all_debrid_api_key: settings.debrid.all_debrid_api_key,
+        real_debrid_api_key: settings.debrid.real_debrid_api_key,
+        proxy_passwords: settings.network.proxies_with_passwords()
+            .map(|proxy| (proxy.vault_key(), proxy.password.clone()))
+            .collect(),
+        ..Default::default()
+    })?;
+    update_settings(|settings| {
+        settings.debrid.all_debrid_api_key.clear();
+        settings.debrid.real_debrid_api_key.clear();
+    })?;
+    remove_legacy_api_keys()
+}
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use crossbeam_channel::Sender;
+use eframe::egui::{Grid, Ui};
+use crate::modules::extract::verify_links;
+use crate::modules::jobs::{Job, JobKind, JobStatus, spawn_job};
+use crate::modules::links::{DownloadLink, MultiUpLink};
+
+/// Represents what the extractor found for an uploaded MultiUp link.
+#[derive(Clone, Debug)]
+pub struct LinkVerification {
+    pub valid_hosts: usize,
+    pub hosts: usize,
+    pub error: Option<String>,
+}
+
+impl LinkVerification {
+    fn new(download_link: &DownloadLink) -> Self {
+        let direct_links = download_link.direct_links.iter().flatten();
+        Self {
+            valid_hosts: direct_links.clone().filter(|direct_link| direct_link.validity == "valid").count(),
+            hosts: direct_links.count(),
+            error: match &download_link.status {
+                Some(Err(error)) => Some(error.message()),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match &self.error {
+            Some(error) => error.clone(),
+            None => format!("{} of {} hosts valid", self.valid_hosts, self.hosts),
+        }
+    }
+}
+
+/// Waits for the delay, then extracts the direct links of the uploaded links and rechecks their validity.
+///
+/// Sends the result of each download link, keyed by the link as it was uploaded.
+/// Returns the job, so a pending verification can be cancelled, or `None` if there are no links.
+pub fn verify_uploaded_links(links: Vec<String>, delay: Duration, sender: Sender<BTreeMap<String, LinkVerification>>) -> Option<Arc<Job>> {
+    if links.is_empty() {
+        return None;
+    }
+    let description = match delay.as_secs() / 60 {
+        0 => format!("Verify {} uploaded links", links.len()),
+        minutes => format!("Verify {} uploaded links after {} minutes", links.len(), minutes),
+    };
+    Some(spawn_job(JobKind::VerifyUpload, description, None, |job| async move {
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {},
+            _ = job.cancellation_token.cancelled() => return JobStatus::Cancelled
+        }
+        let multiup_links = match verify_links(&links.join("\n"), Arc::clone(&job)).await {
+            Ok(multiup_links) => multiup_links,
+            Err(error) => return JobStatus::Failed(error.message())
+        };
+        if job.cancellation_token.is_cancelled() {
+            return JobStatus::Cancelled;
+        }
+
+        let mut verifications = BTreeMap::new();
+        for multiup_link in &multiup_links {
+            match multiup_link {
+                MultiUpLink::Download(download_link) => {
+                    verifications.insert(download_link.original_link.clone(), LinkVerification::new(download_link));
+                }
+                MultiUpLink::Project(project_link) => {
+                    for download_link in project_link.download_links.iter().flatten() {
+                        verifications.insert(download_link.original_link.clone(), LinkVerification::new(download_link));
+                    }
+                }
+            }
+        }
+        let unverified = verifications.values().filter(|verification| verification.error.is_some() || verification.valid_hosts == 0).count();
+        let _ = sender.send(verifications);
+        match unverified {
+            0 => JobStatus::Completed("Every link has valid hosts".to_string()),
+            unverified => JobStatus::Failed(format!("{} links have no valid hosts", unverified))
+        }
+    }))
+}
+
+/// Displays each uploaded link with the number of valid hosts found for it.
+pub fn display_verifications(ui: &mut Ui, links: &[String], verifications: &BTreeMap<String, LinkVerification>) {
+    Grid::new("Upload Verification")
+        .striped(true)
+        .show(ui, |ui| {
+            for link in links {
+                let Some(verification) = verifications.get(link) else {
+                    continue;
+                };
+                ui.label(link);
+                if verification.error.is_some() || verification.valid_hosts == 0 {
+                    ui.colored_label(ui.visuals().error_fg_color, verification.description());
+                } else {
+                    ui.label(verification.description());
+                }
+                ui.end_row();
+            }
+        });
+}
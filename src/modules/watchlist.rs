@@ -0,0 +1,640 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use chrono::{Local, TimeZone};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use eframe::egui::{Align2, Button, CollapsingHeader, Context, DragValue, ScrollArea, TextEdit, Ui, Window};
+use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use crate::modules::api::recheck_validity_api;
+use crate::modules::extract::{MIRROR_PREFIX, resolve_links};
+use crate::modules::general::add_alerts;
+use crate::modules::jobs::{Job, job_manager, JobKind, JobRerun, JobStatus, runtime, spawn_job};
+use crate::modules::links::{DownloadLink, LinkError, MultiUpLink};
+use crate::modules::network::{self, Service};
+use crate::modules::settings::{config_path, load_config_file, settings};
+
+const WATCHLIST_FILE: &str = "watchlist.json";
+const MAX_HISTORY: usize = 100;
+
+/// Represents the result of a single validity check of a watched file.
+/// Contains the time of the check (UNIX timestamp), the validity of each host and
+/// the error returned by MultiUp, if any.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ValidityCheck {
+    pub time: i64,
+    pub hosts: BTreeMap<String, String>,
+    pub error: Option<String>,
+}
+
+impl ValidityCheck {
+    pub fn valid_hosts(&self) -> usize {
+        self.hosts.values().filter(|validity| validity.as_str() == "valid").count()
+    }
+}
+
+/// Represents a file (download link) on the watchlist along with its validity history.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WatchedFile {
+    pub link_id: String,
+    pub link: String,
+    pub file_name: Option<String>,
+    #[serde(default)]
+    pub history: Vec<ValidityCheck>,
+    #[serde(default)]
+    pub alerted: bool,
+}
+
+impl WatchedFile {
+    pub fn new(download_link: &DownloadLink) -> Self {
+        Self {
+            link_id: download_link.link_id.clone(),
+            link: download_link.original_link.clone(),
+            file_name: download_link.link_information.as_ref().and_then(|information| information.file_name.clone()),
+            history: vec![],
+            alerted: false,
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.file_name.as_deref().unwrap_or(&self.link)
+    }
+}
+
+/// Represents a watched MultiUp link, which is either a single download link or a project.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WatchedEntry {
+    pub link: String,
+    pub name: String,
+    pub is_project: bool,
+    pub files: Vec<WatchedFile>,
+}
+
+impl WatchedEntry {
+    /// Creates a watchlist entry from a processed MultiUp link.
+    ///
+    /// Returns `None` for projects whose download links could not be retrieved.
+    pub fn from_multiup_link(link: &MultiUpLink) -> Option<Self> {
+        match link {
+            MultiUpLink::Project(project) => {
+                let download_links = project.download_links.as_ref()?;
+                let mut files: Vec<WatchedFile> = download_links.iter().map(WatchedFile::new).collect();
+                files.sort_by(|file_1, file_2| file_1.display_name().cmp(file_2.display_name()));
+                Some(Self {
+                    link: project.original_link.clone(),
+                    name: project.name.clone(),
+                    is_project: true,
+                    files,
+                })
+            }
+            MultiUpLink::Download(download) => {
+                let file = WatchedFile::new(download);
+                Some(Self {
+                    link: download.original_link.clone(),
+                    name: file.display_name().to_string(),
+                    is_project: false,
+                    files: vec![file],
+                })
+            }
+        }
+    }
+}
+
+/// Represents an alert raised when a watched file decays.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WatchlistAlert {
+    pub time: i64,
+    pub link: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Watchlist {
+    pub entries: Vec<WatchedEntry>,
+    pub alerts: Vec<WatchlistAlert>,
+    pub enabled: bool,
+    pub interval_minutes: u64,
+    pub threshold: usize,
+    pub last_check: Option<i64>,
+    #[serde(skip)]
+    pub checking: bool,
+    /// Why the watchlist file could not be read, in which case it was kept as a backup.
+    #[serde(skip)]
+    pub load_error: Option<String>,
+}
+
+impl Default for Watchlist {
+    fn default() -> Self {
+        Self {
+            entries: vec![],
+            alerts: vec![],
+            enabled: true,
+            interval_minutes: 60,
+            threshold: 3,
+            last_check: None,
+            checking: false,
+            load_error: None,
+        }
+    }
+}
+
+impl Watchlist {
+    pub fn load() -> Self {
+        let (mut watchlist, load_error): (Self, _) = load_config_file(WATCHLIST_FILE);
+        watchlist.load_error = load_error;
+        watchlist
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = config_path(WATCHLIST_FILE);
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+        }
+        let json_string = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        fs::write(path, json_string).map_err(|error| error.to_string())
+    }
+
+    /// Adds an entry, merging its files into an existing entry with the same link.
+    pub fn add_entry(&mut self, entry: WatchedEntry) {
+        match self.entries.iter_mut().find(|existing| existing.link == entry.link) {
+            Some(existing) => {
+                for file in entry.files {
+                    if !existing.files.iter().any(|existing_file| existing_file.link_id == file.link_id) {
+                        existing.files.push(file);
+                    }
+                }
+            }
+            None => self.entries.push(entry),
+        }
+    }
+
+    fn is_due(&self) -> bool {
+        match self.last_check {
+            Some(last_check) => Local::now().timestamp() - last_check >= (self.interval_minutes * 60) as i64,
+            None => true,
+        }
+    }
+
+    /// Records the results of a check and returns any alerts raised.
+    fn record_checks(&mut self, checks: Vec<(String, ValidityCheck)>) -> Vec<WatchlistAlert> {
+        let mut alerts = vec![];
+        for (link_id, check) in checks {
+            for entry in self.entries.iter_mut() {
+                for file in entry.files.iter_mut().filter(|file| file.link_id == link_id) {
+                    let message = if let Some(error) = &check.error {
+                        Some(format!("{} is no longer available: {}", file.display_name(), error))
+                    } else if check.valid_hosts() < self.threshold {
+                        Some(format!("{} has {} valid hosts (threshold: {})", file.display_name(), check.valid_hosts(), self.threshold))
+                    } else {
+                        None
+                    };
+
+                    match message {
+                        Some(message) if !file.alerted => {
+                            file.alerted = true;
+                            alerts.push(WatchlistAlert {
+                                time: check.time,
+                                link: file.link.clone(),
+                                message,
+                            });
+                        }
+                        Some(_) => {}
+                        None => file.alerted = false,
+                    }
+
+                    file.history.push(check.clone());
+                    if file.history.len() > MAX_HISTORY {
+                        file.history.remove(0);
+                    }
+                }
+            }
+        }
+        self.alerts.extend(alerts.iter().cloned());
+        alerts
+    }
+}
+
+/// Checks the validity of every watched file using the MultiUp check-file API.
+///
+/// Files which could not be checked, e.g. because MultiUp could not be reached, are left out,
+/// as only an error answered by the API means a file is gone.
+/// Fails without checking any file if no HTTP client can be created from the network settings.
+async fn check_files(files: Vec<(String, String)>, job: Arc<Job>) -> Result<Vec<(String, ValidityCheck)>, String> {
+    let semaphore = Arc::new(Semaphore::new(settings().watchlist.check_concurrency.max(1)));
+    let client = network::client(Service::MultiUp)?;
+    let mut tasks = vec![];
+    job.set_total(files.len());
+    for (link_id, link) in files {
+        let semaphore = Arc::clone(&semaphore);
+        let client = client.clone();
+        let job = Arc::clone(&job);
+        let cancellation_token = job.cancellation_token.child_token();
+        let task = tokio::spawn(async move {
+            let _permit = semaphore.acquire().await.unwrap();
+            let mut download_link = DownloadLink::new(link, link_id.clone());
+            download_link.direct_links = Some(BTreeSet::new());
+            let mirror_link = MIRROR_PREFIX.to_owned() + &link_id;
+            let download_link = recheck_validity_api(mirror_link, download_link, cancellation_token, client).await;
+
+            let mut check = ValidityCheck {
+                time: Local::now().timestamp(),
+                hosts: BTreeMap::new(),
+                error: None,
+            };
+            match download_link.status {
+                Some(Ok(())) => {
+                    for direct_link in download_link.direct_links.unwrap_or_default() {
+                        check.hosts.insert(direct_link.host, direct_link.validity);
+                    }
+                    if check.hosts.is_empty() {
+                        check.error = Some("No hosts".to_string());
+                    }
+                }
+                Some(Err(LinkError::APIError(error))) => check.error = Some(error),
+                Some(Err(_)) | None => {
+                    job.advance();
+                    return None;
+                }
+            }
+            job.advance();
+            Some((link_id, check))
+        });
+        tasks.push(task);
+    }
+
+    Ok(futures::future::join_all(tasks).await.into_iter().flatten().flatten().collect())
+}
+
+fn run_check(watchlist: &Arc<Mutex<Watchlist>>, alert_sender: &Sender<WatchlistAlert>) {
+    let files: Vec<(String, String)> = {
+        let mut watchlist = watchlist.lock().unwrap();
+        watchlist.checking = true;
+        watchlist.entries.iter()
+            .flat_map(|entry| entry.files.iter().map(|file| (file.link_id.clone(), file.link.clone())))
+            .collect()
+    };
+
+    let total = files.len();
+    let job = job_manager().start_job(JobKind::WatchlistCheck, format!("Check {} watched files", total), Some(JobRerun::WatchlistCheck));
+    let checks = match runtime().block_on(check_files(files, Arc::clone(&job))) {
+        Ok(checks) => checks,
+        Err(error) => {
+            job.finish(JobStatus::Failed(error));
+            watchlist.lock().unwrap().checking = false;
+            return;
+        }
+    };
+    // Results of a cancelled check are incomplete, so they are not recorded in the history
+    if job.cancellation_token.is_cancelled() {
+        job.finish(JobStatus::Cancelled);
+        watchlist.lock().unwrap().checking = false;
+        return;
+    }
+
+    let mut watchlist = watchlist.lock().unwrap();
+    let unchecked = total - checks.len();
+    let alerts = watchlist.record_checks(checks);
+    job.finish(match unchecked {
+        0 => JobStatus::Completed(format!("{} alerts", alerts.len())),
+        unchecked => JobStatus::Failed(format!("{} alerts, {} files could not be checked", alerts.len(), unchecked)),
+    });
+    watchlist.last_check = Some(Local::now().timestamp());
+    watchlist.checking = false;
+    let _ = watchlist.save();
+    for alert in alerts {
+        let _ = alert_sender.send(alert);
+    }
+}
+
+/// Starts the background thread which re-checks the watchlist on a schedule.
+///
+/// The thread runs independently of the UI, so checks continue while the window is minimised.
+/// Sending on `check_now` forces a check regardless of the schedule.
+fn start_scheduler(watchlist: Arc<Mutex<Watchlist>>, alert_sender: Sender<WatchlistAlert>, check_now: Receiver<()>) {
+    thread::spawn(move || {
+        loop {
+            let forced = match check_now.recv_timeout(Duration::from_secs(30)) {
+                Ok(_) => true,
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            let due = {
+                let watchlist = watchlist.lock().unwrap();
+                watchlist.enabled && !watchlist.entries.is_empty() && watchlist.is_due()
+            };
+
+            if forced || due {
+                run_check(&watchlist, &alert_sender);
+            }
+        }
+    });
+}
+
+#[derive(Default)]
+struct Channels {
+    alerts: Option<Receiver<WatchlistAlert>>,
+    check_now: Option<Sender<()>>,
+    resolved_links: Option<Receiver<Result<Vec<MultiUpLink>, LinkError>>>,
+}
+
+pub struct WatchlistUI {
+    watchlist: Arc<Mutex<Watchlist>>,
+    channels: Channels,
+    toasts: Toasts,
+    input_links: String,
+    resolving: bool,
+    pub show_alerts: bool,
+    unread_alerts: usize,
+}
+
+impl Default for WatchlistUI {
+    fn default() -> Self {
+        Self {
+            watchlist: Arc::new(Mutex::new(Watchlist::load())),
+            channels: Channels::default(),
+            toasts: Toasts::default(),
+            input_links: String::new(),
+            resolving: false,
+            show_alerts: false,
+            unread_alerts: 0,
+        }
+    }
+}
+
+impl WatchlistUI {
+    pub fn display(ctx: &Context, ui: &mut Ui, watchlist_ui: &mut WatchlistUI, alerts: Vec<String>) {
+        watchlist_ui.toasts = Toasts::new()
+            .anchor(Align2::RIGHT_TOP, (10.0, 10.0))
+            .direction(eframe::egui::Direction::TopDown);
+        add_alerts(&mut watchlist_ui.toasts, alerts);
+
+        watchlist_ui.display_input_area(ui);
+        watchlist_ui.display_schedule_settings(ui);
+        watchlist_ui.display_watched_links(ui);
+
+        watchlist_ui.toasts.show(ctx);
+    }
+
+    /// Adds processed MultiUp links (e.g. from the Extract tab) to the watchlist.
+    pub fn add_links(&mut self, links: Vec<WatchedEntry>) {
+        if links.is_empty() {
+            return;
+        }
+        let mut watchlist = self.watchlist.lock().unwrap();
+        for entry in links {
+            watchlist.add_entry(entry);
+        }
+        let _ = watchlist.save();
+    }
+
+    /// Asks the scheduler to check the watchlist now, regardless of the schedule.
+    pub fn check_now(&self) {
+        if let Some(sender) = &self.channels.check_now {
+            let _ = sender.send(());
+        }
+    }
+
+    pub fn unread_alerts(&self) -> usize {
+        self.unread_alerts
+    }
+
+    /// Receives alerts from the scheduler and shows the alerts window.
+    ///
+    /// Returns the new alerts, which the selected tab shows as toasts.
+    /// Also starts the scheduler the first time it is called.
+    pub fn display_alerts(&mut self, ctx: &Context) -> Vec<String> {
+        if self.channels.check_now.is_none() {
+            let (alert_sender, alert_receiver) = crossbeam_channel::unbounded();
+            let (check_now_sender, check_now_receiver) = crossbeam_channel::unbounded();
+            self.channels.alerts = Some(alert_receiver);
+            self.channels.check_now = Some(check_now_sender);
+            start_scheduler(Arc::clone(&self.watchlist), alert_sender, check_now_receiver);
+        }
+
+        let mut alerts = vec![];
+        if let Some(receiver) = &self.channels.alerts {
+            while let Ok(alert) = receiver.try_recv() {
+                self.unread_alerts += 1;
+                alerts.push(format!("Watchlist: {}", alert.message));
+            }
+        }
+        // Keep polling the scheduler while the app is idle
+        ctx.request_repaint_after(Duration::from_secs(5));
+
+        let mut show_alerts = self.show_alerts;
+        Window::new("Watchlist Alerts")
+            .default_width(400.0)
+            .open(&mut show_alerts)
+            .show(ctx, |ui| {
+                self.unread_alerts = 0;
+                let mut watchlist = self.watchlist.lock().unwrap();
+                if ui.button("Clear alerts").clicked() {
+                    watchlist.alerts.clear();
+                    let _ = watchlist.save();
+                }
+                ScrollArea::vertical()
+                    .id_source("Watchlist Alerts")
+                    .min_scrolled_height(ui.available_height())
+                    .show(ui, |ui| {
+                        for alert in watchlist.alerts.iter().rev() {
+                            ui.label(format!("[{}] {}\n{}", format_time(alert.time), alert.message, alert.link));
+                            ui.separator();
+                        }
+                    });
+            });
+        self.show_alerts = show_alerts;
+        alerts
+    }
+
+    fn display_input_area(&mut self, ui: &mut Ui) {
+        ui.heading("Watch Links");
+        ScrollArea::both()
+            .id_source("Watchlist Input Area")
+            .max_height(ui.available_height() / 6.0)
+            .show(ui, |ui| {
+                ui.add(
+                    TextEdit::multiline(&mut self.input_links)
+                        .hint_text("Paste the MultiUp links and projects you want to watch here")
+                        .desired_width(ui.available_width()),
+                );
+            });
+
+        if let Some(receiver) = &self.channels.resolved_links {
+            match receiver.try_recv() {
+                Ok(Ok(links)) => {
+                    let entries: Vec<WatchedEntry> = links.iter().filter_map(WatchedEntry::from_multiup_link).collect();
+                    let failed = links.len() - entries.len();
+                    self.add_links(entries);
+                    self.resolving = false;
+                    self.input_links.clear();
+                    if failed > 0 {
+                        self.toasts.add(Toast {
+                            text: format!("Failed to add {} links", failed).into(),
+                            kind: ToastKind::Error,
+                            options: ToastOptions::default()
+                                .duration_in_seconds(5.0)
+                                .show_progress(true)
+                                .show_icon(true)
+                        });
+                    }
+                }
+                Ok(Err(error)) => {
+                    self.resolving = false;
+                    self.toasts.add(Toast {
+                        text: error.message().into(),
+                        kind: ToastKind::Error,
+                        options: ToastOptions::default()
+                            .duration_in_seconds(10.0)
+                            .show_progress(true)
+                            .show_icon(true)
+                    });
+                }
+                Err(_) => {}
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!self.resolving, Button::new("Add to watchlist")).clicked() {
+                self.resolving = true;
+                let (links_sender, links_receiver) = crossbeam_channel::unbounded();
+                self.channels.resolved_links = Some(links_receiver);
+                let input_links = self.input_links.clone();
+                let description = format!("Resolve links for the watchlist ({} lines)", input_links.lines().count());
+                spawn_job(JobKind::ResolveLinks, description, None, |_| async move {
+                    let links = resolve_links(&input_links).await;
+                    let status = match &links {
+                        Ok(links) => JobStatus::Completed(format!("{} links resolved", links.len())),
+                        Err(error) => JobStatus::Failed(error.message())
+                    };
+                    let _ = links_sender.send(links);
+                    status
+                });
+            }
+
+            if self.resolving {
+                ui.spinner();
+                ui.label("Adding links...");
+            }
+        });
+    }
+
+    fn display_schedule_settings(&mut self, ui: &mut Ui) {
+        let mut watchlist = self.watchlist.lock().unwrap();
+        ui.horizontal(|ui| {
+            let mut changed = ui.checkbox(&mut watchlist.enabled, "Check automatically every").changed();
+            changed |= ui.add(DragValue::new(&mut watchlist.interval_minutes).clamp_range(5..=10080).suffix(" min")).changed();
+            ui.label("| Alert below");
+            changed |= ui.add(DragValue::new(&mut watchlist.threshold).clamp_range(1..=100)).changed();
+            ui.label("valid hosts");
+            if changed {
+                let _ = watchlist.save();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!watchlist.checking, Button::new("Check now")).clicked() {
+                if let Some(sender) = &self.channels.check_now {
+                    let _ = sender.send(());
+                }
+            }
+
+            if watchlist.checking {
+                ui.spinner();
+                ui.label("Checking links...");
+            } else if let Some(last_check) = watchlist.last_check {
+                ui.label(format!("Last checked: {}", format_time(last_check)));
+            }
+
+            if ui.button(format!("Alerts ({})", watchlist.alerts.len())).clicked() {
+                self.show_alerts = true;
+            }
+        });
+    }
+
+    fn display_watched_links(&mut self, ui: &mut Ui) {
+        ui.heading("Watchlist");
+        let mut watchlist = self.watchlist.lock().unwrap();
+        if let Some(error) = &watchlist.load_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        let threshold = watchlist.threshold;
+        let mut removed_entry = None;
+        ScrollArea::vertical()
+            .id_source("Watched Links")
+            .min_scrolled_height(ui.available_height())
+            .show(ui, |ui| {
+                for (index, entry) in watchlist.entries.iter().enumerate() {
+                    let decayed_files = entry.files.iter().filter(|file| file.alerted).count();
+                    let header = if entry.is_project {
+                        format!("{} ({} files, {} below threshold)", entry.name, entry.files.len(), decayed_files)
+                    } else {
+                        entry.name.clone()
+                    };
+
+                    let response = CollapsingHeader::new(header)
+                        .id_source(&entry.link)
+                        .show(ui, |ui| {
+                            for file in &entry.files {
+                                let status = match file.history.last() {
+                                    Some(check) => match &check.error {
+                                        Some(error) => format!("unavailable ({})", error),
+                                        None => format!("{}/{} valid hosts", check.valid_hosts(), check.hosts.len()),
+                                    },
+                                    None => "not checked yet".to_string(),
+                                };
+                                let warning = if file.alerted { " ⚠" } else { "" };
+
+                                CollapsingHeader::new(format!("{} - {}{}", file.display_name(), status, warning))
+                                    .id_source(format!("{} {}", entry.link, file.link_id))
+                                    .show(ui, |ui| {
+                                        ui.label(&file.link);
+                                        if let Some(check) = file.history.last() {
+                                            for (host, validity) in &check.hosts {
+                                                ui.label(format!("    {}: {}", host, validity));
+                                            }
+                                        }
+
+                                        CollapsingHeader::new("History")
+                                            .id_source(format!("{} {} History", entry.link, file.link_id))
+                                            .show(ui, |ui| {
+                                                for check in file.history.iter().rev() {
+                                                    let result = match &check.error {
+                                                        Some(error) => error.clone(),
+                                                        None => {
+                                                            let below = if check.valid_hosts() < threshold { " (below threshold)" } else { "" };
+                                                            format!("{}/{} valid{}", check.valid_hosts(), check.hosts.len(), below)
+                                                        }
+                                                    };
+                                                    ui.label(format!("{} - {}", format_time(check.time), result));
+                                                }
+                                            });
+                                    });
+                            }
+                        });
+
+                    response.header_response.context_menu(|ui| {
+                        if ui.button("Remove from watchlist").clicked() {
+                            removed_entry = Some(index);
+                            ui.close_menu();
+                        }
+                    });
+                }
+            });
+
+        if let Some(index) = removed_entry {
+            watchlist.entries.remove(index);
+            let _ = watchlist.save();
+        }
+    }
+}
+
+fn format_time(timestamp: i64) -> String {
+    match Local.timestamp_opt(timestamp, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => timestamp.to_string(),
+    }
+}